@@ -0,0 +1,285 @@
+//! Offline analysis of the music, which suggests bpm and the timing of the first beat
+use bidrum_data_struct_lib::song::parse_bpm;
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use num_rational::Rational64;
+
+/// Length of a frame of the onset envelope in millisecond
+pub(crate) const FRAME_LENGTH: f64 = 10.0;
+
+/// Cutoff frequency of the low band, e.g. kick drum and bass
+const LOW_BAND_CUTOFF: f32 = 200.0;
+
+/// Cutoff frequency of the high band, e.g. snare drum and hi-hat
+const HIGH_BAND_CUTOFF: f32 = 2000.0;
+
+/// Onset strength of the music in every frame
+pub(crate) struct OnsetEnvelope {
+    /// onset strength of the low band
+    pub(crate) low: Vec<f32>,
+    /// onset strength of the high band
+    pub(crate) high: Vec<f32>,
+}
+
+impl OnsetEnvelope {
+    /// Decodes the music and calculates the onset envelope
+    pub(crate) fn from_file(path: &str) -> Result<OnsetEnvelope, String> {
+        let music = StaticSoundData::from_file(path, StaticSoundSettings::default())
+            .map_err(|err| format!("Failed to load music {}: {}", path, err))?;
+        let samples: Vec<f32> = music
+            .frames
+            .iter()
+            .map(|x| (x.left + x.right) / 2.0)
+            .collect();
+
+        Ok(OnsetEnvelope::from_samples(&samples, music.sample_rate))
+    }
+
+    /// Calculates the onset envelope from the mono samples
+    pub(crate) fn from_samples(samples: &[f32], sample_rate: u32) -> OnsetEnvelope {
+        let frame_size = (sample_rate as f64 * FRAME_LENGTH / 1000.0) as usize;
+        let low_alpha = one_pole_alpha(LOW_BAND_CUTOFF, sample_rate);
+        let high_alpha = one_pole_alpha(HIGH_BAND_CUTOFF, sample_rate);
+
+        let mut low_energies = vec![];
+        let mut high_energies = vec![];
+        let mut low_pass = 0.0;
+        let mut high_band_low_pass = 0.0;
+        for frame in samples.chunks(frame_size) {
+            let mut low_energy = 0.0;
+            let mut high_energy = 0.0;
+            for sample in frame {
+                low_pass += low_alpha * (sample - low_pass);
+                high_band_low_pass += high_alpha * (sample - high_band_low_pass);
+                let high_pass = sample - high_band_low_pass;
+                low_energy += low_pass * low_pass;
+                high_energy += high_pass * high_pass;
+            }
+            low_energies.push(low_energy / frame.len() as f32);
+            high_energies.push(high_energy / frame.len() as f32);
+        }
+
+        OnsetEnvelope {
+            low: onset_strength(&low_energies),
+            high: onset_strength(&high_energies),
+        }
+    }
+
+    /// Onset strength of both bands
+    pub(crate) fn combined(&self) -> Vec<f32> {
+        self.low
+            .iter()
+            .zip(&self.high)
+            .map(|(low, high)| low + high)
+            .collect()
+    }
+}
+
+fn one_pole_alpha(cutoff: f32, sample_rate: u32) -> f32 {
+    1.0 - (-2.0 * std::f32::consts::PI * cutoff / sample_rate as f32).exp()
+}
+
+/// Increase of the log energy, subtracted by its local average
+fn onset_strength(energies: &[f32]) -> Vec<f32> {
+    let log_energies: Vec<f32> = energies.iter().map(|x| (1.0 + 1000.0 * x).ln()).collect();
+    let flux: Vec<f32> = (0..log_energies.len())
+        .map(|i| {
+            if i == 0 {
+                0.0
+            } else {
+                (log_energies[i] - log_energies[i - 1]).max(0.0)
+            }
+        })
+        .collect();
+
+    // local average of 0.5 sec
+    let radius = 25;
+    (0..flux.len())
+        .map(|i| {
+            let window = &flux[i.saturating_sub(radius)..(i + radius + 1).min(flux.len())];
+            let average = window.iter().sum::<f32>() / window.len() as f32;
+            (flux[i] - average).max(0.0)
+        })
+        .collect()
+}
+
+/// Onset strength at the fractional frame, linearly interpolated
+fn strength_at(envelope: &[f32], frame: f64) -> f32 {
+    let index = frame.floor() as usize;
+    if index + 1 >= envelope.len() {
+        return 0.0;
+    }
+
+    let ratio = (frame - index as f64) as f32;
+    envelope[index] * (1.0 - ratio) + envelope[index + 1] * ratio
+}
+
+/// Sum of the onset strength at the beats of the period from the phase, in unit of frame
+fn beat_strength(envelope: &[f32], period: f64, phase: f64) -> f32 {
+    let mut strength = 0.0;
+    let mut frame = phase;
+    while frame < envelope.len() as f64 {
+        strength += strength_at(envelope, frame);
+        frame += period;
+    }
+
+    strength
+}
+
+/// Best phase of the beats and its strength, in unit of frame
+fn best_phase(envelope: &[f32], period: f64, step: f64, around: Option<f64>) -> (f64, f32) {
+    let (from, to) = match around {
+        Some(phase) => (phase - 1.0, phase + 1.0),
+        None => (0.0, period),
+    };
+
+    let mut best = (0.0, f32::MIN);
+    let mut phase = from;
+    while phase < to {
+        let strength = beat_strength(envelope, period, phase.rem_euclid(period));
+        if strength > best.1 {
+            best = (phase.rem_euclid(period), strength);
+        }
+        phase += step;
+    }
+
+    best
+}
+
+/// Suggested tempo of the music
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TempoSuggestion {
+    pub(crate) bpm: Rational64,
+    /// timing of the first beat in millisecond
+    pub(crate) delay: u64,
+}
+
+/// Estimates the bpm between the range and the timing of the first beat
+pub(crate) fn suggest_tempo(
+    envelope: &OnsetEnvelope,
+    min_bpm: f64,
+    max_bpm: f64,
+) -> Option<TempoSuggestion> {
+    let combined = envelope.combined();
+    let frames_per_minute = 60000.0 / FRAME_LENGTH;
+    let min_lag = (frames_per_minute / max_bpm).floor() as usize;
+    let max_lag = (frames_per_minute / min_bpm).ceil() as usize;
+    if min_lag == 0 || combined.len() <= max_lag * 4 {
+        return None;
+    }
+
+    // autocorrelation of the envelope, which prefers the tempo around 120 bpm
+    let mut best_lag = None;
+    for lag in min_lag..=max_lag {
+        let correlation: f32 = (0..combined.len() - lag)
+            .map(|i| combined[i] * combined[i + lag])
+            .sum();
+        let bpm = frames_per_minute / lag as f64;
+        let weight = (-0.5 * (bpm / 120.0).log2().powi(2)).exp() as f32;
+        let score = correlation * weight;
+        let is_better = match best_lag {
+            Some((_, best_score)) => score > best_score,
+            None => true,
+        };
+        if is_better {
+            best_lag = Some((lag, score));
+        }
+    }
+    let (lag, _) = best_lag?;
+
+    // refine the bpm by 0.01 between the neighbor lags, with the strength of the beats
+    let from_bpm = (frames_per_minute / (lag as f64 + 1.0)).max(min_bpm);
+    let to_bpm = (frames_per_minute / (lag as f64 - 1.0).max(1.0)).min(max_bpm);
+    let mut best = (from_bpm, 0.0, f32::MIN);
+    let mut bpm = from_bpm;
+    while bpm <= to_bpm {
+        let period = frames_per_minute / bpm;
+        let (phase, strength) = best_phase(&combined, period, 1.0, None);
+        // longer period has fewer beats to sum
+        let strength = strength * period as f32;
+        if strength > best.2 {
+            best = (bpm, phase, strength);
+        }
+        bpm += 0.01;
+    }
+
+    // the bpm close to integer is probably the integer
+    let (bpm, phase, _) = best;
+    let bpm = if (bpm - bpm.round()).abs() < 0.05 {
+        bpm.round()
+    } else {
+        (bpm * 100.0).round() / 100.0
+    };
+    let period = frames_per_minute / bpm;
+    let (phase, _) = best_phase(&combined, period, 0.1, Some(phase));
+
+    Some(TempoSuggestion {
+        bpm: parse_bpm(&format!("{}", bpm)).ok()?,
+        delay: (phase * FRAME_LENGTH).round() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::{suggest_tempo, OnsetEnvelope, FRAME_LENGTH};
+
+    /// Envelope of the clicks at the beats, which are spread to the neighbor frames
+    /// in proportion to the position between them
+    fn click_track(bpm: f64, delay_in_ms: f64, length_in_ms: f64) -> OnsetEnvelope {
+        let mut low = vec![0.0; (length_in_ms / FRAME_LENGTH) as usize];
+        let mut timing = delay_in_ms;
+        while timing < length_in_ms {
+            let frame = timing / FRAME_LENGTH;
+            let index = frame.floor() as usize;
+            let ratio = (frame - index as f64) as f32;
+            if index + 1 < low.len() {
+                low[index] += 1.0 - ratio;
+                low[index + 1] += ratio;
+            }
+            timing += 60000.0 / bpm;
+        }
+
+        OnsetEnvelope {
+            high: vec![0.0; low.len()],
+            low: low,
+        }
+    }
+
+    fn to_f64(value: Rational64) -> f64 {
+        *value.numer() as f64 / *value.denom() as f64
+    }
+
+    #[test]
+    fn tempo_of_click_track_is_suggested() {
+        // the half of the fast tempo is preferred unless the range excludes it
+        for (bpm, delay, min_bpm, max_bpm) in [
+            (128.0, 230.0, 80.0, 200.0),
+            (97.5, 415.0, 80.0, 200.0),
+            (174.0, 40.0, 140.0, 200.0),
+        ] {
+            let envelope = click_track(bpm, delay, 60000.0);
+            let suggestion = suggest_tempo(&envelope, min_bpm, max_bpm)
+                .unwrap_or_else(|| panic!("No tempo suggested for {} bpm", bpm));
+
+            assert!(
+                (to_f64(suggestion.bpm) - bpm).abs() < 0.02,
+                "{} bpm is suggested for {} bpm",
+                suggestion.bpm,
+                bpm
+            );
+            assert!(
+                (suggestion.delay as f64 - delay).abs() <= 5.0,
+                "{} ms is suggested for {} ms",
+                suggestion.delay,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn too_short_music_has_no_suggestion() {
+        let envelope = click_track(120.0, 0.0, 2000.0);
+        assert!(suggest_tempo(&envelope, 80.0, 200.0).is_none());
+    }
+}
@@ -1,90 +1,230 @@
-use std::{fs, thread::sleep, time::Duration};
+use std::{
+    io::{stdout, Write},
+    path::Path,
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
-use device_query::{DeviceQuery, DeviceState, Keycode};
+use bidrum_controller_lib::{janggu_event::JangguEdge, JangguDevice};
+use bidrum_data_struct_lib::calibration::CalibrationProfile;
 use kira::{
     clock::ClockSpeed,
     manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
-    sound::static_sound::{StaticSoundData, StaticSoundSettings},
-    tween::Tween,
+    sound::static_sound::StaticSoundSettings,
 };
-use rand::Rng;
 
-pub fn beep_boop() {
-    println!("Beep-boop input measurement program");
-    println!("");
-    println!("Press d or f or j or k key when you hear beep");
-    println!("initializing kira backend");
+use crate::metronome::{Click, Metronome};
 
-    let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
-        .expect("Failed to init kira backend");
+/// Interval between the cues in millisecond (100 bpm)
+const CUE_INTERVAL: u64 = 600;
+
+/// Cues before the measurement, to get into the rhythm
+const WARM_UP_CUES: u64 = 4;
+
+/// Cues whose hits are measured
+const MEASURED_CUES: u64 = 16;
+
+/// Time to initialize before the first cue, in millisecond
+const LEAD_IN: u64 = 1000;
+
+/// How long the screen flashes, in millisecond
+const FLASH_LENGTH: u64 = 100;
+
+/// Hits farther than this from the cue are not counted, in millisecond
+const MAX_OFFSET: i64 = 250;
+
+/// Hits farther than this many times of the median absolute deviation from the median are rejected
+const OUTLIER_THRESHOLD: f64 = 3.0;
+
+/// What the player hits along with
+#[derive(Debug, Clone, Copy)]
+enum Cue {
+    /// click sound
+    Sound,
+    /// flash on the terminal screen, without the sound
+    Flash,
+}
 
-    println!("loading beep sound file");
-    let beep_sound_filenames: Vec<String> = fs::read_dir("beep_sounds")
-        .expect("ERROR: No beep_sounds directory found!")
-        .filter(|i| i.as_ref().is_ok_and(|x| x.path().is_file()))
-        .map(|i| i.unwrap().path().to_str().unwrap().to_string())
-        .collect();
+/// Turns the reverse video of the terminal on or off, which flashes the whole screen
+fn set_flash(on: bool) {
+    print!("{}", if on { "\x1b[?5h" } else { "\x1b[?5l" });
+    stdout().flush().expect("Failed to flush stdout");
+}
 
-    if beep_sound_filenames.len() == 0 {
-        panic!("No beep sound file found!");
+/// Plays the cues and measures the offsets of the hits from them, in millisecond
+///
+/// Each cue has at most one hit, which is the first hit close enough to it.
+fn measure(
+    cue: Cue,
+    device: &dyn JangguDevice,
+    manager: &mut AudioManager,
+    metronome: &Metronome,
+) -> Vec<i64> {
+    let clock = manager
+        .add_clock(ClockSpeed::TicksPerSecond(1000.0))
+        .expect("Failed to initialize clock");
+    let cue_count = WARM_UP_CUES + MEASURED_CUES;
+    let cue_tick = |idx: u64| LEAD_IN + idx * CUE_INTERVAL;
+    if let Cue::Sound = cue {
+        for idx in 0..cue_count {
+            let click = if idx < WARM_UP_CUES {
+                Click::Beat
+            } else {
+                Click::Accent
+            };
+            let settings = StaticSoundSettings::new().start_time(clock.time() + cue_tick(idx));
+            manager
+                .play(metronome.sound(click).with_settings(settings))
+                .expect("Failed to play beep sound");
+        }
     }
 
-    let mut delays = vec![];
-    for i in 0..10 {
-        let beep_index = rand::thread_rng().gen_range(0..beep_sound_filenames.len());
-        let start_time = rand::thread_rng().gen_range(1000..5000);
-        let clock = manager
-            .add_clock(ClockSpeed::TicksPerSecond(1000.0))
-            .expect("Failed to initialize clock");
-        let sound_settings = StaticSoundSettings::new().start_time(clock.time() + start_time);
-        let sound =
-            StaticSoundData::from_file(beep_sound_filenames[beep_index].clone(), sound_settings)
-                .expect("Failed to load sound");
-
-        let mut sound_handle = manager.play(sound).expect("Failed to play beep sound");
-        println!("{}th beep", i + 1);
-        clock.start().expect("Failed to start clock");
-
-        while clock.time().ticks < 10000 {
-            let tick = clock.time().ticks;
-            let pressed = DeviceState::new()
-                .get_keys()
-                .iter()
-                .find(|x| {
-                    matches!(x, Keycode::D)
-                        || matches!(x, Keycode::F)
-                        || matches!(x, Keycode::J)
-                        || matches!(x, Keycode::K)
-                })
-                .is_some();
-
-            if pressed && tick >= start_time {
-                delays.push(tick - start_time);
-                sound_handle
-                    .stop(Tween {
-                        duration: Duration::ZERO,
-                        easing: kira::tween::Easing::Linear,
-                        start_time: kira::StartTime::Immediate,
-                    })
-                    .expect("Failed to stop sound");
-                break;
+    let mut offsets: Vec<Option<i64>> = vec![None; MEASURED_CUES as usize];
+    let mut is_flashing = false;
+    // hits before the measurement are not counted
+    device.read_janggu_events();
+    clock.start().expect("Failed to start clock");
+    loop {
+        let tick = clock.time().ticks;
+        let now = Instant::now();
+        if tick > cue_tick(cue_count) {
+            break;
+        }
+
+        if let Cue::Flash = cue {
+            let since_cue = (tick as i64 - LEAD_IN as i64).rem_euclid(CUE_INTERVAL as i64);
+            let should_flash =
+                tick >= LEAD_IN && tick < cue_tick(cue_count) && since_cue < FLASH_LENGTH as i64;
+            if should_flash != is_flashing {
+                set_flash(should_flash);
+                is_flashing = should_flash;
             }
         }
 
-        clock.stop().expect("Failed to stop clock");
-        sleep(Duration::from_millis(800));
+        for event in device.read_janggu_events() {
+            if event.edge != JangguEdge::Down {
+                continue;
+            }
+
+            // the tick when the controller sent the hit
+            let hit_tick = tick as i64 - now.duration_since(event.timestamp).as_millis() as i64;
+
+            // the hit belongs to the closest cue
+            let idx = ((hit_tick - LEAD_IN as i64) as f64 / CUE_INTERVAL as f64).round();
+            if idx < WARM_UP_CUES as f64 || idx >= cue_count as f64 {
+                continue;
+            }
+            let idx = idx as u64;
+            let offset = hit_tick - cue_tick(idx) as i64;
+            let measured = &mut offsets[(idx - WARM_UP_CUES) as usize];
+            if offset.abs() <= MAX_OFFSET && measured.is_none() {
+                *measured = Some(offset);
+                println!("{:+} ms", offset);
+            }
+        }
+        sleep(Duration::from_millis(1));
     }
 
-    for i in &delays {
-        println!("delay measured: {}", i);
+    if is_flashing {
+        set_flash(false);
     }
+    clock.stop().expect("Failed to stop clock");
+
+    offsets.into_iter().flatten().collect()
+}
 
-    if &delays.len() > &0 {
-        println!("delay min: {}", delays.iter().min().unwrap());
-        println!("delay max: {}", delays.iter().max().unwrap());
-        println!(
-            "delay avg: {}",
-            (delays.iter().sum::<u64>() / delays.iter().len() as u64)
-        );
+fn median(sorted: &[f64]) -> f64 {
+    // two middle values are same if the length is odd
+    (sorted[(sorted.len() - 1) / 2] + sorted[sorted.len() / 2]) / 2.0
+}
+
+/// Removes the offsets too far from the others, with the median absolute deviation
+fn reject_outliers(offsets: &[i64]) -> Vec<i64> {
+    if offsets.len() < 3 {
+        return offsets.to_vec();
+    }
+
+    let mut sorted: Vec<f64> = offsets.iter().map(|x| *x as f64).collect();
+    sorted.sort_by(f64::total_cmp);
+    let center = median(&sorted);
+    let mut deviations: Vec<f64> = sorted.iter().map(|x| (x - center).abs()).collect();
+    deviations.sort_by(f64::total_cmp);
+    // at least 1ms, not to reject everything when most of the hits are at the same timing
+    let deviation = median(&deviations).max(1.0);
+
+    offsets
+        .iter()
+        .copied()
+        .filter(|x| (*x as f64 - center).abs() <= deviation * OUTLIER_THRESHOLD)
+        .collect()
+}
+
+/// Prints the measurement and returns the average offset
+fn summarize(name: &str, offsets: &[i64]) -> Option<i64> {
+    let accepted = reject_outliers(offsets);
+    println!(
+        "{}: {} hits measured, {} rejected as outliers",
+        name,
+        offsets.len(),
+        offsets.len() - accepted.len()
+    );
+    if accepted.is_empty() {
+        println!("{}: no hits, the offset is not measured", name);
+        return None;
     }
+
+    let average = (accepted.iter().sum::<i64>() as f64 / accepted.len() as f64).round() as i64;
+    println!(
+        "{}: min {} ms, max {} ms, avg {} ms",
+        name,
+        accepted.iter().min().unwrap(),
+        accepted.iter().max().unwrap(),
+        average
+    );
+
+    Some(average)
+}
+
+/// Measures the audio and visual offsets with the janggu and saves them into the calibration profile
+pub fn beep_boop(device: &dyn JangguDevice, profile_path: &Path) {
+    println!("Beep-boop input measurement program");
+    println!("");
+    println!("Hit the janggu along with the cues");
+    println!(
+        "The first {} cues are for getting into the rhythm",
+        WARM_UP_CUES
+    );
+    println!("initializing kira backend");
+
+    let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+        .expect("Failed to init kira backend");
+    let metronome = Metronome::new();
+
+    let mut profile = CalibrationProfile::load_or_default(profile_path)
+        .expect("Failed to load the calibration profile");
+
+    println!("");
+    println!("1. Hit along with the beeps");
+    let audio_offsets = measure(Cue::Sound, device, &mut manager, &metronome);
+    if let Some(offset) = summarize("audio", &audio_offsets) {
+        profile.audio_offset = offset;
+    }
+    sleep(Duration::from_millis(800));
+
+    println!("");
+    println!("2. Hit along with the flashes of the screen");
+    let visual_offsets = measure(Cue::Flash, device, &mut manager, &metronome);
+    if let Some(offset) = summarize("visual", &visual_offsets) {
+        profile.visual_offset = offset;
+    }
+
+    profile
+        .save(profile_path)
+        .expect("Failed to save the calibration profile");
+    println!(
+        "Calibration profile is saved to {} (audio {} ms, visual {} ms)",
+        profile_path.display(),
+        profile.audio_offset,
+        profile.visual_offset
+    );
 }
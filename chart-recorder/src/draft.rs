@@ -0,0 +1,257 @@
+//! Draft of the chart generated from the onsets of the music
+use std::{collections::BTreeSet, ops::Bound};
+
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::TempoMap,
+};
+use num_rational::Rational64;
+
+use crate::{
+    analysis::{OnsetEnvelope, FRAME_LENGTH},
+    event_list::{NoteEvent, NoteEventKind},
+    quantize::splits_per_beat,
+};
+
+/// Onsets weaker than the average onset of the band are not drafted
+const MIN_STRENGTH: f32 = 1.0;
+
+/// How many notes are drafted for the level
+struct Density {
+    /// minimum gap between the notes in unit of beat
+    min_gap: Rational64,
+    /// maximum number of the notes per beat on average
+    notes_per_beat: f64,
+}
+
+fn density_of(level: u32) -> Density {
+    let (min_gap, notes_per_beat) = match level {
+        0..=2 => (Rational64::from_integer(1), 0.5),
+        3..=4 => (Rational64::new(1, 2), 1.0),
+        5..=6 => (Rational64::new(1, 2), 1.5),
+        7..=8 => (Rational64::new(1, 4), 2.0),
+        _ => (Rational64::new(1, 4), 3.0),
+    };
+
+    Density {
+        min_gap: min_gap,
+        notes_per_beat: notes_per_beat,
+    }
+}
+
+/// Onset strength divided by the average of the onsets, to compare the bands
+fn normalize(envelope: &[f32]) -> Vec<f32> {
+    let onsets: Vec<f32> = envelope.iter().copied().filter(|x| *x > 0.0).collect();
+    if onsets.is_empty() {
+        return vec![0.0; envelope.len()];
+    }
+
+    let average = onsets.iter().sum::<f32>() / onsets.len() as f32;
+    envelope.iter().map(|x| x / average).collect()
+}
+
+/// Strongest onset in the frames around the timing
+fn peak_at(envelope: &[f32], timing_in_ms: f64) -> f32 {
+    let frame = (timing_in_ms / FRAME_LENGTH).round() as i64;
+    (frame - 1..=frame + 1)
+        .filter(|x| *x >= 0 && (*x as usize) < envelope.len())
+        .map(|x| envelope[x as usize])
+        .fold(0.0, f32::max)
+}
+
+/// Onset at the grid line which can be a note
+struct Candidate {
+    beat: Rational64,
+    face: JangguFace,
+    strength: f32,
+}
+
+/// Drafts the notes from the onsets of the music
+///
+/// Low band onsets are on 궁편 and high band onsets are on 열편, and the sticks alternate.
+/// The stronger onsets are kept first until the density of the level is reached,
+/// so the same music always gives the same draft.
+pub(crate) fn draft_events(
+    envelope: &OnsetEnvelope,
+    tempo_map: &TempoMap,
+    level: u32,
+    grid: u16,
+) -> Vec<NoteEvent> {
+    let low = normalize(&envelope.low);
+    let high = normalize(&envelope.high);
+    let length_in_ms = envelope.low.len() as f64 * FRAME_LENGTH;
+    let split_length = Rational64::new(1, splits_per_beat(grid));
+    let density = density_of(level);
+    let min_gap = density.min_gap.max(split_length);
+
+    let mut candidates = vec![];
+    let mut beat = Rational64::from_integer(0);
+    loop {
+        let timing = tempo_map.precise_timing_in_ms(beat);
+        let timing = *timing.numer() as f64 / *timing.denom() as f64;
+        if timing >= length_in_ms {
+            break;
+        }
+
+        let low_strength = peak_at(&low, timing);
+        let high_strength = peak_at(&high, timing);
+        let (face, strength) = if low_strength >= high_strength {
+            (JangguFace::궁편, low_strength)
+        } else {
+            (JangguFace::열편, high_strength)
+        };
+        if strength >= MIN_STRENGTH {
+            candidates.push(Candidate {
+                beat: beat,
+                face: face,
+                strength: strength,
+            });
+        }
+        beat += split_length;
+    }
+
+    // stronger onsets first, and earlier one first if the strengths are same
+    candidates.sort_by(|a, b| {
+        b.strength
+            .total_cmp(&a.strength)
+            .then_with(|| a.beat.cmp(&b.beat))
+    });
+    let max_notes = (beat.to_integer() as f64 * density.notes_per_beat).ceil() as usize;
+
+    let mut beats = BTreeSet::new();
+    let mut faces = vec![];
+    for candidate in &candidates {
+        if faces.len() >= max_notes {
+            break;
+        }
+
+        let is_too_close = beats
+            .range((
+                Bound::Excluded(candidate.beat - min_gap),
+                Bound::Excluded(candidate.beat + min_gap),
+            ))
+            .next()
+            .is_some();
+        if !is_too_close {
+            beats.insert(candidate.beat);
+            faces.push((candidate.beat, candidate.face));
+        }
+    }
+    faces.sort_by_key(|x| x.0);
+
+    faces
+        .iter()
+        .enumerate()
+        .map(|(idx, (beat, face))| NoteEvent {
+            stick: if idx % 2 == 0 {
+                JangguStick::궁채
+            } else {
+                JangguStick::열채
+            },
+            face: *face,
+            beat: *beat,
+            kind: NoteEventKind::Tap,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bidrum_data_struct_lib::{
+        janggu::{JangguFace, JangguStick},
+        song::TempoMap,
+    };
+    use num_rational::Rational64;
+
+    use super::draft_events;
+    use crate::analysis::OnsetEnvelope;
+
+    const SAMPLE_RATE: u32 = 22050;
+
+    /// Synthesizes 8 seconds of the music at 120 bpm with the delay of 1 second,
+    /// whose beats alternate the kick (low) and the snare (high)
+    /// and the quiet hi-hat is on every half beat
+    fn synthesize_music() -> Vec<f32> {
+        let mut samples = vec![0.0; SAMPLE_RATE as usize * 8];
+        for half_beat in 0..14 {
+            let start = (1000 + half_beat * 250) as usize * SAMPLE_RATE as usize / 1000;
+            let (frequency, volume) = match half_beat % 4 {
+                0 => (60.0, 0.8),
+                2 => (5000.0, 0.8),
+                _ => (8000.0, 0.1),
+            };
+            for i in 0..(SAMPLE_RATE as usize / 10) {
+                let time = i as f32 / SAMPLE_RATE as f32;
+                samples[start + i] +=
+                    (2.0 * PI * frequency * time).sin() * volume * (-time * 40.0).exp();
+            }
+        }
+
+        samples
+    }
+
+    fn tempo_map() -> TempoMap {
+        TempoMap::new(Rational64::from_integer(120), 1000, &[], &[])
+    }
+
+    #[test]
+    fn drafts_low_band_on_궁편_and_high_band_on_열편() {
+        let envelope = OnsetEnvelope::from_samples(&synthesize_music(), SAMPLE_RATE);
+        let events = draft_events(&envelope, &tempo_map(), 1, 16);
+
+        let notes: Vec<(Rational64, JangguFace)> =
+            events.iter().map(|x| (x.beat, x.face)).collect();
+        assert_eq!(
+            notes,
+            (0..7)
+                .map(|beat| (
+                    Rational64::from_integer(beat),
+                    if beat % 2 == 0 {
+                        JangguFace::궁편
+                    } else {
+                        JangguFace::열편
+                    }
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn alternates_sticks() {
+        let envelope = OnsetEnvelope::from_samples(&synthesize_music(), SAMPLE_RATE);
+        let events = draft_events(&envelope, &tempo_map(), 1, 16);
+
+        for (idx, event) in events.iter().enumerate() {
+            let stick = if idx % 2 == 0 {
+                JangguStick::궁채
+            } else {
+                JangguStick::열채
+            };
+            assert_eq!(event.stick, stick);
+        }
+    }
+
+    #[test]
+    fn drafts_more_notes_for_higher_level() {
+        let envelope = OnsetEnvelope::from_samples(&synthesize_music(), SAMPLE_RATE);
+        let easy = draft_events(&envelope, &tempo_map(), 1, 16);
+        let hard = draft_events(&envelope, &tempo_map(), 10, 16);
+
+        assert!(hard.len() > easy.len());
+        for pair in hard.windows(2) {
+            assert!(pair[1].beat - pair[0].beat >= Rational64::new(1, 4));
+        }
+    }
+
+    #[test]
+    fn drafts_same_notes_from_same_music() {
+        let envelope = OnsetEnvelope::from_samples(&synthesize_music(), SAMPLE_RATE);
+
+        assert_eq!(
+            draft_events(&envelope, &tempo_map(), 7, 16),
+            draft_events(&envelope, &tempo_map(), 7, 16)
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::{GameLongNote, GameLongNoteKind, GameNote},
+};
+use num_rational::Rational64;
+
+use crate::quantize::{splits_per_beat, QuantizedHit};
+
+/// Kind of the recorded note
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NoteEventKind {
+    Tap,
+    /// the stick is kept on the face until the end
+    Hold {
+        end: Rational64,
+    },
+    /// the face is hit repeatedly until the end
+    Roll {
+        end: Rational64,
+        hits: u32,
+    },
+}
+
+/// Note recorded from the hits, which keeps the stick, the face and the position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct NoteEvent {
+    pub(crate) stick: JangguStick,
+    pub(crate) face: JangguFace,
+    pub(crate) beat: Rational64,
+    pub(crate) kind: NoteEventKind,
+}
+
+/// Hits of the same stick quantized into the same position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Collision {
+    pub(crate) stick: JangguStick,
+    pub(crate) beat: Rational64,
+    /// face of the hit kept first, and the face of the colliding hit
+    ///
+    /// The colliding hit is dropped if the faces are same, otherwise both are kept.
+    pub(crate) faces: (JangguFace, JangguFace),
+}
+
+/// Notes recorded from the quantized hits
+#[derive(Debug)]
+pub(crate) struct EventList {
+    pub(crate) events: Vec<NoteEvent>,
+    pub(crate) collisions: Vec<Collision>,
+}
+
+/// split the position in unit of beat into beat index and tick
+pub(crate) fn split_beat(beat: Rational64) -> (u64, i64, i64) {
+    let beat_index = beat.floor();
+    let tick = beat - beat_index;
+
+    (beat_index.to_integer() as u64, *tick.numer(), *tick.denom())
+}
+
+impl EventList {
+    /// Creates the event list from the quantized hits
+    ///
+    /// # Arguments
+    ///   * `grid`: grid of the quantization
+    ///   * `hold_splits`: the hit becomes hold note if the stick is kept on the face for this many
+    ///     splits of the grid (0 disables hold notes)
+    ///   * `roll_hits`: consecutive hits on the same face become roll note if there are this many hits
+    ///     with gaps of at most 2 splits of the grid (0 disables roll notes)
+    pub(crate) fn new(
+        hits: &[QuantizedHit],
+        grid: u16,
+        hold_splits: u16,
+        roll_hits: u16,
+    ) -> EventList {
+        let split_length = Rational64::new(1, splits_per_beat(grid));
+        let mut sorted_hits = hits.to_vec();
+        // stable, so the hits at the same position keep the order of the recording
+        sorted_hits.sort_by_key(|x| x.beat);
+
+        let mut events: Vec<NoteEvent> = vec![];
+        let mut collisions = vec![];
+        for hit in sorted_hits {
+            let mut is_duplicated = false;
+            for event in events
+                .iter()
+                .filter(|x| x.stick == hit.stick && x.beat == hit.beat)
+            {
+                collisions.push(Collision {
+                    stick: hit.stick,
+                    beat: hit.beat,
+                    faces: (event.face, hit.face),
+                });
+                is_duplicated |= event.face == hit.face;
+            }
+            if is_duplicated {
+                continue;
+            }
+
+            let kind = match hit.release_beat {
+                Some(end)
+                    if hold_splits > 0 && end - hit.beat >= split_length * hold_splits as i64 =>
+                {
+                    NoteEventKind::Hold { end: end }
+                }
+                _ => NoteEventKind::Tap,
+            };
+            events.push(NoteEvent {
+                stick: hit.stick,
+                face: hit.face,
+                beat: hit.beat,
+                kind: kind,
+            });
+        }
+
+        let mut event_list = EventList {
+            events: events,
+            collisions: collisions,
+        };
+        if roll_hits > 0 {
+            event_list.merge_rolls(JangguStick::궁채, roll_hits, split_length * 2);
+            event_list.merge_rolls(JangguStick::열채, roll_hits, split_length * 2);
+        }
+
+        event_list
+    }
+
+    /// Merges the consecutive taps of the stick on the same face into roll notes
+    fn merge_rolls(&mut self, stick: JangguStick, roll_hits: u16, max_gap: Rational64) {
+        let (stick_events, mut events): (Vec<NoteEvent>, Vec<NoteEvent>) =
+            self.events.iter().partition(|x| x.stick == stick);
+
+        let mut idx = 0;
+        while idx < stick_events.len() {
+            // find the end of the consecutive taps
+            let mut end_idx = idx;
+            while stick_events[idx].kind == NoteEventKind::Tap
+                && end_idx + 1 < stick_events.len()
+                && stick_events[end_idx + 1].kind == NoteEventKind::Tap
+                && stick_events[end_idx + 1].face == stick_events[idx].face
+                && stick_events[end_idx + 1].beat - stick_events[end_idx].beat <= max_gap
+            {
+                end_idx += 1;
+            }
+
+            let hit_count = end_idx - idx + 1;
+            if hit_count >= roll_hits as usize && hit_count > 1 {
+                events.push(NoteEvent {
+                    stick: stick,
+                    face: stick_events[idx].face,
+                    beat: stick_events[idx].beat,
+                    kind: NoteEventKind::Roll {
+                        end: stick_events[end_idx].beat,
+                        hits: hit_count as u32,
+                    },
+                });
+            } else {
+                events.extend(&stick_events[idx..=end_idx]);
+            }
+            idx = end_idx + 1;
+        }
+
+        events.sort_by_key(|x| x.beat);
+        self.events = events;
+    }
+
+    /// Converts the events into the notes on 궁편, the notes on 열편 and the long notes
+    pub(crate) fn to_notes(&self) -> (Vec<GameNote>, Vec<GameNote>, Vec<GameLongNote>) {
+        let mut left_face = vec![];
+        let mut right_face = vec![];
+        let mut long_notes = vec![];
+        for event in &self.events {
+            let (beat_index, tick_nomiator, tick_denomiator) = split_beat(event.beat);
+            let (kind, end, required_hits) = match event.kind {
+                NoteEventKind::Tap => {
+                    let mut note = GameNote::create_raw_note(
+                        event.stick,
+                        beat_index,
+                        tick_nomiator,
+                        tick_denomiator,
+                    );
+                    note.face = event.face;
+                    match event.face {
+                        JangguFace::궁편 => left_face.push(note),
+                        JangguFace::열편 => right_face.push(note),
+                    }
+                    continue;
+                }
+                NoteEventKind::Hold { end } => (GameLongNoteKind::Hold, end, None),
+                NoteEventKind::Roll { end, hits } => (GameLongNoteKind::Roll, end, Some(hits)),
+            };
+
+            let (end_beat_index, end_tick_nomiator, end_tick_denomiator) = split_beat(end);
+            let mut note = GameLongNote::create_raw_long_note(
+                kind,
+                event.stick,
+                event.face,
+                beat_index,
+                tick_nomiator,
+                tick_denomiator,
+                end_beat_index,
+                end_tick_nomiator,
+                end_tick_denomiator,
+            );
+            note.required_hits = required_hits;
+            long_notes.push(note);
+        }
+
+        (left_face, right_face, long_notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bidrum_data_struct_lib::{
+        janggu::{JangguFace, JangguInputState, JangguStick},
+        song::{GameChart, GameLongNoteKind, GameNote},
+    };
+    use num_rational::Rational64;
+
+    use super::{Collision, EventList};
+    use crate::{
+        hit_log::HitLog, janggu_state_with_tick::JangguStateWithTick, overdub::merge_notes,
+        quantize::quantize,
+    };
+
+    /// Records the inputs of 궁채 and 열채 at 120 bpm (500ms per beat), and quantizes them into the chart
+    fn record(
+        inputs: &[(u64, Option<JangguFace>, Option<JangguFace>)],
+        hold_splits: u16,
+        roll_hits: u16,
+    ) -> (GameChart, Vec<Collision>) {
+        let mut hit_log = HitLog::new(Rational64::from_integer(120), 0, vec![], vec![]);
+        let mut janggu_state = JangguStateWithTick::new();
+        for (tick, 궁채, 열채) in inputs {
+            let previous_janggu_state = (janggu_state.궁채, janggu_state.열채);
+            janggu_state.update_with_input(
+                *tick,
+                JangguInputState {
+                    궁채: *궁채,
+                    열채: *열채,
+                },
+            );
+            hit_log.record_stick(
+                JangguStick::궁채,
+                previous_janggu_state.0,
+                janggu_state.궁채,
+                *tick,
+            );
+            hit_log.record_stick(
+                JangguStick::열채,
+                previous_janggu_state.1,
+                janggu_state.열채,
+                *tick,
+            );
+        }
+
+        let quantized = quantize(&hit_log, 16);
+        let event_list = EventList::new(&quantized.hits, 16, hold_splits, roll_hits);
+        let (left_face, right_face, long_notes) = event_list.to_notes();
+        let mut chart = hit_log.empty_chart();
+        merge_notes(&mut chart, left_face, right_face, long_notes, vec![]);
+
+        (chart, event_list.collisions)
+    }
+
+    fn notes(notes: &[GameNote]) -> Vec<(JangguStick, Rational64)> {
+        notes.iter().map(|x| (x.stick, x.beat())).collect()
+    }
+
+    #[test]
+    fn records_face_regardless_of_stick() {
+        let (chart, collisions) = record(
+            &[
+                (0, Some(JangguFace::열편), None),
+                (50, None, None),
+                (250, None, Some(JangguFace::궁편)),
+                (300, None, None),
+            ],
+            0,
+            0,
+        );
+
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![(JangguStick::열채, Rational64::new(1, 2))]
+        );
+        assert_eq!(
+            notes(&chart.right_face),
+            vec![(JangguStick::궁채, Rational64::from_integer(0))]
+        );
+        assert!(chart.left_face.iter().all(|x| x.face == JangguFace::궁편));
+        assert!(chart.right_face.iter().all(|x| x.face == JangguFace::열편));
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn quantizes_to_nearest_split() {
+        let (chart, _) = record(
+            &[
+                (490, Some(JangguFace::궁편), None),
+                (520, None, None),
+                (640, Some(JangguFace::궁편), None),
+                (660, None, None),
+            ],
+            0,
+            0,
+        );
+
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![
+                (JangguStick::궁채, Rational64::from_integer(1)),
+                (JangguStick::궁채, Rational64::new(5, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_both_faces_of_stick_in_same_split() {
+        let (chart, collisions) = record(
+            &[
+                (1000, Some(JangguFace::궁편), None),
+                (1010, None, None),
+                (1030, Some(JangguFace::열편), None),
+                (1040, None, None),
+            ],
+            0,
+            0,
+        );
+
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![(JangguStick::궁채, Rational64::from_integer(2))]
+        );
+        assert_eq!(
+            notes(&chart.right_face),
+            vec![(JangguStick::궁채, Rational64::from_integer(2))]
+        );
+        assert_eq!(
+            collisions,
+            vec![Collision {
+                stick: JangguStick::궁채,
+                beat: Rational64::from_integer(2),
+                faces: (JangguFace::궁편, JangguFace::열편),
+            }]
+        );
+    }
+
+    #[test]
+    fn drops_duplicated_hit_in_same_split() {
+        let (chart, collisions) = record(
+            &[
+                (1000, None, Some(JangguFace::궁편)),
+                (1010, None, None),
+                (1040, None, Some(JangguFace::궁편)),
+                (1050, None, None),
+            ],
+            0,
+            0,
+        );
+
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![(JangguStick::열채, Rational64::from_integer(2))]
+        );
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].faces, (JangguFace::궁편, JangguFace::궁편));
+    }
+
+    #[test]
+    fn records_hold_and_roll() {
+        let (chart, collisions) = record(
+            &[
+                (0, None, Some(JangguFace::궁편)),
+                (600, None, None),
+                (1000, Some(JangguFace::열편), None),
+                (1010, None, None),
+                (1125, Some(JangguFace::열편), None),
+                (1135, None, None),
+                (1250, Some(JangguFace::열편), None),
+                (1260, None, None),
+            ],
+            4,
+            3,
+        );
+
+        assert!(chart.left_face.is_empty());
+        assert!(chart.right_face.is_empty());
+        assert!(collisions.is_empty());
+        assert_eq!(chart.long_notes.len(), 2);
+
+        let hold = &chart.long_notes[0];
+        assert_eq!(hold.kind, GameLongNoteKind::Hold);
+        assert_eq!(
+            (hold.stick, hold.face),
+            (JangguStick::열채, JangguFace::궁편)
+        );
+        assert_eq!(hold.beat(), Rational64::from_integer(0));
+        assert_eq!(hold.end_beat(), Rational64::new(5, 4));
+
+        let roll = &chart.long_notes[1];
+        assert_eq!(roll.kind, GameLongNoteKind::Roll);
+        assert_eq!(
+            (roll.stick, roll.face),
+            (JangguStick::궁채, JangguFace::열편)
+        );
+        assert_eq!(roll.beat(), Rational64::from_integer(2));
+        assert_eq!(roll.end_beat(), Rational64::new(5, 2));
+        assert_eq!(roll.required_hits, Some(3));
+    }
+}
@@ -0,0 +1,51 @@
+use bidrum_hat::BidrumHat;
+use clap::ValueEnum;
+use device_query::{DeviceQuery, DeviceState, Keycode};
+
+/// Device to record the hat notes with
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum HatInput {
+    /// BidrumHat connected via Bluetooth
+    BidrumHat,
+    /// Space key of the keyboard, instead of spinning the hat
+    Keyboard,
+}
+
+enum HatDevice {
+    BidrumHat(BidrumHat),
+    Keyboard,
+}
+
+/// Processes the spin of the hat
+pub(crate) struct HatStateWithTick {
+    device: HatDevice,
+    /// whether the hat is spinning
+    pub spinning: bool,
+    /// Whether it's the EXACT time the hat is started to spin right now
+    pub is_spin_started_now: bool,
+}
+
+impl HatStateWithTick {
+    pub(crate) fn new(input: HatInput) -> HatStateWithTick {
+        let device = match input {
+            HatInput::BidrumHat => HatDevice::BidrumHat(BidrumHat::new()),
+            HatInput::Keyboard => HatDevice::Keyboard,
+        };
+
+        HatStateWithTick {
+            device: device,
+            spinning: false,
+            is_spin_started_now: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        let spinning = match &self.device {
+            HatDevice::BidrumHat(hat) => hat.spinning(),
+            HatDevice::Keyboard => DeviceState::new().get_keys().contains(&Keycode::Space),
+        };
+
+        self.is_spin_started_now = spinning && !self.spinning;
+        self.spinning = spinning;
+    }
+}
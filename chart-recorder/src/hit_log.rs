@@ -0,0 +1,146 @@
+use std::fs;
+
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::{
+        bpm_to_string, parse_bpm, ChartMetadata, GameBpmChange, GameChart, GameTimeSignature,
+        TempoMap,
+    },
+};
+use num_rational::Rational64;
+use serde::{Deserialize, Serialize};
+
+use crate::janggu_state_with_tick::JangguStickStateWithTick;
+
+/// Hit of the stick with the raw timing
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub(crate) struct RecordedHit {
+    pub(crate) stick: JangguStick,
+    pub(crate) face: JangguFace,
+    /// timing of the hit in millisecond from the first beat
+    pub(crate) tick: u64,
+    /// timing when the stick is taken off the face
+    ///
+    /// None if the stick is kept on the face until the end of the recording.
+    pub(crate) release_tick: Option<u64>,
+}
+
+/// Raw timings of the hits in the recording session, which are quantized into the chart afterward
+///
+/// The timings are in millisecond from the first beat, with the input delay already applied.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct HitLog {
+    /// bpm at the start of the song (e.g. `120`, `127.5`, `400/3`)
+    pub(crate) bpm: String,
+    /// timing of the first beat in the music in millisecond
+    pub(crate) delay: u64,
+    pub(crate) bpm_changes: Vec<GameBpmChange>,
+    pub(crate) time_signatures: Vec<GameTimeSignature>,
+    pub(crate) hits: Vec<RecordedHit>,
+    /// timings when the hat started spinning
+    pub(crate) hats: Vec<u64>,
+}
+
+impl HitLog {
+    pub(crate) fn new(
+        bpm: Rational64,
+        delay: u64,
+        bpm_changes: Vec<GameBpmChange>,
+        time_signatures: Vec<GameTimeSignature>,
+    ) -> HitLog {
+        HitLog {
+            bpm: bpm_to_string(bpm),
+            delay: delay,
+            bpm_changes: bpm_changes,
+            time_signatures: time_signatures,
+            hits: vec![],
+            hats: vec![],
+        }
+    }
+
+    pub(crate) fn load(path: &str) -> Result<HitLog, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read hit log {}: {}", path, err))?;
+        let log: HitLog = serde_json::from_str(&json)
+            .map_err(|err| format!("Failed to parse hit log {}: {}", path, err))?;
+        parse_bpm(&log.bpm)?;
+
+        Ok(log)
+    }
+
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("Hit log is always serializable");
+        fs::write(path, json).map_err(|err| format!("Failed to write hit log {}: {}", path, err))
+    }
+
+    pub(crate) fn bpm(&self) -> Rational64 {
+        parse_bpm(&self.bpm).expect("Bpm of the hit log is checked when loaded")
+    }
+
+    /// Tempo map of the log, whose first beat is at zero
+    pub(crate) fn tempo_map(&self) -> TempoMap {
+        TempoMap::new(self.bpm(), 0, &self.bpm_changes, &self.time_signatures)
+    }
+
+    /// Chart without notes, which has the tempo of the log
+    pub(crate) fn empty_chart(&self) -> GameChart {
+        GameChart {
+            artist: "Team Overchaos".to_string(),
+            metadata: ChartMetadata::default(),
+            delay: self.delay,
+            bpm: self.bpm(),
+            left_face: vec![],
+            right_face: vec![],
+            hats: vec![],
+            strokes: vec![],
+            long_notes: vec![],
+            bpm_changes: self.bpm_changes.clone(),
+            time_signatures: self.time_signatures.clone(),
+        }
+    }
+
+    /// Records the hit and the release from the change of the stick state
+    ///
+    /// Returns the face if the stick hit it now.
+    pub(crate) fn record_stick(
+        &mut self,
+        stick: JangguStick,
+        previous: JangguStickStateWithTick,
+        current: JangguStickStateWithTick,
+        tick: u64,
+    ) -> Option<JangguFace> {
+        // the stick is taken off the face, or moved to the other face
+        if previous.face.is_some() && previous.keydown_timing != current.keydown_timing {
+            self.release(stick, current.keydown_timing);
+        }
+        if !current.is_keydown_now {
+            return None;
+        }
+
+        let face = current.face?;
+        self.hit(stick, face, tick);
+        Some(face)
+    }
+
+    /// Starts the hit of the stick
+    pub(crate) fn hit(&mut self, stick: JangguStick, face: JangguFace, tick: u64) {
+        self.hits.push(RecordedHit {
+            stick: stick,
+            face: face,
+            tick: tick,
+            release_tick: None,
+        });
+    }
+
+    /// Takes the stick off the face
+    pub(crate) fn release(&mut self, stick: JangguStick, tick: u64) {
+        if let Some(hit) = self
+            .hits
+            .iter_mut()
+            .rev()
+            .find(|x| x.stick == stick && x.release_tick.is_none())
+        {
+            hit.release_tick = Some(tick);
+        }
+    }
+}
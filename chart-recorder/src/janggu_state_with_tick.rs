@@ -64,23 +64,29 @@ impl JangguStateWithTick {
         let device_states = DeviceState::new();
         let keys = device_states.get_keys();
 
-        let state = JangguInputState {
-            궁채: if keys.contains(&Keycode::D) {
-                Some(JangguFace::궁편)
-            } else if keys.contains(&Keycode::F) {
-                Some(JangguFace::열편)
-            } else {
-                None
-            },
-            열채: if keys.contains(&Keycode::J) {
-                Some(JangguFace::궁편)
-            } else if keys.contains(&Keycode::K) {
-                Some(JangguFace::열편)
-            } else {
-                None
+        self.update_with_input(
+            time,
+            JangguInputState {
+                궁채: if keys.contains(&Keycode::D) {
+                    Some(JangguFace::궁편)
+                } else if keys.contains(&Keycode::F) {
+                    Some(JangguFace::열편)
+                } else {
+                    None
+                },
+                열채: if keys.contains(&Keycode::J) {
+                    Some(JangguFace::궁편)
+                } else if keys.contains(&Keycode::K) {
+                    Some(JangguFace::열편)
+                } else {
+                    None
+                },
             },
-        };
+        );
+    }
 
+    /// Updates the state with the input, instead of reading the keyboard
+    pub(crate) fn update_with_input(&mut self, time: u64, state: JangguInputState) {
         self.궁채 = if state.궁채 == self.궁채.face {
             self.궁채.toggle_keydown(false)
         } else {
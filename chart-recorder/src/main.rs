@@ -1,236 +1,616 @@
+mod analysis;
 mod beep_boop;
+mod draft;
+mod event_list;
+mod hat_state_with_tick;
+mod hit_log;
 mod janggu_state_with_tick;
+mod metronome;
+mod overdub;
+mod quantize;
+mod session;
 
-use std::{collections::HashMap, env, fs::File, io::Write};
+use std::{fs::File, io::Write, path::Path, thread::sleep, time::Duration};
 
+use bidrum_controller_lib::{
+    keyboard::janggu_device::KeyboardJangguDevice, serial, ConnectionState,
+};
 use bidrum_data_struct_lib::{
-    janggu::{JangguFace, JangguStick},
-    song::{GameChart, GameNote},
+    calibration::{CalibrationProfile, DEFAULT_CALIBRATION_PROFILE_PATH},
+    song::{bpm_to_string, parse_bpm, GameBpmChange, GameChart, GameHatNote, GameTimeSignature},
 };
-use clap::Parser;
-use kira::{
-    clock::ClockSpeed,
-    manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
-    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use num_rational::Rational64;
+
+use crate::{
+    analysis::{suggest_tempo, OnsetEnvelope, TempoSuggestion},
+    beep_boop::beep_boop,
+    draft::draft_events,
+    event_list::{split_beat, EventList},
+    hat_state_with_tick::HatInput,
+    hit_log::HitLog,
+    overdub::{
+        erase_notes, existing_hits, merge_notes, parse_erase_target, EraseTarget, PunchRange,
+    },
+    quantize::{parse_grid, quantize},
+    session::{
+        parse_loop_section, record, select_take, LoopSection, SessionOptions, TakeSelection,
+    },
 };
 
-use crate::{beep_boop::beep_boop, janggu_state_with_tick::JangguStateWithTick};
-
 /// Chart recorder for bidrum, which plays the music and generates the chart as you hit the janggu
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path of music file
-    #[arg(short, long)]
-    music: String,
+    #[arg(short, long, required_unless_present = "quantize")]
+    music: Option<String>,
 
     /// Path of output file
-    /// Default is automatically created temp file
+    #[arg(short, long, required = true)]
+    output: Option<String>,
+
+    /// Analyzes the music and uses the suggested bpm and delay
+    #[arg(long, conflicts_with_all = ["input", "quantize", "bpm", "delay"])]
+    analyze: bool,
+
+    #[command(flatten)]
+    bpm_range: BpmRange,
+
+    /// Raw hits to save (Default is the output with `.hits.json` extension)
+    ///
+    /// The raw hits can be quantized again with --quantize, e.g. with the other grid.
+    #[arg(long)]
+    hit_log: Option<String>,
+
+    /// Quantizes the raw hits saved before, instead of recording
+    #[arg(long, conflicts_with = "hit_log")]
+    quantize: Option<String>,
+
+    /// Existing chart to edit
+    ///
+    /// The notes of the chart are played with the hit sounds, and the recorded notes are added to it.
+    /// Bpm, bpm changes, time signatures and delay are read from the chart.
     #[arg(short, long)]
-    output: String,
+    input: Option<String>,
+
+    /// Beat where the recording starts when editing the existing chart
+    ///
+    /// The music is played from the count-in before it.
+    #[arg(long, requires = "input")]
+    punch_in: Option<u64>,
+
+    /// Beat where the recording ends when editing the existing chart
+    #[arg(long, requires = "input")]
+    punch_out: Option<u64>,
+
+    /// Erases the existing notes of the stick, the face or the hat (궁채, 열채, 궁편, 열편, hat) between
+    /// the punch-in and the punch-out
+    /// Can be given multiple times
+    #[arg(long, requires = "input", value_parser = parse_erase_target)]
+    erase: Vec<EraseTarget>,
+
+    /// Directory of the hit sounds (kung.wav and deok.wav) to play the notes of the existing chart
+    #[arg(long, default_value = "../game/assets/sound/janggu_hit")]
+    hit_sound_dir: String,
 
     /// Delay before starting the music (in milliseconds)
-    #[arg(long, default_value_t = 0)]
+    #[arg(long, default_value_t = 0, conflicts_with_all = ["input", "quantize"])]
     delay: u16,
 
-    /// How many splits per beat?
-    #[arg(short, long, default_value_t = 4)]
-    splits: u16,
-
-    /// Music bpm
-    #[arg(short, long)]
-    bpm: u16,
+    /// Grid to quantize the hits (4, 8, 12, 16, 24 for quarter notes to 24th notes)
+    ///
+    /// With 8 and 16, the beats whose hits fit the triplets better are quantized into the triplets.
+    #[arg(short, long, default_value_t = 16, value_parser = parse_grid)]
+    grid: u16,
+
+    /// Music bpm (e.g. 120, 127.5, 400/3)
+    #[arg(
+        short,
+        long,
+        value_parser = parse_bpm,
+        required_unless_present_any = ["input", "quantize", "analyze"],
+        conflicts_with_all = ["input", "quantize"]
+    )]
+    bpm: Option<Rational64>,
+
+    /// BPM change in the format of BEAT:BPM (e.g. 64:127.5 changes the bpm into 127.5 from 64th beat)
+    /// Can be given multiple times
+    #[arg(long, conflicts_with_all = ["input", "quantize"])]
+    bpm_change: Vec<String>,
+
+    /// Time signature change in the format of BEAT:NUMERATOR/DENOMINATOR (e.g. 0:3/4)
+    /// Can be given multiple times
+    #[arg(long, conflicts_with_all = ["input", "quantize"])]
+    time_signature: Vec<String>,
 
     /// Chart artist name
     #[arg(short, long)]
     artist: Option<String>,
 
-    /// Delay of input device(or you) in milliseconds
+    /// Difficulty name of the chart (e.g. 쉬움, 보통, 어려움)
+    #[arg(long)]
+    difficulty_name: Option<String>,
+
+    /// Name of the person who makes the chart
+    #[arg(long)]
+    charter: Option<String>,
+
+    /// Records the hit as hold note if the stick is kept on the face for this many splits of the grid
+    /// (0 disables hold notes)
     #[arg(long, default_value_t = 0)]
-    input_delay: i16,
+    hold_splits: u16,
+
+    /// Records consecutive hits on the same face as roll note if there are this many hits
+    /// with gaps of at most 2 splits of the grid (0 disables roll notes)
+    #[arg(long, default_value_t = 0)]
+    roll_hits: u16,
+
+    /// Records the hat notes as the hat starts spinning
+    /// (bidrum-hat: BidrumHat, keyboard: space key)
+    #[arg(long, value_enum)]
+    hat: Option<HatInput>,
+
+    /// Beats of the count-in clicks before the recording starts
+    #[arg(long, default_value_t = 4)]
+    count_in: u16,
 
-    /// Run Beep-Boop input delay measurement
+    /// Plays the metronome clicks while recording
     #[arg(long)]
-    beep_boop: bool,
+    metronome: bool,
+
+    /// How many clicks per beat the metronome plays
+    #[arg(long, default_value_t = 1, requires = "metronome", value_parser = clap::value_parser!(u16).range(1..))]
+    metronome_splits: u16,
+
+    /// Repeats the section in the format of START:END in unit of beat (e.g. 32:40),
+    /// and records only the section
+    #[arg(long = "loop", value_parser = parse_loop_section, conflicts_with_all = ["punch_in", "punch_out", "quantize"])]
+    loop_section: Option<LoopSection>,
+
+    /// How many times the loop section is played
+    #[arg(long, default_value_t = 4, requires = "loop_section", value_parser = clap::value_parser!(u16).range(1..))]
+    loop_passes: u16,
+
+    /// How to make the notes from the passes of the loop section
+    #[arg(long, value_enum, default_value_t = TakeSelection::Merge, requires = "loop_section")]
+    take: TakeSelection,
+
+    /// Delay of input device(or you) in milliseconds
+    /// Default is the audio offset of the calibration profile
+    #[arg(long)]
+    input_delay: Option<i16>,
+
+    /// Path of the calibration profile measured by beep-boop
+    #[arg(long, default_value = DEFAULT_CALIBRATION_PROFILE_PATH)]
+    calibration_profile: String,
 }
 
-fn janggu_face_to_one_letter_str(face: Option<&JangguFace>) -> &str {
-    if matches!(face, Some(JangguFace::궁편)) {
-        "L"
-    } else if matches!(face, Some(JangguFace::열편)) {
-        "R"
-    } else {
-        "_"
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Suggests bpm and delay (timing of the first beat) of the music
+    Analyze(AnalyzeArgs),
+    /// Drafts the chart from the onsets of the music, to be refined by recording
+    Draft(DraftArgs),
+    /// Measures the input delay with the janggu, and saves it into the calibration profile
+    BeepBoop(BeepBoopArgs),
 }
 
-fn main() {
-    // Run beep-boop
-    if env::args().find(|x| x.eq("--beep-boop")).is_some() {
-        return beep_boop();
-    }
+#[derive(ClapArgs, Debug)]
+struct BeepBoopArgs {
+    /// Port of janggu controller
+    /// Default is the keyboard (d, f, j, k)
+    #[arg(short, long)]
+    controller_port: Option<String>,
 
-    // Parse args and run beep-boop if given
-    let args = Args::parse();
-    if args.beep_boop {
-        return beep_boop();
-    }
+    /// Path of the calibration profile to save
+    #[arg(long, default_value = DEFAULT_CALIBRATION_PROFILE_PATH)]
+    calibration_profile: String,
+}
 
-    // Introduction
-    println!("Bidrum chart recorder");
-    println!("");
-    println!("This program CANNOT edit the existing chart.");
-    println!("but, this program can play music and generate");
-    println!("the chart as you hit the janggu (via keyboard)");
-    println!("");
+#[derive(ClapArgs, Debug)]
+struct AnalyzeArgs {
+    /// Path of music file
+    music: String,
 
-    // Init kira backend
-    println!("initializing kira backend");
-    let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
-        .expect("kira AudioManager initialization failure");
-    let clock_handle = manager
-        .add_clock(ClockSpeed::TicksPerSecond(1000.0))
-        .expect("kira clock add failure");
-
-    // Load music which starts after 5 sec
-    let start_tick = clock_handle.time() + 5000;
-    let settings = StaticSoundSettings::new().start_time(start_tick);
-
-    // Init variables
-    let mut left_stick = HashMap::new();
-    let mut right_stick = HashMap::new();
-    let mut janggu_state = JangguStateWithTick::new();
-
-    // Load music
-    let music = StaticSoundData::from_file(args.music, settings).expect("Failed to load music");
-    let music_duration = music.duration();
-
-    // Start music
-    println!("music will start after 5 sec");
-    clock_handle.start().expect("Failed to start kira clock");
-    let mut beat_and_split_before: Option<u64> = None;
-    manager.play(music).expect("Failed to play music");
-    loop {
-        let tick = {
-            let elapsed = clock_handle.time().ticks;
-
-            if elapsed as u128 > start_tick.ticks as u128 + music_duration.as_millis() {
-                break;
-            }
+    #[command(flatten)]
+    bpm_range: BpmRange,
+}
 
-            let record_start_tick: u64 = start_tick.ticks + args.delay as u64;
+#[derive(ClapArgs, Debug)]
+struct DraftArgs {
+    /// Path of music file
+    music: String,
 
-            if elapsed < record_start_tick {
-                println!(
-                    "chart recording will start after {:.2} sec",
-                    (record_start_tick - elapsed) as f32 / 1000.0,
-                );
-                continue;
-            }
+    /// Path of output file
+    #[arg(short, long)]
+    output: String,
 
-            let tick = elapsed - record_start_tick;
-            let delayed_tick = tick as i64 - args.input_delay as i64;
+    /// Level of the chart, which limits the density of the notes
+    #[arg(short, long)]
+    level: u32,
 
-            if delayed_tick < 0 {
-                println!("delayed...");
-                continue;
-            }
+    /// Grid of the notes (4, 8, 12, 16, 24 for quarter notes to 24th notes)
+    #[arg(short, long, default_value_t = 16, value_parser = parse_grid)]
+    grid: u16,
 
-            delayed_tick as u64
-        };
+    /// Music bpm (e.g. 120, 127.5, 400/3)
+    /// Default is the suggested bpm of the music
+    #[arg(short, long, value_parser = parse_bpm)]
+    bpm: Option<Rational64>,
 
-        janggu_state.update(tick);
+    /// Delay before starting the music (in milliseconds)
+    #[arg(long, requires = "bpm")]
+    delay: Option<u16>,
 
-        let beat_and_split =
-            ((tick as f64 / (1000.0 * 60.0)) * (args.bpm * args.splits) as f64) as u64;
-        if janggu_state.궁채.is_keydown_now && janggu_state.궁채.face.is_some() {
-            left_stick.insert(beat_and_split, janggu_state.궁채.face.unwrap());
-        }
-        if janggu_state.열채.is_keydown_now && janggu_state.열채.face.is_some() {
-            right_stick.insert(beat_and_split, janggu_state.열채.face.unwrap());
-        }
+    #[command(flatten)]
+    bpm_range: BpmRange,
+}
 
-        let split_digits = args.splits.to_string().len();
-        if !beat_and_split_before.is_some_and(|x| x == beat_and_split) {
-            if let Some(beat_and_split_before_unwrapped) = beat_and_split_before {
-                let beat_idx = beat_and_split_before_unwrapped / args.splits as u64;
-                let split = beat_and_split_before_unwrapped % args.splits as u64;
-                println!(
-                    "beat: {} ({:0split_width$} / {}) : left_stick = {}, right_stick = {}",
-                    beat_idx,
-                    split,
-                    args.splits,
-                    janggu_face_to_one_letter_str(left_stick.get(&beat_and_split_before_unwrapped)),
-                    janggu_face_to_one_letter_str(
-                        right_stick.get(&beat_and_split_before_unwrapped)
-                    ),
-                    split_width = split_digits
-                )
-            }
-            beat_and_split_before = Some(beat_and_split);
-        }
-    }
+/// Range of bpm to search when analyzing the music
+#[derive(ClapArgs, Debug)]
+struct BpmRange {
+    /// Minimum bpm to suggest
+    #[arg(long, default_value_t = 60.0)]
+    min_bpm: f64,
 
-    println!("Converting to chart json format...");
-    let mut left_face = vec![];
-    let mut right_face = vec![];
-    for i in left_stick.iter() {
-        let beat_idx = i.0 / args.splits as u64;
-        let split = i.0 % args.splits as u64;
-
-        if matches!(i.1, JangguFace::궁편) {
-            left_face.push(GameNote::create_raw_note(
-                JangguStick::궁채,
-                beat_idx.into(),
-                split as i64,
-                args.splits.into(),
-            ));
-        } else if matches!(i.1, JangguFace::열편) {
-            right_face.push(GameNote::create_raw_note(
-                JangguStick::궁채,
-                beat_idx.into(),
-                split as i64,
-                args.splits.into(),
-            ));
-        }
-    }
+    /// Maximum bpm to suggest
+    #[arg(long, default_value_t = 240.0)]
+    max_bpm: f64,
+}
 
-    for i in right_stick.iter() {
-        let beat_idx = i.0 / args.splits as u64;
-        let split = i.0 % args.splits as u64;
-
-        if matches!(i.1, JangguFace::궁편) {
-            left_face.push(GameNote::create_raw_note(
-                JangguStick::열채,
-                beat_idx.into(),
-                split as i64,
-                args.splits.into(),
-            ));
-        } else if matches!(i.1, JangguFace::열편) {
-            right_face.push(GameNote::create_raw_note(
-                JangguStick::열채,
-                beat_idx.into(),
-                split as i64,
-                args.splits.into(),
-            ));
+/// Analyzes the music and prints the suggested bpm and delay
+fn analyze(music: &str, bpm_range: &BpmRange) -> Option<TempoSuggestion> {
+    println!("analyzing {}", music);
+    let envelope = match OnsetEnvelope::from_file(music) {
+        Ok(envelope) => envelope,
+        Err(err) => {
+            println!("{}", err);
+            return None;
         }
+    };
+
+    let suggestion = suggest_tempo(&envelope, bpm_range.min_bpm, bpm_range.max_bpm);
+    match suggestion {
+        Some(suggestion) => println!(
+            "suggested: --bpm {} --delay {}",
+            bpm_to_string(suggestion.bpm),
+            suggestion.delay
+        ),
+        None => println!("Failed to find the beats of the music"),
     }
 
-    left_face.sort_by(|a, b| a.beat().cmp(&b.beat()));
-    right_face.sort_by(|a, b| a.beat().cmp(&b.beat()));
+    suggestion
+}
+
+fn parse_bpm_change(arg: &str) -> GameBpmChange {
+    let (beat, bpm) = arg
+        .split_once(':')
+        .expect("BPM change should be in the format of BEAT:BPM");
+
+    GameBpmChange::create_raw_bpm_change(
+        beat.parse().expect("Invalid beat of BPM change"),
+        0,
+        0,
+        parse_bpm(bpm).expect("Invalid bpm of BPM change"),
+    )
+}
+
+fn parse_time_signature(arg: &str) -> GameTimeSignature {
+    let (beat, time_signature) = arg
+        .split_once(':')
+        .expect("Time signature should be in the format of BEAT:NUMERATOR/DENOMINATOR");
+    let (numerator, denominator) = time_signature
+        .split_once('/')
+        .expect("Time signature should be in the format of BEAT:NUMERATOR/DENOMINATOR");
+
+    GameTimeSignature::create_raw_time_signature(
+        beat.parse().expect("Invalid beat of time signature"),
+        0,
+        0,
+        numerator
+            .parse()
+            .expect("Invalid numerator of time signature"),
+        denominator
+            .parse()
+            .expect("Invalid denominator of time signature"),
+    )
+}
+
+/// Drafts the chart from the onsets of the music and saves it
+fn draft(args: &DraftArgs) {
+    println!("analyzing {}", args.music);
+    let envelope = OnsetEnvelope::from_file(&args.music).expect("Failed to analyze the music");
+    let (bpm, delay) = match args.bpm {
+        Some(bpm) => (bpm, args.delay.unwrap_or(0) as u64),
+        None => {
+            let suggestion =
+                suggest_tempo(&envelope, args.bpm_range.min_bpm, args.bpm_range.max_bpm)
+                    .expect("Failed to find the beats of the music");
+            println!(
+                "suggested: --bpm {} --delay {}",
+                bpm_to_string(suggestion.bpm),
+                suggestion.delay
+            );
+            (suggestion.bpm, suggestion.delay)
+        }
+    };
+
+    let mut chart = HitLog::new(bpm, delay, vec![], vec![]).empty_chart();
+    let event_list = EventList {
+        events: draft_events(&envelope, &chart.tempo_map(), args.level, args.grid),
+        collisions: vec![],
+    };
+    println!("{} notes are drafted", event_list.events.len());
+    let (left_face, right_face, long_notes) = event_list.to_notes();
+    merge_notes(&mut chart, left_face, right_face, long_notes, vec![]);
+
+    save_chart(chart, &args.output);
+    println!("Done!");
+}
 
+/// Writes the chart into the file
+fn save_chart(chart: GameChart, path: &str) {
     let json = GameChart::to_json_string(
-        args.artist.unwrap_or("Team Overchaos".to_string()),
-        args.delay.into(),
-        args.bpm.into(),
-        left_face,
-        right_face,
-        vec![],
+        chart.artist,
+        chart.metadata,
+        chart.delay,
+        chart.bpm,
+        chart.left_face,
+        chart.right_face,
+        chart.hats,
+        chart.strokes,
+        chart.long_notes,
+        chart.bpm_changes,
+        chart.time_signatures,
     )
     .unwrap();
 
-    let mut f = File::create(args.output).expect("Failed to create or truncate output file");
+    let mut f = File::create(path).expect("Failed to create or truncate output file");
     write!(f, "{}", json).expect("Failed to write");
+}
+
+fn main() {
+    // Parse args and run the subcommand if given
+    let args = Args::parse();
+    match &args.command {
+        Some(Command::Analyze(analyze_args)) => {
+            analyze(&analyze_args.music, &analyze_args.bpm_range);
+            return;
+        }
+        Some(Command::Draft(draft_args)) => return draft(draft_args),
+        Some(Command::BeepBoop(beep_boop_args)) => {
+            let profile_path = Path::new(&beep_boop_args.calibration_profile);
+            return match &beep_boop_args.controller_port {
+                Some(controller_port) => {
+                    let (janggu_device, _) = serial::new(controller_port.clone());
+                    println!("Waiting for the controller");
+                    while janggu_device.connection_state() != ConnectionState::Connected {
+                        sleep(Duration::from_millis(100));
+                    }
+                    beep_boop(&janggu_device, profile_path)
+                }
+                None => beep_boop(&KeyboardJangguDevice::new(), profile_path),
+            };
+        }
+        None => {}
+    }
+
+    // Introduction
+    println!("Bidrum chart recorder");
+    println!("");
+    println!("This program can play music and generate");
+    println!("the chart as you hit the janggu (via keyboard)");
+    println!("With --input, the notes are added to the existing chart");
+    println!("");
+
+    // Load the chart to edit
+    let existing_chart = args.input.as_ref().map(|input| {
+        GameChart::from_file(Path::new(input)).expect("Failed to load the existing chart")
+    });
+    let punch_range = match args.loop_section {
+        Some(loop_section) => PunchRange {
+            start: Rational64::from_integer(loop_section.start as i64),
+            end: Some(Rational64::from_integer(loop_section.end as i64)),
+        },
+        None => PunchRange {
+            start: Rational64::from_integer(args.punch_in.unwrap_or(0) as i64),
+            end: args.punch_out.map(|x| Rational64::from_integer(x as i64)),
+        },
+    };
+
+    // Record the hits, or load the hits recorded before
+    let hit_log = match &args.quantize {
+        Some(hit_log_path) => HitLog::load(hit_log_path).expect("Failed to load the hit log"),
+        None => {
+            let hit_log = match &existing_chart {
+                Some(chart) => HitLog::new(
+                    chart.bpm,
+                    chart.delay,
+                    chart.bpm_changes.clone(),
+                    chart.time_signatures.clone(),
+                ),
+                None => {
+                    // the suggested tempo is used instead of the args
+                    let (bpm, delay) = if args.analyze {
+                        let suggestion = analyze(
+                            args.music.as_ref().expect("Music is required to analyze"),
+                            &args.bpm_range,
+                        )
+                        .expect("Failed to analyze the music");
+                        (suggestion.bpm, suggestion.delay)
+                    } else {
+                        (
+                            args.bpm
+                                .expect("Bpm is required without the existing chart"),
+                            args.delay as u64,
+                        )
+                    };
+                    HitLog::new(
+                        bpm,
+                        delay,
+                        args.bpm_change
+                            .iter()
+                            .map(|x| parse_bpm_change(x))
+                            .collect::<Vec<GameBpmChange>>(),
+                        args.time_signature
+                            .iter()
+                            .map(|x| parse_time_signature(x))
+                            .collect::<Vec<GameTimeSignature>>(),
+                    )
+                }
+            };
+
+            // Hits of the existing chart, except the erased ones
+            let mut hits_to_play = vec![];
+            if let Some(chart) = &existing_chart {
+                let mut chart = chart.clone();
+                erase_notes(&mut chart, &punch_range, &args.erase);
+                hits_to_play = existing_hits(&chart, &chart.tempo_map());
+            }
+
+            let options = SessionOptions {
+                music_path: args
+                    .music
+                    .as_ref()
+                    .expect("Music is required to record the chart"),
+                start_beat: match args.loop_section {
+                    Some(loop_section) => loop_section.start,
+                    None => args.punch_in.unwrap_or(0),
+                },
+                count_in: args.count_in,
+                metronome_splits: if args.metronome {
+                    Some(args.metronome_splits)
+                } else {
+                    None
+                },
+                loop_section: args.loop_section,
+                loop_passes: args.loop_passes,
+                hits_to_play: hits_to_play,
+                hit_sound_dir: &args.hit_sound_dir,
+                hat: args.hat,
+                input_delay: match args.input_delay {
+                    Some(input_delay) => input_delay,
+                    None => {
+                        let profile = CalibrationProfile::load_or_default(Path::new(
+                            &args.calibration_profile,
+                        ))
+                        .unwrap_or_else(|err| {
+                            eprintln!("{}, so the input delay is not applied", err);
+                            CalibrationProfile::default()
+                        });
+                        println!(
+                            "input delay from the calibration profile: {} ms",
+                            profile.audio_offset
+                        );
+                        i16::try_from(profile.audio_offset).expect(
+                            "Audio offset of the calibration profile is out of range of the input delay",
+                        )
+                    }
+                },
+            };
+            let takes = record(&options, hit_log);
+            let hit_log = select_take(takes, args.take, args.grid);
+
+            let hit_log_path = match &args.hit_log {
+                Some(hit_log_path) => hit_log_path.clone(),
+                None => Path::new(args.output.as_ref().expect("Output is required"))
+                    .with_extension("hits.json")
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            hit_log
+                .save(&hit_log_path)
+                .expect("Failed to save the hit log");
+            println!("Raw hits are saved to {}", hit_log_path);
+
+            hit_log
+        }
+    };
+
+    println!("Quantizing into {}th notes...", args.grid);
+    let quantized = quantize(&hit_log, args.grid);
+    if !quantized.off_grid_hits.is_empty() {
+        println!(
+            "{} hits are far from the grid lines:",
+            quantized.off_grid_hits.len()
+        );
+        for i in &quantized.off_grid_hits {
+            println!(
+                "  {} ms ({}): quantized to beat {} ({:+.0} ms)",
+                i.tick,
+                match i.stick {
+                    Some(stick) => format!("{:?}", stick),
+                    None => "hat".to_string(),
+                },
+                i.beat,
+                i.offset_in_ms
+            );
+        }
+    }
+
+    println!("Converting to chart json format...");
+    let event_list = EventList::new(&quantized.hits, args.grid, args.hold_splits, args.roll_hits);
+    if !event_list.collisions.is_empty() {
+        println!(
+            "{} hits collide with the hits of the same stick:",
+            event_list.collisions.len()
+        );
+        for i in &event_list.collisions {
+            println!(
+                "  beat {} ({:?}): {:?} and {:?}{}",
+                i.beat,
+                i.stick,
+                i.faces.0,
+                i.faces.1,
+                if i.faces.0 == i.faces.1 {
+                    ", recorded once"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+    let (mut left_face, mut right_face, mut game_long_notes) = event_list.to_notes();
+
+    let mut hats: Vec<GameHatNote> = quantized
+        .hats
+        .iter()
+        .map(|x| {
+            let (beat_index, tick_nomiator, tick_denomiator) = split_beat(*x);
+            GameHatNote::create_raw_note(beat_index, tick_nomiator, tick_denomiator)
+        })
+        .collect();
+    hats.sort_by_key(|x| x.beat());
+    hats.dedup_by_key(|x| x.beat());
+
+    // merge the recorded notes into the existing chart (or the empty chart)
+    let mut chart = existing_chart.unwrap_or_else(|| hit_log.empty_chart());
+    left_face.retain(|x| punch_range.contains(x.beat()));
+    right_face.retain(|x| punch_range.contains(x.beat()));
+    game_long_notes.retain(|x| punch_range.contains(x.beat()));
+    hats.retain(|x| punch_range.contains(x.beat()));
+    erase_notes(&mut chart, &punch_range, &args.erase);
+    merge_notes(&mut chart, left_face, right_face, game_long_notes, hats);
+    if let Some(artist) = args.artist {
+        chart.artist = artist;
+    }
+    if args.difficulty_name.is_some() {
+        chart.metadata.difficulty_name = args.difficulty_name;
+    }
+    if args.charter.is_some() {
+        chart.metadata.charter = args.charter;
+    }
+
+    save_chart(chart, &args.output.expect("Output is required"));
 
     println!("Done!");
 }
@@ -0,0 +1,94 @@
+use std::f32::consts::PI;
+
+use bidrum_data_struct_lib::song::TempoMap;
+use kira::{
+    dsp::Frame,
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+};
+use num_rational::Rational64;
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Length of the click sound in millisecond
+const CLICK_LENGTH: u32 = 30;
+
+/// Kind of the metronome click
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Click {
+    /// start of the measure
+    Accent,
+    Beat,
+    /// split between the beats
+    Split,
+}
+
+/// Synthesizes the click sound, which is the sine wave fading out
+fn synthesize_click(frequency: f32, volume: f32) -> StaticSoundData {
+    let length = (SAMPLE_RATE * CLICK_LENGTH / 1000) as usize;
+    let frames: Vec<Frame> = (0..length)
+        .map(|i| {
+            let time = i as f32 / SAMPLE_RATE as f32;
+            let envelope = (1.0 - i as f32 / length as f32).powi(2);
+            Frame::from_mono((2.0 * PI * frequency * time).sin() * volume * envelope)
+        })
+        .collect();
+
+    StaticSoundData {
+        sample_rate: SAMPLE_RATE,
+        frames: frames.into(),
+        settings: StaticSoundSettings::default(),
+    }
+}
+
+/// Click sounds of the metronome
+pub(crate) struct Metronome {
+    accent: StaticSoundData,
+    beat: StaticSoundData,
+    split: StaticSoundData,
+}
+
+impl Metronome {
+    pub(crate) fn new() -> Metronome {
+        Metronome {
+            accent: synthesize_click(1760.0, 0.8),
+            beat: synthesize_click(1320.0, 0.6),
+            split: synthesize_click(880.0, 0.3),
+        }
+    }
+
+    pub(crate) fn sound(&self, click: Click) -> &StaticSoundData {
+        match click {
+            Click::Accent => &self.accent,
+            Click::Beat => &self.beat,
+            Click::Split => &self.split,
+        }
+    }
+}
+
+/// Clicks between the timings with the given splits per beat
+///
+/// The timings are in millisecond from the first beat, and can be negative for the count-in.
+pub(crate) fn clicks(tempo_map: &TempoMap, from: i64, to: i64, splits: u16) -> Vec<(i64, Click)> {
+    let split_length = Rational64::new(1, splits as i64);
+    let mut beat = (tempo_map.beat_at(from) / split_length).ceil() * split_length;
+
+    let mut clicks = vec![];
+    loop {
+        let timing = tempo_map.precise_timing_in_ms(beat).round().to_integer();
+        if timing >= to {
+            break;
+        }
+
+        let click = if tempo_map.is_measure_start(beat) {
+            Click::Accent
+        } else if beat.is_integer() {
+            Click::Beat
+        } else {
+            Click::Split
+        };
+        clicks.push((timing, click));
+        beat += split_length;
+    }
+
+    clicks
+}
@@ -0,0 +1,456 @@
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguInputState, JangguStick, JangguStroke},
+    song::{GameChart, GameHatNote, GameLongNote, GameNote, GameStrokeNote, TempoMap},
+};
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use num_rational::Rational64;
+
+/// Notes to erase from the existing chart
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum EraseTarget {
+    Stick(JangguStick),
+    Face(JangguFace),
+    Hat,
+}
+
+pub(crate) fn parse_erase_target(arg: &str) -> Result<EraseTarget, String> {
+    match arg {
+        "궁채" => Ok(EraseTarget::Stick(JangguStick::궁채)),
+        "열채" => Ok(EraseTarget::Stick(JangguStick::열채)),
+        "궁편" => Ok(EraseTarget::Face(JangguFace::궁편)),
+        "열편" => Ok(EraseTarget::Face(JangguFace::열편)),
+        "hat" => Ok(EraseTarget::Hat),
+        _ => Err(format!(
+            "Erase target should be one of 궁채, 열채, 궁편, 열편 and hat: {}",
+            arg
+        )),
+    }
+}
+
+impl EraseTarget {
+    fn matches(&self, stick: JangguStick, face: JangguFace) -> bool {
+        match self {
+            EraseTarget::Stick(target) => *target == stick,
+            EraseTarget::Face(target) => *target == face,
+            EraseTarget::Hat => false,
+        }
+    }
+}
+
+/// Range of the beats where the new notes are recorded and the existing notes are erased
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PunchRange {
+    pub(crate) start: Rational64,
+    /// None if the range continues until the end of the song
+    pub(crate) end: Option<Rational64>,
+}
+
+impl PunchRange {
+    pub(crate) fn contains(&self, beat: Rational64) -> bool {
+        match self.end {
+            Some(end) => self.start <= beat && beat < end,
+            None => self.start <= beat,
+        }
+    }
+}
+
+/// Removes the notes of the targets in the range from the chart
+///
+/// The stroke notes lose only the erased stick, e.g. 덩 becomes 덕 when 궁채 is erased.
+pub(crate) fn erase_notes(chart: &mut GameChart, range: &PunchRange, targets: &[EraseTarget]) {
+    let is_erased = |stick: JangguStick, face: JangguFace, beat: Rational64| {
+        range.contains(beat) && targets.iter().any(|x| x.matches(stick, face))
+    };
+
+    chart
+        .left_face
+        .retain(|x| !is_erased(x.stick, JangguFace::궁편, x.beat()));
+    chart
+        .right_face
+        .retain(|x| !is_erased(x.stick, JangguFace::열편, x.beat()));
+    chart
+        .long_notes
+        .retain(|x| !is_erased(x.stick, x.face, x.beat()));
+    if targets.iter().any(|x| matches!(x, EraseTarget::Hat)) {
+        chart.hats.retain(|x| !range.contains(x.beat()));
+    }
+
+    let mut strokes = vec![];
+    for stroke in &chart.strokes {
+        let faces = stroke.stroke.faces();
+        let remaining = JangguInputState {
+            궁채: faces
+                .궁채
+                .filter(|x| !is_erased(JangguStick::궁채, *x, stroke.beat())),
+            열채: faces
+                .열채
+                .filter(|x| !is_erased(JangguStick::열채, *x, stroke.beat())),
+        };
+        if let Some(remaining_stroke) = JangguStroke::from_faces(remaining) {
+            strokes.push(stroke_at(remaining_stroke, stroke.beat()));
+        }
+    }
+    chart.strokes = strokes;
+}
+
+fn stroke_at(stroke: JangguStroke, beat: Rational64) -> GameStrokeNote {
+    let beat_index = beat.floor();
+    let tick = beat - beat_index;
+
+    GameStrokeNote::create_raw_stroke_note(
+        stroke,
+        beat_index.to_integer() as u64,
+        *tick.numer(),
+        *tick.denom(),
+    )
+}
+
+/// Adds the recorded notes into the chart
+///
+/// The existing notes hit by the same stick at the same position are replaced.
+pub(crate) fn merge_notes(
+    chart: &mut GameChart,
+    left_face: Vec<GameNote>,
+    right_face: Vec<GameNote>,
+    long_notes: Vec<GameLongNote>,
+    hats: Vec<GameHatNote>,
+) {
+    let recorded: Vec<(JangguStick, Rational64)> = left_face
+        .iter()
+        .chain(&right_face)
+        .map(|x| (x.stick, x.beat()))
+        .chain(long_notes.iter().map(|x| (x.stick, x.beat())))
+        .collect();
+    let is_replaced = |stick: JangguStick, beat: Rational64| recorded.contains(&(stick, beat));
+
+    chart.left_face.retain(|x| !is_replaced(x.stick, x.beat()));
+    chart.right_face.retain(|x| !is_replaced(x.stick, x.beat()));
+    chart.long_notes.retain(|x| !is_replaced(x.stick, x.beat()));
+    chart
+        .hats
+        .retain(|x| !hats.iter().any(|hat| hat.beat() == x.beat()));
+    let mut strokes = vec![];
+    for stroke in &chart.strokes {
+        let faces = stroke.stroke.faces();
+        let remaining = JangguInputState {
+            궁채: faces
+                .궁채
+                .filter(|_| !is_replaced(JangguStick::궁채, stroke.beat())),
+            열채: faces
+                .열채
+                .filter(|_| !is_replaced(JangguStick::열채, stroke.beat())),
+        };
+        if let Some(remaining_stroke) = JangguStroke::from_faces(remaining) {
+            strokes.push(stroke_at(remaining_stroke, stroke.beat()));
+        }
+    }
+    chart.strokes = strokes;
+
+    chart.left_face.extend(left_face);
+    chart.right_face.extend(right_face);
+    chart.long_notes.extend(long_notes);
+    chart.hats.extend(hats);
+    chart.left_face.sort_by_key(|x| x.beat());
+    chart.right_face.sort_by_key(|x| x.beat());
+    chart.long_notes.sort_by_key(|x| x.beat());
+    chart.hats.sort_by_key(|x| x.beat());
+}
+
+/// Timings (in millisecond from the start of the music) and faces of the hits in the chart,
+/// which are played with the hit sounds
+pub(crate) fn existing_hits(chart: &GameChart, tempo_map: &TempoMap) -> Vec<(u64, JangguFace)> {
+    let mut hits: Vec<(u64, JangguFace)> = chart
+        .left_face
+        .iter()
+        .map(|x| (x.timing_in_ms(tempo_map), JangguFace::궁편))
+        .chain(
+            chart
+                .right_face
+                .iter()
+                .map(|x| (x.timing_in_ms(tempo_map), JangguFace::열편)),
+        )
+        .chain(
+            chart
+                .strokes
+                .iter()
+                .flat_map(|x| x.notes())
+                .map(|x| (x.timing_in_ms(tempo_map), x.face)),
+        )
+        .chain(
+            chart
+                .long_notes
+                .iter()
+                .map(|x| (x.timing_in_ms(tempo_map), x.face)),
+        )
+        .collect();
+    hits.sort_by_key(|x| x.0);
+
+    hits
+}
+
+/// Loads the hit sounds of 궁편 and 열편
+pub(crate) fn load_hit_sounds(directory: &str) -> Option<(StaticSoundData, StaticSoundData)> {
+    let load = |filename: &str| {
+        let path = format!("{}/{}", directory, filename);
+        match StaticSoundData::from_file(&path, StaticSoundSettings::default()) {
+            Ok(sound) => Some(sound),
+            Err(err) => {
+                println!("Failed to load hit sound {}: {}", path, err);
+                None
+            }
+        }
+    };
+
+    Some((load("kung.wav")?, load("deok.wav")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use bidrum_data_struct_lib::{
+        janggu::{JangguFace, JangguStick, JangguStroke},
+        song::{
+            GameBpmChange, GameChart, GameHatNote, GameLongNote, GameLongNoteKind, GameNote,
+            GameStrokeNote,
+        },
+    };
+    use num_rational::Rational64;
+
+    use super::{erase_notes, existing_hits, merge_notes, EraseTarget, PunchRange};
+    use crate::hit_log::HitLog;
+
+    fn beat(numer: i64, denom: i64) -> Rational64 {
+        Rational64::new(numer, denom)
+    }
+
+    fn note(stick: JangguStick, beat_index: u64) -> GameNote {
+        GameNote::create_raw_note(stick, beat_index, 0, 1)
+    }
+
+    fn long_note(stick: JangguStick, face: JangguFace, beat_index: u64) -> GameLongNote {
+        GameLongNote::create_raw_long_note(
+            GameLongNoteKind::Hold,
+            stick,
+            face,
+            beat_index,
+            0,
+            1,
+            beat_index + 1,
+            0,
+            1,
+        )
+    }
+
+    fn positions(notes: &[GameNote]) -> Vec<(JangguStick, Rational64)> {
+        notes.iter().map(|x| (x.stick, x.beat())).collect()
+    }
+
+    fn strokes(chart: &GameChart) -> Vec<(JangguStroke, Rational64)> {
+        chart.strokes.iter().map(|x| (x.stroke, x.beat())).collect()
+    }
+
+    fn hats(chart: &GameChart) -> Vec<Rational64> {
+        chart.hats.iter().map(|x| x.beat()).collect()
+    }
+
+    /// Chart at 120 bpm with the notes inside and outside of the range from 4th to 8th beat
+    fn chart() -> GameChart {
+        let mut chart = HitLog::new(Rational64::from_integer(120), 0, vec![], vec![]).empty_chart();
+        chart.left_face = vec![
+            note(JangguStick::궁채, 2),
+            note(JangguStick::궁채, 5),
+            note(JangguStick::열채, 5),
+        ];
+        chart.right_face = vec![note(JangguStick::열채, 6), note(JangguStick::궁채, 9)];
+        chart.long_notes = vec![long_note(JangguStick::궁채, JangguFace::궁편, 6)];
+        chart.strokes = vec![
+            GameStrokeNote::create_raw_stroke_note(JangguStroke::덩, 5, 1, 2),
+            GameStrokeNote::create_raw_stroke_note(JangguStroke::덩, 10, 0, 1),
+        ];
+        chart.hats = vec![
+            GameHatNote::create_raw_note(5, 0, 1),
+            GameHatNote::create_raw_note(8, 0, 1),
+        ];
+
+        chart
+    }
+
+    fn range() -> PunchRange {
+        PunchRange {
+            start: Rational64::from_integer(4),
+            end: Some(Rational64::from_integer(8)),
+        }
+    }
+
+    #[test]
+    fn range_contains_start_but_not_end() {
+        let range = range();
+        assert!(!range.contains(beat(15, 4)));
+        assert!(range.contains(beat(4, 1)));
+        assert!(range.contains(beat(31, 4)));
+        assert!(!range.contains(beat(8, 1)));
+
+        let open_range = PunchRange {
+            start: Rational64::from_integer(4),
+            end: None,
+        };
+        assert!(!open_range.contains(beat(15, 4)));
+        assert!(open_range.contains(beat(4, 1)));
+        assert!(open_range.contains(beat(1000, 1)));
+    }
+
+    #[test]
+    fn stick_is_erased_in_range() {
+        let mut chart = chart();
+        erase_notes(
+            &mut chart,
+            &range(),
+            &[EraseTarget::Stick(JangguStick::궁채)],
+        );
+
+        assert_eq!(
+            positions(&chart.left_face),
+            vec![
+                (JangguStick::궁채, beat(2, 1)),
+                (JangguStick::열채, beat(5, 1))
+            ]
+        );
+        assert_eq!(
+            positions(&chart.right_face),
+            vec![
+                (JangguStick::열채, beat(6, 1)),
+                (JangguStick::궁채, beat(9, 1))
+            ]
+        );
+        assert!(chart.long_notes.is_empty());
+        // 덩 loses 궁채 and becomes 덕
+        assert_eq!(
+            strokes(&chart),
+            vec![
+                (JangguStroke::덕, beat(11, 2)),
+                (JangguStroke::덩, beat(10, 1))
+            ]
+        );
+        assert_eq!(hats(&chart), vec![beat(5, 1), beat(8, 1)]);
+    }
+
+    #[test]
+    fn face_and_hats_are_erased_in_range() {
+        let mut chart = chart();
+        erase_notes(
+            &mut chart,
+            &range(),
+            &[EraseTarget::Face(JangguFace::열편), EraseTarget::Hat],
+        );
+
+        assert_eq!(chart.left_face.len(), 3);
+        assert_eq!(
+            positions(&chart.right_face),
+            vec![(JangguStick::궁채, beat(9, 1))]
+        );
+        assert_eq!(chart.long_notes.len(), 1);
+        assert_eq!(
+            strokes(&chart),
+            vec![
+                (JangguStroke::쿵, beat(11, 2)),
+                (JangguStroke::덩, beat(10, 1))
+            ]
+        );
+        // the hat at the end of the range is kept
+        assert_eq!(hats(&chart), vec![beat(8, 1)]);
+    }
+
+    #[test]
+    fn merge_replaces_notes_of_same_stick_and_keeps_tempo() {
+        let mut chart = HitLog::new(
+            Rational64::from_integer(120),
+            300,
+            vec![GameBpmChange::create_raw_bpm_change(
+                4,
+                0,
+                1,
+                Rational64::from_integer(60),
+            )],
+            vec![],
+        )
+        .empty_chart();
+        chart.left_face = vec![note(JangguStick::궁채, 2)];
+        chart.strokes = vec![GameStrokeNote::create_raw_stroke_note(
+            JangguStroke::덩,
+            3,
+            0,
+            1,
+        )];
+        chart.hats = vec![
+            GameHatNote::create_raw_note(2, 0, 1),
+            GameHatNote::create_raw_note(4, 0, 1),
+        ];
+
+        merge_notes(
+            &mut chart,
+            vec![note(JangguStick::궁채, 3)],
+            vec![note(JangguStick::열채, 1)],
+            vec![],
+            vec![GameHatNote::create_raw_note(4, 0, 1)],
+        );
+
+        assert_eq!(
+            positions(&chart.left_face),
+            vec![
+                (JangguStick::궁채, beat(2, 1)),
+                (JangguStick::궁채, beat(3, 1))
+            ]
+        );
+        assert_eq!(
+            positions(&chart.right_face),
+            vec![(JangguStick::열채, beat(1, 1))]
+        );
+        // 궁채 of 덩 is replaced by the recorded note
+        assert_eq!(strokes(&chart), vec![(JangguStroke::덕, beat(3, 1))]);
+        assert_eq!(hats(&chart), vec![beat(2, 1), beat(4, 1)]);
+        assert_eq!(chart.delay, 300);
+        assert_eq!(
+            chart
+                .bpm_changes
+                .iter()
+                .map(|x| x.beat())
+                .collect::<Vec<_>>(),
+            vec![beat(4, 1)]
+        );
+    }
+
+    #[test]
+    fn existing_hits_follow_tempo() {
+        let mut chart = HitLog::new(
+            Rational64::from_integer(120),
+            100,
+            vec![GameBpmChange::create_raw_bpm_change(
+                2,
+                0,
+                1,
+                Rational64::from_integer(60),
+            )],
+            vec![],
+        )
+        .empty_chart();
+        chart.left_face = vec![note(JangguStick::궁채, 1)];
+        chart.right_face = vec![note(JangguStick::열채, 2)];
+        chart.strokes = vec![GameStrokeNote::create_raw_stroke_note(
+            JangguStroke::넘겨덩,
+            0,
+            0,
+            1,
+        )];
+        chart.long_notes = vec![long_note(JangguStick::열채, JangguFace::궁편, 3)];
+
+        assert_eq!(
+            existing_hits(&chart, &chart.tempo_map()),
+            vec![
+                (100, JangguFace::열편),
+                (100, JangguFace::궁편),
+                (600, JangguFace::궁편),
+                (1100, JangguFace::열편),
+                (2100, JangguFace::궁편),
+            ]
+        );
+    }
+}
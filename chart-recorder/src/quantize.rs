@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::TempoMap,
+};
+use num_rational::Rational64;
+
+use crate::hit_log::HitLog;
+
+/// Hits farther from the grid line than this ratio of the split are reported
+const OFF_GRID_RATIO: f64 = 0.3;
+
+/// Parses the grid in unit of the note (e.g. 16 for the sixteenth notes)
+pub(crate) fn parse_grid(arg: &str) -> Result<u16, String> {
+    match arg.parse::<u16>() {
+        Ok(grid) if [4, 8, 12, 16, 24].contains(&grid) => Ok(grid),
+        _ => Err(format!(
+            "Grid should be one of 4, 8, 12, 16 and 24: {}",
+            arg
+        )),
+    }
+}
+
+/// Number of the splits per beat of the grid
+pub(crate) fn splits_per_beat(grid: u16) -> i64 {
+    grid as i64 / 4
+}
+
+/// Number of the splits per beat of the triplets which can replace the grid
+///
+/// None if the grid is already the triplets or has no triplets fitting in a beat.
+fn triplet_splits_per_beat(grid: u16) -> Option<i64> {
+    match grid {
+        8 => Some(3),
+        16 => Some(6),
+        _ => None,
+    }
+}
+
+/// Hit of the stick at the grid line
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct QuantizedHit {
+    pub(crate) stick: JangguStick,
+    pub(crate) face: JangguFace,
+    pub(crate) beat: Rational64,
+    /// position where the stick is taken off the face
+    pub(crate) release_beat: Option<Rational64>,
+    /// difference from the grid line in millisecond (positive if the hit is late)
+    pub(crate) offset_in_ms: f64,
+}
+
+/// Hit which is far from any grid line
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OffGridHit {
+    /// None if the hit is the hat
+    pub(crate) stick: Option<JangguStick>,
+    pub(crate) tick: u64,
+    /// grid line where the hit is quantized to
+    pub(crate) beat: Rational64,
+    /// difference from the grid line in millisecond (positive if the hit is late)
+    pub(crate) offset_in_ms: f64,
+}
+
+#[derive(Debug)]
+pub(crate) struct QuantizedHits {
+    pub(crate) hits: Vec<QuantizedHit>,
+    pub(crate) hats: Vec<Rational64>,
+    pub(crate) off_grid_hits: Vec<OffGridHit>,
+}
+
+fn to_f64(value: Rational64) -> f64 {
+    *value.numer() as f64 / *value.denom() as f64
+}
+
+fn beat_of(tempo_map: &TempoMap, tick: u64) -> f64 {
+    to_f64(tempo_map.beat_at(tick as i64))
+}
+
+/// Index of the beat whose splits are used for the position
+///
+/// Slightly early hits belong to the next beat, up to the half of the split of the finest grid.
+fn beat_index_of(beat: f64) -> i64 {
+    (beat + 1.0 / 12.0).floor() as i64
+}
+
+/// Rounds the position to the nearest grid line
+fn round_to_grid(beat: f64, splits: i64) -> Rational64 {
+    Rational64::new((beat * splits as f64).round() as i64, splits)
+}
+
+/// Sum of the squared distances from the nearest grid lines in unit of beat
+fn grid_error(beats: &[f64], splits: i64) -> f64 {
+    beats
+        .iter()
+        .map(|x| (x - to_f64(round_to_grid(*x, splits))).powi(2))
+        .sum()
+}
+
+/// Decides the splits of each beat, which is the triplets if the hits of the beat fit them better
+fn splits_of_beats(beats: &[f64], grid: u16) -> HashMap<i64, i64> {
+    let mut beats_by_index: HashMap<i64, Vec<f64>> = HashMap::new();
+    for beat in beats {
+        beats_by_index
+            .entry(beat_index_of(*beat))
+            .or_default()
+            .push(*beat);
+    }
+
+    let mut splits = HashMap::new();
+    if let Some(triplet_splits) = triplet_splits_per_beat(grid) {
+        for (beat_index, beats) in beats_by_index {
+            let straight_error = grid_error(&beats, splits_per_beat(grid));
+            let triplet_error = grid_error(&beats, triplet_splits);
+            // the triplets should be clearly better, since the hits on the beat fit both
+            if triplet_error * 2.0 < straight_error {
+                splits.insert(beat_index, triplet_splits);
+            }
+        }
+    }
+
+    splits
+}
+
+/// Quantizes the hits in the log into the nearest grid lines
+pub(crate) fn quantize(log: &HitLog, grid: u16) -> QuantizedHits {
+    let tempo_map = log.tempo_map();
+    let onsets: Vec<f64> = log
+        .hits
+        .iter()
+        .map(|x| x.tick)
+        .chain(log.hats.iter().copied())
+        .map(|x| beat_of(&tempo_map, x))
+        .collect();
+    let splits_of_beats = splits_of_beats(&onsets, grid);
+    let splits_at = |beat: f64| {
+        *splits_of_beats
+            .get(&beat_index_of(beat))
+            .unwrap_or(&splits_per_beat(grid))
+    };
+
+    let mut off_grid_hits = vec![];
+    let mut quantize_onset = |stick: Option<JangguStick>, tick: u64| {
+        let splits = splits_at(beat_of(&tempo_map, tick));
+        let beat = round_to_grid(beat_of(&tempo_map, tick), splits);
+        let offset_in_ms = tick as f64 - to_f64(tempo_map.precise_timing_in_ms(beat));
+        let split_length_in_ms = 60000.0 / to_f64(tempo_map.bpm_at(beat)) / splits as f64;
+        if offset_in_ms.abs() > split_length_in_ms * OFF_GRID_RATIO {
+            off_grid_hits.push(OffGridHit {
+                stick: stick,
+                tick: tick,
+                beat: beat,
+                offset_in_ms: offset_in_ms,
+            });
+        }
+
+        (beat, offset_in_ms)
+    };
+
+    let mut hits = vec![];
+    for hit in &log.hits {
+        let (beat, offset_in_ms) = quantize_onset(Some(hit.stick), hit.tick);
+        hits.push(QuantizedHit {
+            stick: hit.stick,
+            face: hit.face,
+            beat: beat,
+            release_beat: hit.release_tick.map(|x| {
+                let beat = beat_of(&tempo_map, x);
+                round_to_grid(beat, splits_at(beat))
+            }),
+            offset_in_ms: offset_in_ms,
+        });
+    }
+    let mut hats = vec![];
+    for hat in &log.hats {
+        hats.push(quantize_onset(None, *hat).0);
+    }
+    off_grid_hits.sort_by_key(|x| x.tick);
+
+    QuantizedHits {
+        hits: hits,
+        hats: hats,
+        off_grid_hits: off_grid_hits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bidrum_data_struct_lib::janggu::{JangguFace, JangguStick};
+    use num_rational::Rational64;
+
+    use super::{beat_index_of, parse_grid, quantize, splits_of_beats};
+    use crate::hit_log::HitLog;
+
+    /// Log at 120 bpm, so a beat is 500ms
+    fn hit_log(hits: &[(JangguStick, u64)], hats: &[u64]) -> HitLog {
+        let mut hit_log = HitLog::new(Rational64::from_integer(120), 0, vec![], vec![]);
+        for (stick, tick) in hits {
+            hit_log.hit(*stick, JangguFace::궁편, *tick);
+        }
+        hit_log.hats = hats.to_vec();
+
+        hit_log
+    }
+
+    fn beats(numerator_and_denominators: &[(i64, i64)]) -> Vec<Rational64> {
+        numerator_and_denominators
+            .iter()
+            .map(|(numer, denom)| Rational64::new(*numer, *denom))
+            .collect()
+    }
+
+    #[test]
+    fn grid_is_parsed() {
+        assert_eq!(parse_grid("16"), Ok(16));
+        assert_eq!(parse_grid("24"), Ok(24));
+        for arg in ["0", "32", "-4", "sixteen", ""] {
+            assert!(parse_grid(arg).is_err(), "{} is accepted", arg);
+        }
+    }
+
+    #[test]
+    fn hits_are_rounded_to_nearest_grid_line() {
+        // quarter notes, so the hits round at the half of the beat
+        let hit_log = hit_log(
+            &[(JangguStick::궁채, 740), (JangguStick::열채, 760)],
+            &[1240],
+        );
+        let quantized = quantize(&hit_log, 4);
+
+        assert_eq!(
+            quantized.hits.iter().map(|x| x.beat).collect::<Vec<_>>(),
+            beats(&[(1, 1), (2, 1)])
+        );
+        assert_eq!(quantized.hits[0].offset_in_ms, 240.0);
+        assert_eq!(quantized.hits[1].offset_in_ms, -240.0);
+        assert_eq!(quantized.hats, beats(&[(2, 1)]));
+    }
+
+    #[test]
+    fn slightly_early_hit_belongs_to_next_beat() {
+        // the half of the split of the 24th notes is 1/12 beat
+        assert_eq!(beat_index_of(5.0), 5);
+        assert_eq!(beat_index_of(5.95), 6);
+        assert_eq!(beat_index_of(5.9), 5);
+        assert_eq!(beat_index_of(-0.05), 0);
+    }
+
+    #[test]
+    fn triplets_are_detected_by_beat() {
+        let splits = splits_of_beats(&[1.98, 2.27, 4.0, 4.334, 4.666], 16);
+        assert_eq!(splits.get(&4), Some(&6));
+        assert_eq!(splits.get(&2), None);
+
+        // the grid of the triplets can't be replaced
+        assert!(splits_of_beats(&[4.0, 4.334, 4.666], 12).is_empty());
+    }
+
+    #[test]
+    fn early_late_and_triplet_hits_are_quantized() {
+        let hit_log = hit_log(
+            &[
+                // early and late sixteenth notes
+                (JangguStick::궁채, 990),
+                (JangguStick::열채, 1135),
+                // triplets
+                (JangguStick::궁채, 2000),
+                (JangguStick::열채, 2167),
+                (JangguStick::궁채, 2333),
+                // early hit on the next beat
+                (JangguStick::열채, 2980),
+                // between the sixteenth note and the triplet
+                (JangguStick::궁채, 3542),
+            ],
+            &[2340],
+        );
+        let quantized = quantize(&hit_log, 16);
+
+        assert_eq!(
+            quantized.hits.iter().map(|x| x.beat).collect::<Vec<_>>(),
+            beats(&[(2, 1), (9, 4), (4, 1), (13, 3), (14, 3), (6, 1), (7, 1)])
+        );
+        assert_eq!(quantized.hits[0].offset_in_ms, -10.0);
+        assert_eq!(quantized.hits[1].offset_in_ms, 10.0);
+        // the hat follows the splits of the beat as well
+        assert_eq!(quantized.hats, beats(&[(14, 3)]));
+
+        assert_eq!(quantized.off_grid_hits.len(), 1);
+        let off_grid_hit = quantized.off_grid_hits[0];
+        assert_eq!(off_grid_hit.stick, Some(JangguStick::궁채));
+        assert_eq!(off_grid_hit.tick, 3542);
+        assert_eq!(off_grid_hit.beat, Rational64::from_integer(7));
+        assert_eq!(off_grid_hit.offset_in_ms, 42.0);
+    }
+
+    #[test]
+    fn off_grid_hits_and_hats_are_reported_in_order() {
+        // 150ms is the limit of the quarter notes
+        let log = hit_log(
+            &[(JangguStick::궁채, 1160), (JangguStick::열채, 1640)],
+            &[660, 1500],
+        );
+        let quantized = quantize(&log, 4);
+
+        assert_eq!(
+            quantized
+                .off_grid_hits
+                .iter()
+                .map(|x| (x.stick, x.tick))
+                .collect::<Vec<_>>(),
+            vec![(None, 660), (Some(JangguStick::궁채), 1160)]
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::ops::Range;
+
+use bidrum_data_struct_lib::janggu::{JangguFace, JangguStick};
+use clap::ValueEnum;
+use kira::{
+    clock::ClockSpeed,
+    manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings},
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+};
+use num_rational::Rational64;
+
+use crate::{
+    hat_state_with_tick::{HatInput, HatStateWithTick},
+    hit_log::HitLog,
+    janggu_state_with_tick::JangguStateWithTick,
+    metronome::{clicks, Click, Metronome},
+    overdub::load_hit_sounds,
+    quantize::quantize,
+};
+
+/// Time to initialize before the count-in starts, in millisecond
+const LEAD_IN: u64 = 1000;
+
+/// Section of the song repeated in the loop mode, in unit of beat
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoopSection {
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+}
+
+pub(crate) fn parse_loop_section(arg: &str) -> Result<LoopSection, String> {
+    let (start, end) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("Loop section should be in the format of START:END: {}", arg))?;
+    let start: u64 = start
+        .parse()
+        .map_err(|_| format!("Invalid start beat of loop section: {}", arg))?;
+    let end: u64 = end
+        .parse()
+        .map_err(|_| format!("Invalid end beat of loop section: {}", arg))?;
+    if start >= end {
+        return Err(format!(
+            "Start of loop section should be before the end: {}",
+            arg
+        ));
+    }
+
+    Ok(LoopSection {
+        start: start,
+        end: end,
+    })
+}
+
+/// How to make the hits from the passes of the loop
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum TakeSelection {
+    /// hits of all passes are merged
+    Merge,
+    /// hits of the pass which is the closest to the grid are kept
+    Best,
+}
+
+/// Options of the recording session
+pub(crate) struct SessionOptions<'a> {
+    pub(crate) music_path: &'a str,
+    /// beat where the recording starts, after the count-in
+    pub(crate) start_beat: u64,
+    /// beats of the count-in before the start beat
+    pub(crate) count_in: u16,
+    /// splits per beat of the metronome, None if the metronome is off
+    pub(crate) metronome_splits: Option<u16>,
+    pub(crate) loop_section: Option<LoopSection>,
+    pub(crate) loop_passes: u16,
+    /// timings (in millisecond from the start of the music) and faces of the hits to play
+    pub(crate) hits_to_play: Vec<(u64, JangguFace)>,
+    pub(crate) hit_sound_dir: &'a str,
+    pub(crate) hat: Option<HatInput>,
+    /// delay of input device(or you) in milliseconds
+    pub(crate) input_delay: i16,
+}
+
+/// Part of the music played in the session
+struct Pass {
+    /// clock tick when the music is at `from`
+    tick: u64,
+    /// position of the music in millisecond, which is negative before the music starts
+    from: i64,
+    to: i64,
+}
+
+impl Pass {
+    fn tick_of(&self, position: i64) -> u64 {
+        (self.tick as i64 + position - self.from) as u64
+    }
+
+    fn end_tick(&self) -> u64 {
+        self.tick_of(self.to)
+    }
+
+    fn position_at(&self, tick: u64) -> i64 {
+        tick as i64 - self.tick as i64 + self.from
+    }
+}
+
+enum ScheduledSound {
+    /// part of the music in second
+    Music(Range<f64>),
+    Click(Click),
+    Hit(JangguFace),
+}
+
+/// Plays the music and records the hits into the log
+///
+/// Returns the logs of each pass, which is only one without the loop.
+pub(crate) fn record(options: &SessionOptions, hit_log: HitLog) -> Vec<HitLog> {
+    // Init kira backend
+    println!("initializing kira backend");
+    let mut manager = AudioManager::<DefaultBackend>::new(AudioManagerSettings::default())
+        .expect("kira AudioManager initialization failure");
+    let clock_handle = manager
+        .add_clock(ClockSpeed::TicksPerSecond(1000.0))
+        .expect("kira clock add failure");
+    let clock_start = clock_handle.time();
+
+    // Load music
+    let music = StaticSoundData::from_file(options.music_path, StaticSoundSettings::default())
+        .expect("Failed to load music");
+    let music_duration = music.duration().as_millis() as i64;
+    let hit_sounds = if options.hits_to_play.is_empty() {
+        None
+    } else {
+        load_hit_sounds(options.hit_sound_dir)
+    };
+    let metronome = Metronome::new();
+
+    // tick is counted from the first beat, so delay is added to get the position of the music
+    let tempo_map = hit_log.tempo_map();
+    let delay = hit_log.delay as i64;
+    let position_of = |beat: i64| {
+        delay
+            + tempo_map
+                .precise_timing_in_ms(Rational64::from_integer(beat))
+                .round()
+                .to_integer()
+    };
+
+    // the first pass has the count-in, and the others repeat the loop section
+    let start_position = position_of(options.start_beat as i64);
+    let mut passes = vec![Pass {
+        tick: LEAD_IN,
+        from: position_of(options.start_beat as i64 - options.count_in as i64),
+        to: match options.loop_section {
+            Some(loop_section) => position_of(loop_section.end as i64),
+            None => music_duration,
+        },
+    }];
+    if let Some(loop_section) = options.loop_section {
+        for _ in 1..options.loop_passes {
+            passes.push(Pass {
+                tick: passes.last().unwrap().end_tick(),
+                from: position_of(loop_section.start as i64),
+                to: position_of(loop_section.end as i64),
+            });
+        }
+    }
+
+    let mut schedule: Vec<(u64, ScheduledSound)> = vec![];
+    for pass in &passes {
+        let music_from = pass.from.max(0);
+        schedule.push((
+            pass.tick_of(music_from),
+            ScheduledSound::Music(music_from as f64 / 1000.0..pass.to as f64 / 1000.0),
+        ));
+        for (timing, face) in &options.hits_to_play {
+            let position = *timing as i64;
+            if pass.from <= position && position < pass.to {
+                schedule.push((pass.tick_of(position), ScheduledSound::Hit(*face)));
+            }
+        }
+        // clicks of the count-in are always played
+        for (timing, click) in clicks(
+            &tempo_map,
+            pass.from - delay,
+            pass.to - delay,
+            options.metronome_splits.unwrap_or(1),
+        ) {
+            let position = timing + delay;
+            if options.metronome_splits.is_some() || position < start_position {
+                schedule.push((pass.tick_of(position), ScheduledSound::Click(click)));
+            }
+        }
+    }
+    schedule.sort_by_key(|x| x.0);
+
+    let mut takes: Vec<HitLog> = passes.iter().map(|_| hit_log.clone()).collect();
+    let mut janggu_state = JangguStateWithTick::new();
+    let mut hat_state = options.hat.map(HatStateWithTick::new);
+    let mut next_sound = 0;
+    let mut current_pass = 0;
+    let end_tick = passes.last().unwrap().end_tick();
+
+    // Start music
+    println!(
+        "music will start after {:.2} sec",
+        passes[0].tick_of(passes[0].from.max(0)) as f32 / 1000.0
+    );
+    clock_handle.start().expect("Failed to start kira clock");
+    loop {
+        let elapsed = clock_handle.time().ticks;
+        if elapsed > end_tick {
+            break;
+        }
+
+        // schedule the sounds a bit earlier
+        while next_sound < schedule.len() && schedule[next_sound].0 < elapsed + 500 {
+            let (tick, sound) = &schedule[next_sound];
+            let settings = StaticSoundSettings::new().start_time(clock_start + *tick);
+            let sound = match sound {
+                ScheduledSound::Music(region) => {
+                    music.with_settings(settings.playback_region(region.clone()))
+                }
+                ScheduledSound::Click(click) => metronome.sound(*click).with_settings(settings),
+                ScheduledSound::Hit(face) => match &hit_sounds {
+                    Some((kung, deok)) => match face {
+                        JangguFace::궁편 => kung.with_settings(settings),
+                        JangguFace::열편 => deok.with_settings(settings),
+                    },
+                    None => {
+                        next_sound += 1;
+                        continue;
+                    }
+                },
+            };
+            manager.play(sound).expect("Failed to play sound");
+            next_sound += 1;
+        }
+
+        while current_pass + 1 < passes.len() && passes[current_pass + 1].tick <= elapsed {
+            current_pass += 1;
+            println!("pass {} / {}", current_pass + 1, passes.len());
+        }
+
+        let tick = {
+            let position = passes[current_pass].position_at(elapsed);
+            if current_pass == 0 && position < start_position {
+                println!(
+                    "chart recording will start after {:.2} sec",
+                    (start_position - position) as f32 / 1000.0,
+                );
+                continue;
+            }
+
+            let delayed_tick = position - delay - options.input_delay as i64;
+
+            if delayed_tick < 0 {
+                println!("delayed...");
+                continue;
+            }
+
+            delayed_tick as u64
+        };
+        let beat = tempo_map.beat_at(tick as i64);
+        let beat = *beat.numer() as f64 / *beat.denom() as f64;
+        let hit_log = &mut takes[current_pass];
+
+        let previous_janggu_state = (janggu_state.궁채, janggu_state.열채);
+        janggu_state.update(tick);
+        for (stick, previous, current) in [
+            (
+                JangguStick::궁채,
+                previous_janggu_state.0,
+                janggu_state.궁채,
+            ),
+            (
+                JangguStick::열채,
+                previous_janggu_state.1,
+                janggu_state.열채,
+            ),
+        ] {
+            if let Some(face) = hit_log.record_stick(stick, previous, current, tick) {
+                println!("beat: {:.3} : {:?} -> {:?}", beat, stick, face);
+            }
+        }
+        if let Some(hat_state) = &mut hat_state {
+            hat_state.update();
+            if hat_state.is_spin_started_now {
+                hit_log.hats.push(tick);
+                println!("beat: {:.3} : hat", beat);
+            }
+        }
+    }
+
+    takes
+}
+
+/// Makes one log from the logs of the passes
+pub(crate) fn select_take(mut takes: Vec<HitLog>, selection: TakeSelection, grid: u16) -> HitLog {
+    match selection {
+        TakeSelection::Merge => {
+            let mut merged = takes.remove(0);
+            for take in takes {
+                merged.hits.extend(take.hits);
+                merged.hats.extend(take.hats);
+            }
+            merged.hits.sort_by_key(|x| x.tick);
+            merged.hats.sort();
+
+            merged
+        }
+        TakeSelection::Best => {
+            // the take with more hits on the grid is better, and then the take closer to the grid
+            let mut best: Option<(usize, usize, f64)> = None;
+            for (idx, take) in takes.iter().enumerate() {
+                let quantized = quantize(take, grid);
+                let hit_count = quantized.hits.len() + quantized.hats.len();
+                let on_grid = hit_count - quantized.off_grid_hits.len();
+                let mean_offset = if quantized.hits.is_empty() {
+                    0.0
+                } else {
+                    quantized
+                        .hits
+                        .iter()
+                        .map(|x| x.offset_in_ms.abs())
+                        .sum::<f64>()
+                        / quantized.hits.len() as f64
+                };
+                println!(
+                    "take {}: {} hits, {} off the grid, mean offset {:.1} ms",
+                    idx + 1,
+                    hit_count,
+                    hit_count - on_grid,
+                    mean_offset
+                );
+
+                let is_better = match best {
+                    Some((_, best_on_grid, best_mean_offset)) => {
+                        on_grid > best_on_grid
+                            || (on_grid == best_on_grid && mean_offset < best_mean_offset)
+                    }
+                    None => true,
+                };
+                if is_better {
+                    best = Some((idx, on_grid, mean_offset));
+                }
+            }
+
+            let best_idx = best.expect("There is at least one take").0;
+            println!("take {} is kept", best_idx + 1);
+            takes.swap_remove(best_idx)
+        }
+    }
+}
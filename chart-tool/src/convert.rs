@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bidrum_data_struct_lib::{export::export_osu, song::GameChart, song::GameSong};
+use clap::{Args, ValueEnum};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// osu!taiko beatmap
+    Osu,
+    /// text notation, which can be converted back with `from-text`
+    Text,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ExportArgs {
+    /// Song directory (which has info.json)
+    song_directory: String,
+
+    /// Level of the chart to export
+    #[arg(short, long)]
+    level: u32,
+
+    /// Format to export
+    #[arg(short, long, value_enum)]
+    format: ExportFormat,
+
+    /// File to write (standard output if not given)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct FromTextArgs {
+    /// Chart in the text notation
+    input: PathBuf,
+
+    /// Chart json to write (e.g. `music/song/1.json`, standard output if not given)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn write_output(output: &Option<PathBuf>, contents: &str) -> Result<(), String> {
+    match output {
+        Some(path) => fs::write(path, contents)
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err)),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+fn export_chart(args: &ExportArgs) -> Result<(), String> {
+    let song =
+        GameSong::get_song(Path::new(&args.song_directory)).map_err(|err| err.to_string())?;
+    let chart = song.get_chart(args.level).map_err(|err| err.to_string())?;
+
+    let contents = match args.format {
+        ExportFormat::Osu => export_osu(
+            &chart,
+            &song.title,
+            &file_name(&song.audio_filename),
+            Some(&file_name(&song.cover_image_filename)),
+        ),
+        ExportFormat::Text => chart.to_notation()?,
+    };
+
+    write_output(&args.output, &contents)
+}
+
+/// Exports the chart into the other format
+///
+/// Returns false if the export failed
+pub(crate) fn export(args: ExportArgs) -> bool {
+    match export_chart(&args) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
+
+fn convert_text(args: &FromTextArgs) -> Result<(), String> {
+    let source = fs::read_to_string(&args.input)
+        .map_err(|err| format!("Failed to read {}: {}", args.input.display(), err))?;
+    let chart = GameChart::from_notation(&source)
+        .map_err(|err| format!("{}: {}", args.input.display(), err))?;
+    let mut contents = serde_json::to_string_pretty(&chart).expect("Chart is always serializable");
+    contents.push('\n');
+
+    write_output(&args.output, &contents)
+}
+
+/// Converts the chart in the text notation back into the chart json
+///
+/// Returns false if the conversion failed
+pub(crate) fn from_text(args: FromTextArgs) -> bool {
+    match convert_text(&args) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bidrum_data_struct_lib::{
+    import::{import_chart, DrumrollImport, ImportOptions, ImportedChart},
+    song::GameSongCategory,
+};
+use clap::Args;
+use serde_json::json;
+
+use crate::lint::lint_song;
+
+#[derive(Args, Debug)]
+pub(crate) struct ImportArgs {
+    /// Charts of osu!taiko (.osu), BMS (.bms, .bme, .bml, .pms) or DTXMania (.dtx),
+    /// which become the levels in the given order
+    #[arg(required = true)]
+    charts: Vec<PathBuf>,
+
+    /// Song directory to create
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Level of the first chart
+    #[arg(long, default_value_t = 1)]
+    first_level: u32,
+
+    /// Audio file to use instead of the one referenced by the first chart
+    #[arg(long)]
+    audio: Option<PathBuf>,
+
+    /// Cover image to use instead of the one referenced by the first chart
+    #[arg(long)]
+    cover: Option<PathBuf>,
+
+    /// Category of the song (Kpop, TraditionalKpop, Jpop, Varierty)
+    #[arg(long, default_value = "Varierty")]
+    category: String,
+
+    /// Do not import the drumrolls as roll notes
+    #[arg(long)]
+    drop_drumrolls: bool,
+
+    /// Snap the notes to the grid of 1/N beat (for the charts which store the timing in millisecond)
+    #[arg(long, default_value_t = 48)]
+    quantization: i64,
+
+    /// Overwrite the song directory if it already exists
+    #[arg(long)]
+    force: bool,
+}
+
+/// Finds the asset referenced by the chart
+///
+/// The files with the same name and the other extension are tried, too,
+/// since the charts often reference `.wav` while `.ogg` is shipped.
+fn find_asset(chart_path: &Path, filename: &str, extensions: &[&str]) -> Option<PathBuf> {
+    let path = chart_path.parent().unwrap_or(Path::new(".")).join(filename);
+    if path.is_file() {
+        return Some(path);
+    }
+
+    extensions
+        .iter()
+        .map(|x| path.with_extension(x))
+        .find(|x| x.is_file())
+}
+
+/// Copies the asset into the song directory, and returns its file name
+fn copy_asset(asset: &Path, output: &Path) -> Result<String, String> {
+    let filename = asset
+        .file_name()
+        .ok_or_else(|| format!("Invalid file name: {}", asset.display()))?;
+    fs::copy(asset, output.join(filename))
+        .map_err(|err| format!("Failed to copy {}: {}", asset.display(), err))?;
+
+    Ok(filename.to_string_lossy().to_string())
+}
+
+fn import_song(args: &ImportArgs) -> Result<(), String> {
+    let category: GameSongCategory = serde_json::from_value(json!(args.category))
+        .map_err(|_| format!("Unknown category: {}", args.category))?;
+    let options = ImportOptions {
+        drumrolls: if args.drop_drumrolls {
+            DrumrollImport::Drop
+        } else {
+            DrumrollImport::Roll
+        },
+        quantization: args.quantization.max(1),
+    };
+
+    let mut charts: Vec<ImportedChart> = vec![];
+    for path in &args.charts {
+        let chart =
+            import_chart(path, &options).map_err(|err| format!("{}: {}", path.display(), err))?;
+        for warning in &chart.warnings {
+            println!("{}: {}", path.display(), warning);
+        }
+        charts.push(chart);
+    }
+    let first_chart_path = &args.charts[0];
+    let first_chart = &charts[0];
+
+    let audio = match (&args.audio, &first_chart.audio_filename) {
+        (Some(audio), _) => audio.clone(),
+        (None, Some(filename)) => {
+            find_asset(first_chart_path, filename, &["ogg", "mp3", "wav", "flac"])
+                .ok_or_else(|| format!("Audio file {} does not exist", filename))?
+        }
+        (None, None) => {
+            return Err("The chart has no audio file, give it with --audio".to_string());
+        }
+    };
+    let cover = match (&args.cover, &first_chart.cover_image_filename) {
+        (Some(cover), _) => cover.clone(),
+        (None, Some(filename)) => {
+            find_asset(first_chart_path, filename, &["png", "jpg", "jpeg", "bmp"])
+                .ok_or_else(|| format!("Cover image {} does not exist", filename))?
+        }
+        (None, None) => {
+            return Err("The chart has no cover image, give it with --cover".to_string());
+        }
+    };
+    for (path, chart) in args.charts.iter().zip(&charts).skip(1) {
+        if args.audio.is_none() && chart.audio_filename != first_chart.audio_filename {
+            println!(
+                "{}: The audio file is different from the first chart, {} is used",
+                path.display(),
+                audio.display()
+            );
+        }
+    }
+
+    if args.output.join("info.json").exists() && !args.force {
+        return Err(format!(
+            "{} already exists, give --force to overwrite it",
+            args.output.display()
+        ));
+    }
+    fs::create_dir_all(&args.output)
+        .map_err(|err| format!("Failed to create {}: {}", args.output.display(), err))?;
+
+    let audio_filename = copy_asset(&audio, &args.output)?;
+    let cover_image_filename = copy_asset(&cover, &args.output)?;
+
+    let mut levels = vec![];
+    for (index, chart) in charts.iter().enumerate() {
+        let level = args.first_level + index as u32;
+        let chart_path = args.output.join(format!("{}.json", level));
+        let chart_json =
+            serde_json::to_string_pretty(&chart.chart).expect("Chart is always serializable");
+        fs::write(&chart_path, chart_json)
+            .map_err(|err| format!("Failed to write {}: {}", chart_path.display(), err))?;
+        levels.push(level);
+    }
+
+    let info = json!({
+        "title": first_chart.title,
+        "artist": first_chart.chart.artist,
+        "category": category,
+        "audio_filename": audio_filename,
+        "video_filename": null,
+        "cover_image_filename": cover_image_filename,
+        "levels": levels,
+    });
+    let info_path = args.output.join("info.json");
+    fs::write(
+        &info_path,
+        serde_json::to_string_pretty(&info).expect("Json value is always serializable"),
+    )
+    .map_err(|err| format!("Failed to write {}: {}", info_path.display(), err))?;
+
+    Ok(())
+}
+
+/// Imports the charts of the other rhythm games into a new song directory
+///
+/// Returns false if the import failed or the imported charts have errors
+pub(crate) fn import(args: ImportArgs) -> bool {
+    if let Err(err) = import_song(&args) {
+        println!("{}", err);
+        return false;
+    }
+
+    // the imported charts are linted to show the problems to fix by hand
+    let output = args.output.to_string_lossy().to_string();
+    let (warnings, errors) = lint_song(&output, false);
+    println!(
+        "{} chart(s) imported into {}: {} error(s), {} warning(s)",
+        args.charts.len(),
+        output,
+        errors,
+        warnings
+    );
+
+    errors == 0
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use bidrum_data_struct_lib::song::{
+    validation::{validate_chart, ChartDiagnostic, Severity},
+    GameSong,
+};
+use clap::Args;
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+
+#[derive(Args, Debug)]
+pub(crate) struct LintArgs {
+    /// Song directories (which have info.json) to check
+    #[arg(required = true)]
+    song_directories: Vec<String>,
+
+    /// Do not decode the audio file
+    /// (notes after the end of the audio are not checked)
+    #[arg(long)]
+    no_audio: bool,
+
+    /// Treat warnings as errors
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+fn print_diagnostic(location: &str, diagnostic: &ChartDiagnostic) {
+    println!("{}: {}", location, diagnostic);
+}
+
+/// Lints the song directory and returns the number of warnings and errors
+pub(crate) fn lint_song(song_directory: &str, no_audio: bool) -> (usize, usize) {
+    let mut warnings = 0;
+    let mut errors = 0;
+    let mut report = |location: &str, diagnostic: ChartDiagnostic| {
+        match diagnostic.severity {
+            Severity::Warning => warnings += 1,
+            Severity::Error => errors += 1,
+        }
+        print_diagnostic(location, &diagnostic);
+    };
+    let song_error = |message: String| ChartDiagnostic {
+        severity: Severity::Error,
+        note_id: None,
+        beat: None,
+        message: message,
+    };
+
+    let song = match GameSong::get_song(Path::new(song_directory)) {
+        Ok(song) => song,
+        Err(err) => {
+            report(song_directory, song_error(err.to_string()));
+            return (warnings, errors);
+        }
+    };
+
+    // get the length of the audio to check the notes after the end of the audio
+    let audio_duration_in_ms = if no_audio {
+        None
+    } else {
+        match StaticSoundData::from_file(&song.audio_filename, StaticSoundSettings::default()) {
+            Ok(sound) => Some(sound.duration().as_millis() as u64),
+            Err(err) => {
+                report(
+                    song_directory,
+                    song_error(format!("Failed to decode {}: {}", song.audio_filename, err)),
+                );
+                None
+            }
+        }
+    };
+
+    // check the chart files
+    let mut chart_levels = song.get_chart_levels().unwrap_or_default();
+    chart_levels.sort();
+    for level in &chart_levels {
+        let location = format!("{}/{}.json", song_directory, level);
+        if !song.levels.contains(level) {
+            report(
+                &location,
+                ChartDiagnostic {
+                    severity: Severity::Warning,
+                    note_id: None,
+                    beat: None,
+                    message: "The chart is not in the levels of info.json".to_string(),
+                },
+            );
+        }
+
+        match song.get_chart(*level) {
+            Ok(chart) => {
+                for diagnostic in validate_chart(&chart, audio_duration_in_ms) {
+                    report(&location, diagnostic);
+                }
+            }
+            Err(err) => report(&location, song_error(err.to_string())),
+        }
+    }
+
+    (warnings, errors)
+}
+
+/// Lints the song directories
+///
+/// Returns false if there's an error (or a warning when `--deny-warnings` is given)
+pub(crate) fn lint(args: LintArgs) -> bool {
+    let mut total_warnings = 0;
+    let mut total_errors = 0;
+
+    for song_directory in &args.song_directories {
+        let (warnings, errors) = lint_song(song_directory, args.no_audio);
+        total_warnings += warnings;
+        total_errors += errors;
+    }
+
+    println!(
+        "{} song(s) checked: {} error(s), {} warning(s)",
+        args.song_directories.len(),
+        total_errors,
+        total_warnings
+    );
+
+    total_errors == 0 && (!args.deny_warnings || total_warnings == 0)
+}
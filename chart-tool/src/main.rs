@@ -0,0 +1,44 @@
+mod convert;
+mod import;
+mod lint;
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+/// Command line tools for bidrum charts
+#[derive(Parser, Debug)]
+#[command(name = "bidrum-chart", version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks the charts in the song directories before the game loads them
+    Lint(lint::LintArgs),
+    /// Imports the charts of osu!taiko, BMS and DTXMania into a new song directory
+    Import(import::ImportArgs),
+    /// Exports the chart as osu!taiko beatmap or the text notation
+    Export(convert::ExportArgs),
+    /// Converts the chart in the text notation back into the chart json
+    FromText(convert::FromTextArgs),
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let success = match args.command {
+        Command::Lint(lint_args) => lint::lint(lint_args),
+        Command::Import(import_args) => import::import(import_args),
+        Command::Export(export_args) => convert::export(export_args),
+        Command::FromText(from_text_args) => convert::from_text(from_text_args),
+    };
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
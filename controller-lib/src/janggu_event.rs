@@ -0,0 +1,256 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState, JangguStick};
+
+/// Events older than this many are dropped if nobody reads them
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Whether the stick started or stopped touching the face
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JangguEdge {
+    Down,
+    Up,
+}
+
+/// Change of the stick, timestamped when the reader thread received it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JangguInputEvent {
+    pub stick: JangguStick,
+    pub face: JangguFace,
+    pub edge: JangguEdge,
+    pub timestamp: Instant,
+}
+
+/// Queue of the janggu input events shared between threads
+///
+/// The oldest events are dropped when the queue is full, so it's fine not to read it.
+#[derive(Debug, Clone)]
+pub struct JangguEventQueue {
+    events: Arc<Mutex<VecDeque<JangguInputEvent>>>,
+}
+
+impl JangguEventQueue {
+    pub fn new() -> JangguEventQueue {
+        JangguEventQueue {
+            events: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn push(&self, event: JangguInputEvent) {
+        let mut events = self.events.lock().expect("Janggu event queue is poisoned");
+        if events.len() >= MAX_QUEUED_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Takes all the events in the order they happened
+    pub fn drain(&self) -> Vec<JangguInputEvent> {
+        let mut events = self.events.lock().expect("Janggu event queue is poisoned");
+        events.drain(..).collect()
+    }
+}
+
+/// Parses the janggu bits of the controller
+///
+/// bit 0, 1: 궁채 on 궁편, 열편
+/// bit 2, 3: 열채 on 궁편, 열편
+pub(crate) fn bits_to_janggu_input_state(bits: u8) -> JangguInputState {
+    JangguInputState {
+        궁채: if bits & 1 != 0 {
+            Some(JangguFace::궁편)
+        } else if bits & 2 != 0 {
+            Some(JangguFace::열편)
+        } else {
+            None
+        },
+        열채: if bits & 4 != 0 {
+            Some(JangguFace::궁편)
+        } else if bits & 8 != 0 {
+            Some(JangguFace::열편)
+        } else {
+            None
+        },
+    }
+}
+
+/// Janggu bits of the controller from the input state
+pub(crate) fn janggu_input_state_to_bits(state: JangguInputState) -> u8 {
+    let mut bits = match state.궁채 {
+        Some(JangguFace::궁편) => 1,
+        Some(JangguFace::열편) => 2,
+        None => 0,
+    };
+    bits |= match state.열채 {
+        Some(JangguFace::궁편) => 4,
+        Some(JangguFace::열편) => 8,
+        None => 0,
+    };
+
+    bits
+}
+
+/// Events of the change between the janggu bits
+///
+/// If the stick moves to the other face, it's released from the previous face first.
+pub(crate) fn janggu_events_between(
+    previous: u8,
+    current: u8,
+    timestamp: Instant,
+) -> Vec<JangguInputEvent> {
+    let previous = bits_to_janggu_input_state(previous);
+    let current = bits_to_janggu_input_state(current);
+
+    let mut events = vec![];
+    for (stick, previous_face, current_face) in [
+        (JangguStick::궁채, previous.궁채, current.궁채),
+        (JangguStick::열채, previous.열채, current.열채),
+    ] {
+        if previous_face == current_face {
+            continue;
+        }
+
+        if let Some(face) = previous_face {
+            events.push(JangguInputEvent {
+                stick: stick,
+                face: face,
+                edge: JangguEdge::Up,
+                timestamp: timestamp,
+            });
+        }
+        if let Some(face) = current_face {
+            events.push(JangguInputEvent {
+                stick: stick,
+                face: face,
+                edge: JangguEdge::Down,
+                timestamp: timestamp,
+            });
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState, JangguStick};
+
+    use super::{
+        bits_to_janggu_input_state, janggu_events_between, janggu_input_state_to_bits, JangguEdge,
+        JangguEventQueue, JangguInputEvent, MAX_QUEUED_EVENTS,
+    };
+
+    fn event(index: u64, started_at: Instant) -> JangguInputEvent {
+        JangguInputEvent {
+            stick: JangguStick::궁채,
+            face: JangguFace::궁편,
+            edge: [JangguEdge::Down, JangguEdge::Up][index as usize % 2],
+            timestamp: started_at + Duration::from_millis(index),
+        }
+    }
+
+    fn edges(events: &[JangguInputEvent]) -> Vec<(JangguStick, JangguFace, JangguEdge)> {
+        events.iter().map(|x| (x.stick, x.face, x.edge)).collect()
+    }
+
+    #[test]
+    fn queue_drains_events_in_order() {
+        let started_at = Instant::now();
+        let queue = JangguEventQueue::new();
+        assert!(queue.drain().is_empty());
+
+        for index in 0..3 {
+            queue.push(event(index, started_at));
+        }
+        assert_eq!(
+            queue.drain(),
+            (0..3).map(|x| event(x, started_at)).collect::<Vec<_>>()
+        );
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn queue_drops_oldest_events_when_full() {
+        let started_at = Instant::now();
+        let queue = JangguEventQueue::new();
+        // the clone shares the events, as the reader thread does
+        let reader = queue.clone();
+
+        for index in 0..MAX_QUEUED_EVENTS as u64 {
+            queue.push(event(index, started_at));
+        }
+        assert_eq!(reader.drain().len(), MAX_QUEUED_EVENTS);
+
+        for index in 0..MAX_QUEUED_EVENTS as u64 + 10 {
+            queue.push(event(index, started_at));
+        }
+        let events = reader.drain();
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(events[0], event(10, started_at));
+        assert_eq!(
+            events[MAX_QUEUED_EVENTS - 1],
+            event(MAX_QUEUED_EVENTS as u64 + 9, started_at)
+        );
+    }
+
+    #[test]
+    fn no_events_without_change() {
+        for bits in [0, 1, 6, 9] {
+            assert!(janggu_events_between(bits, bits, Instant::now()).is_empty());
+        }
+    }
+
+    #[test]
+    fn events_between_bits() {
+        let timestamp = Instant::now();
+
+        // 궁채 hits 궁편, and 열채 hits 열편
+        let events = janggu_events_between(0, 1 | 8, timestamp);
+        assert_eq!(
+            edges(&events),
+            vec![
+                (JangguStick::궁채, JangguFace::궁편, JangguEdge::Down),
+                (JangguStick::열채, JangguFace::열편, JangguEdge::Down),
+            ]
+        );
+        assert!(events.iter().all(|x| x.timestamp == timestamp));
+
+        // 궁채 moves to 열편 while 열채 is kept
+        assert_eq!(
+            edges(&janggu_events_between(1 | 8, 2 | 8, timestamp)),
+            vec![
+                (JangguStick::궁채, JangguFace::궁편, JangguEdge::Up),
+                (JangguStick::궁채, JangguFace::열편, JangguEdge::Down),
+            ]
+        );
+
+        // both are released
+        assert_eq!(
+            edges(&janggu_events_between(2 | 8, 0, timestamp)),
+            vec![
+                (JangguStick::궁채, JangguFace::열편, JangguEdge::Up),
+                (JangguStick::열채, JangguFace::열편, JangguEdge::Up),
+            ]
+        );
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        for 궁채 in [None, Some(JangguFace::궁편), Some(JangguFace::열편)] {
+            for 열채 in [None, Some(JangguFace::궁편), Some(JangguFace::열편)] {
+                let state = JangguInputState {
+                    궁채: 궁채,
+                    열채: 열채,
+                };
+                let round_trip = bits_to_janggu_input_state(janggu_input_state_to_bits(state));
+                assert_eq!((round_trip.궁채, round_trip.열채), (궁채, 열채));
+            }
+        }
+    }
+}
@@ -4,27 +4,36 @@ use std::{
         Arc, RwLock,
     },
     thread,
+    time::Instant,
 };
 
-use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState};
+use bidrum_data_struct_lib::janggu::JangguInputState;
 use device_query::{DeviceQuery, DeviceState, Keycode};
 
-use crate::JangguDevice;
+use crate::{
+    janggu_event::{
+        bits_to_janggu_input_state, janggu_events_between, JangguEventQueue, JangguInputEvent,
+    },
+    JangguDevice,
+};
 
 pub struct KeyboardJangguDevice {
     stopping: Arc<AtomicBool>,
     // Using RwLock<JangguInputState> is too slow
     state: Arc<AtomicU8>,
+    events: JangguEventQueue,
 }
 
 impl KeyboardJangguDevice {
     pub fn new() -> KeyboardJangguDevice {
         let stopping = Arc::new(AtomicBool::new(false));
         let state = Arc::new(AtomicU8::new(0));
+        let events = JangguEventQueue::new();
 
         {
             let stopping = stopping.clone();
             let state = state.clone();
+            let events = events.clone();
 
             thread::spawn(move || {
                 let mut device_states = DeviceState::new();
@@ -33,7 +42,11 @@ impl KeyboardJangguDevice {
                         break;
                     }
 
-                    state.store(keyboard_to_bits(&mut device_states), Ordering::Relaxed);
+                    let bits = keyboard_to_bits(&mut device_states);
+                    let previous = state.swap(bits, Ordering::Relaxed);
+                    for event in janggu_events_between(previous, bits, Instant::now()) {
+                        events.push(event);
+                    }
                 }
             });
         }
@@ -41,6 +54,7 @@ impl KeyboardJangguDevice {
         KeyboardJangguDevice {
             state: state,
             stopping: stopping,
+            events: events,
         }
     }
 }
@@ -55,24 +69,9 @@ impl JangguDevice for KeyboardJangguDevice {
     fn read_janggu_input_state(&self) -> JangguInputState {
         bits_to_janggu_input_state(self.state.load(Ordering::Relaxed))
     }
-}
 
-fn bits_to_janggu_input_state(bits: u8) -> JangguInputState {
-    JangguInputState {
-        궁채: if bits & 1 != 0 {
-            Some(JangguFace::궁편)
-        } else if bits & 2 != 0 {
-            Some(JangguFace::열편)
-        } else {
-            None
-        },
-        열채: if bits & 4 != 0 {
-            Some(JangguFace::궁편)
-        } else if bits & 8 != 0 {
-            Some(JangguFace::열편)
-        } else {
-            None
-        },
+    fn read_janggu_events(&self) -> Vec<JangguInputEvent> {
+        self.events.drain()
     }
 }
 
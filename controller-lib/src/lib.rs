@@ -1,12 +1,30 @@
+pub mod janggu_event;
 pub mod keyboard;
+pub mod scripted;
 pub mod serial;
 
 use bidrum_data_struct_lib::janggu::JangguInputState;
+use janggu_event::JangguInputEvent;
+
+/// Connection state of the controller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// trying to open the port until it's connected for the first time
+    Connecting,
+    Connected,
+    /// trying to open the port again after the connection is lost
+    Reconnecting,
+    /// the last try after the connection is lost has failed with the reason,
+    /// and it will be tried again
+    Failed(String),
+}
 
 /// Bidrum Janggu Controller
 pub trait JangguDevice {
     /// Reads janggu controller input state
     fn read_janggu_input_state(&self) -> JangguInputState;
+    /// Takes the input events since the last call, in the order they happened
+    fn read_janggu_events(&self) -> Vec<JangguInputEvent>;
 }
 
 /// Bidrum Coin/Bill Acceptor
@@ -0,0 +1,307 @@
+//! Devices replaying a script of the inputs, to test the game without the controller
+//!
+//! Each line of the script file is the time in millisecond and the keys pressed at the time,
+//! which are same as the keyboard devices (d, f: 궁채 / j, k: 열채 / c: coin).
+//! The sticks not in the line are released, and `-` releases everything.
+//!
+//! ```text
+//! # time keys
+//! 500 d
+//! 550 -
+//! 1000 dk
+//! 1040 -
+//! 2000 c
+//! ```
+pub mod coin_device;
+pub mod janggu_device;
+
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState, JangguStick};
+
+use crate::janggu_event::janggu_input_state_to_bits;
+
+use self::{coin_device::ScriptedCoinDevice, janggu_device::ScriptedJangguDevice};
+
+/// Time of the script in millisecond, shared by the scripted devices
+#[derive(Debug, Clone)]
+pub struct ScriptClock {
+    started_at: Instant,
+    /// time set by hand, or None if the time flows in real time
+    manual_time: Option<Arc<AtomicU64>>,
+}
+
+impl ScriptClock {
+    /// Clock whose time flows from now
+    pub fn real_time() -> ScriptClock {
+        ScriptClock {
+            started_at: Instant::now(),
+            manual_time: None,
+        }
+    }
+
+    /// Clock which stays at the time given by `set_time`, starting from zero
+    pub fn manual() -> ScriptClock {
+        ScriptClock {
+            started_at: Instant::now(),
+            manual_time: Some(Arc::new(AtomicU64::new(0))),
+        }
+    }
+
+    /// Sets the time of the manual clock, which is ignored by the real time clock
+    pub fn set_time(&self, time: u64) {
+        if let Some(manual_time) = &self.manual_time {
+            manual_time.store(time, Ordering::Relaxed);
+        }
+    }
+
+    pub fn time(&self) -> u64 {
+        match &self.manual_time {
+            Some(manual_time) => manual_time.load(Ordering::Relaxed),
+            None => self.started_at.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Instant of the time, which is the timestamp of the events at the time
+    pub fn instant_at(&self, time: u64) -> Instant {
+        self.started_at + Duration::from_millis(time)
+    }
+}
+
+/// Timeline of the inputs, read from a file or built in code
+#[derive(Debug, Clone, Default)]
+pub struct InputScript {
+    /// janggu bits from the time, sorted by the time
+    janggu: Vec<(u64, u8)>,
+    /// times when the coins are inserted, sorted
+    coins: Vec<u64>,
+}
+
+impl InputScript {
+    pub fn new() -> InputScript {
+        InputScript::default()
+    }
+
+    /// Sets the janggu input state from the time
+    ///
+    /// The time shouldn't be earlier than the previous one.
+    pub fn janggu(mut self, time: u64, state: JangguInputState) -> InputScript {
+        self.janggu.push((time, janggu_input_state_to_bits(state)));
+        self
+    }
+
+    /// Hits the face with the stick at the time, and releases it after the length
+    ///
+    /// The other stick is released too.
+    pub fn hit(self, time: u64, stick: JangguStick, face: JangguFace, length: u64) -> InputScript {
+        let state = match stick {
+            JangguStick::궁채 => JangguInputState {
+                궁채: Some(face),
+                열채: None,
+            },
+            JangguStick::열채 => JangguInputState {
+                궁채: None,
+                열채: Some(face),
+            },
+        };
+
+        self.janggu(time, state).janggu(
+            time + length,
+            JangguInputState {
+                궁채: None,
+                열채: None,
+            },
+        )
+    }
+
+    /// Inserts a coin at the time
+    pub fn coin(mut self, time: u64) -> InputScript {
+        self.coins.push(time);
+        self
+    }
+
+    /// Parses the script, whose format is in the module documentation
+    pub fn parse(text: &str) -> Result<InputScript, String> {
+        let mut script = InputScript::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (time, keys) = line.split_once(' ').unwrap_or((line, "-"));
+            let time: u64 = time
+                .parse()
+                .map_err(|_| format!("line {}: invalid time {}", idx + 1, time))?;
+            if script.janggu.last().is_some_and(|x| x.0 > time) {
+                return Err(format!("line {}: time goes backward", idx + 1));
+            }
+
+            let mut bits = 0;
+            for key in keys.trim().chars() {
+                bits |= match key {
+                    'd' => 1,
+                    'f' => 2,
+                    'j' => 4,
+                    'k' => 8,
+                    'c' => 16,
+                    '-' => 0,
+                    _ => return Err(format!("line {}: invalid key {}", idx + 1, key)),
+                };
+            }
+            if bits & 3 == 3 || bits & 12 == 12 {
+                return Err(format!("line {}: a stick hits both faces", idx + 1));
+            }
+
+            script.janggu.push((time, bits & 15));
+            if bits & 16 != 0 {
+                script.coins.push(time);
+            }
+        }
+
+        Ok(script)
+    }
+
+    pub fn from_file(path: &Path) -> Result<InputScript, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        InputScript::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+/// Creates the devices replaying the script with the clock
+pub fn new(
+    mut script: InputScript,
+    clock: ScriptClock,
+) -> (ScriptedJangguDevice, ScriptedCoinDevice) {
+    // the entries at the same time keep their order
+    script.janggu.sort_by_key(|x| x.0);
+    script.coins.sort();
+
+    (
+        ScriptedJangguDevice::new(script.janggu, clock.clone()),
+        ScriptedCoinDevice::new(script.coins, clock),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState, JangguStick};
+
+    use super::{InputScript, ScriptClock};
+    use crate::{janggu_event::JangguEdge, CoinInputDevice, JangguDevice};
+
+    const SCRIPT: &str = "
+        # time keys
+        500 d
+        550 -
+        1000 fk
+        1040 -
+        2000 c
+    ";
+
+    #[test]
+    fn replays_input_states() {
+        let clock = ScriptClock::manual();
+        let (janggu, _) = super::new(InputScript::parse(SCRIPT).unwrap(), clock.clone());
+
+        clock.set_time(499);
+        assert_eq!(janggu.read_janggu_input_state().궁채, None);
+        clock.set_time(500);
+        assert_eq!(
+            janggu.read_janggu_input_state().궁채,
+            Some(JangguFace::궁편)
+        );
+        clock.set_time(1020);
+        let state = janggu.read_janggu_input_state();
+        assert_eq!(state.궁채, Some(JangguFace::열편));
+        assert_eq!(state.열채, Some(JangguFace::열편));
+    }
+
+    #[test]
+    fn replays_events_with_timestamps() {
+        let clock = ScriptClock::manual();
+        let (janggu, _) = super::new(InputScript::parse(SCRIPT).unwrap(), clock.clone());
+
+        // hits shorter than the reads are not lost
+        clock.set_time(1100);
+        let events = janggu.read_janggu_events();
+        let summary: Vec<_> = events
+            .iter()
+            .map(|x| (x.stick, x.face, x.edge, x.timestamp - clock.instant_at(0)))
+            .map(|(stick, face, edge, time)| (stick, face, edge, time.as_millis()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (JangguStick::궁채, JangguFace::궁편, JangguEdge::Down, 500),
+                (JangguStick::궁채, JangguFace::궁편, JangguEdge::Up, 550),
+                (JangguStick::궁채, JangguFace::열편, JangguEdge::Down, 1000),
+                (JangguStick::열채, JangguFace::열편, JangguEdge::Down, 1000),
+                (JangguStick::궁채, JangguFace::열편, JangguEdge::Up, 1040),
+                (JangguStick::열채, JangguFace::열편, JangguEdge::Up, 1040),
+            ]
+        );
+        assert!(janggu.read_janggu_events().is_empty());
+    }
+
+    #[test]
+    fn inserts_and_consumes_coins() {
+        let clock = ScriptClock::manual();
+        let script = InputScript::parse(SCRIPT).unwrap().coin(3000);
+        let (_, mut coin) = super::new(script, clock.clone());
+
+        assert_eq!(coin.get_unconsumed_coins(), 0);
+        clock.set_time(2000);
+        assert_eq!(coin.get_unconsumed_coins(), 1);
+        coin.consume_coins(1);
+        assert_eq!(coin.get_unconsumed_coins(), 0);
+        clock.set_time(3000);
+        assert_eq!(coin.get_unconsumed_coins(), 1);
+    }
+
+    #[test]
+    fn builds_script_in_code() {
+        let clock = ScriptClock::manual();
+        let script = InputScript::new()
+            .hit(100, JangguStick::열채, JangguFace::궁편, 30)
+            .janggu(
+                200,
+                JangguInputState {
+                    궁채: Some(JangguFace::궁편),
+                    열채: None,
+                },
+            );
+        let (janggu, _) = super::new(script, clock.clone());
+
+        clock.set_time(110);
+        assert_eq!(
+            janggu.read_janggu_input_state().열채,
+            Some(JangguFace::궁편)
+        );
+        clock.set_time(130);
+        assert_eq!(janggu.read_janggu_input_state().열채, None);
+        clock.set_time(200);
+        assert_eq!(
+            janggu.read_janggu_input_state().궁채,
+            Some(JangguFace::궁편)
+        );
+        assert_eq!(janggu.read_janggu_events().len(), 3);
+    }
+
+    #[test]
+    fn rejects_invalid_scripts() {
+        assert!(InputScript::parse("abc d").is_err());
+        assert!(InputScript::parse("100 x").is_err());
+        assert!(InputScript::parse("100 df").is_err());
+        assert!(InputScript::parse("200 d\n100 -").is_err());
+    }
+}
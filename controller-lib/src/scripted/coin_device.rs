@@ -0,0 +1,33 @@
+use crate::CoinInputDevice;
+
+use super::ScriptClock;
+
+/// Coin/Bill acceptor replaying the coins of the script
+pub struct ScriptedCoinDevice {
+    /// times when the coins are inserted, sorted
+    coins: Vec<u64>,
+    clock: ScriptClock,
+    consumed_coins: u32,
+}
+
+impl ScriptedCoinDevice {
+    pub(super) fn new(coins: Vec<u64>, clock: ScriptClock) -> ScriptedCoinDevice {
+        ScriptedCoinDevice {
+            coins: coins,
+            clock: clock,
+            consumed_coins: 0,
+        }
+    }
+}
+
+impl CoinInputDevice for ScriptedCoinDevice {
+    fn get_unconsumed_coins(&self) -> u32 {
+        let time = self.clock.time();
+        let inserted_coins = self.coins.partition_point(|x| *x <= time) as u32;
+        inserted_coins.saturating_sub(self.consumed_coins)
+    }
+
+    fn consume_coins(&mut self, coins: u32) {
+        self.consumed_coins += coins;
+    }
+}
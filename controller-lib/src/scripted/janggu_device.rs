@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+
+use bidrum_data_struct_lib::janggu::JangguInputState;
+
+use crate::{
+    janggu_event::{bits_to_janggu_input_state, janggu_events_between, JangguInputEvent},
+    JangguDevice,
+};
+
+use super::ScriptClock;
+
+/// Janggu replaying the input states of the script
+pub struct ScriptedJangguDevice {
+    /// janggu bits from the time, sorted by the time
+    timeline: Vec<(u64, u8)>,
+    clock: ScriptClock,
+    /// number of the timeline entries whose events are read
+    read_count: Mutex<usize>,
+}
+
+impl ScriptedJangguDevice {
+    pub(super) fn new(timeline: Vec<(u64, u8)>, clock: ScriptClock) -> ScriptedJangguDevice {
+        ScriptedJangguDevice {
+            timeline: timeline,
+            clock: clock,
+            read_count: Mutex::new(0),
+        }
+    }
+
+    /// Number of the timeline entries until the time
+    fn count_until(&self, time: u64) -> usize {
+        self.timeline.partition_point(|x| x.0 <= time)
+    }
+
+    fn bits_before(&self, count: usize) -> u8 {
+        if count == 0 {
+            0
+        } else {
+            self.timeline[count - 1].1
+        }
+    }
+}
+
+impl JangguDevice for ScriptedJangguDevice {
+    fn read_janggu_input_state(&self) -> JangguInputState {
+        let count = self.count_until(self.clock.time());
+        bits_to_janggu_input_state(self.bits_before(count))
+    }
+
+    fn read_janggu_events(&self) -> Vec<JangguInputEvent> {
+        let count = self.count_until(self.clock.time());
+        let mut read_count = self.read_count.lock().expect("Scripted janggu is poisoned");
+
+        let mut events = vec![];
+        for idx in *read_count..count {
+            let (time, bits) = self.timeline[idx];
+            events.extend(janggu_events_between(
+                self.bits_before(idx),
+                bits,
+                self.clock.instant_at(time),
+            ));
+        }
+        *read_count = count.max(*read_count);
+
+        events
+    }
+}
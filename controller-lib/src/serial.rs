@@ -1,10 +1,13 @@
 mod coin_device;
+pub mod discovery;
 mod janggu_device;
+pub mod protocol;
 mod serial_reader;
 
 use coin_device::SerialCoinDevice;
 use janggu_device::SerialJangguDevice;
 use serial_reader::BidrumSerialReader;
+pub use serial_reader::ProtocolMode;
 
 pub fn new(serial_port: String) -> (SerialJangguDevice, SerialCoinDevice) {
     let serial_reader = BidrumSerialReader::new(serial_port);
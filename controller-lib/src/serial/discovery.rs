@@ -0,0 +1,98 @@
+//! Finds the janggu controller among the serial ports
+use std::thread;
+
+use serialport::{SerialPortInfo, SerialPortType};
+
+use super::{
+    protocol::{DeviceInfo, CAPABILITY_JANGGU},
+    serial_reader::{open_port, ProtocolMode},
+};
+
+/// USB vendor and product ids of the boards used for the janggu controller
+const KNOWN_USB_IDS: [(u16, u16); 7] = [
+    (0x2341, 0x0043), // Arduino Uno
+    (0x2341, 0x0001), // Arduino Uno (old bootloader)
+    (0x2341, 0x0042), // Arduino Mega 2560
+    (0x2A03, 0x0043), // Arduino Uno (arduino.org)
+    (0x1A86, 0x7523), // CH340, used by Arduino clones
+    (0x0403, 0x6001), // FTDI FT232R, used by Arduino clones
+    (0x10C4, 0xEA60), // CP2102, used by Arduino clones
+];
+
+/// Serial port probed with the handshake
+#[derive(Debug, Clone)]
+pub struct FoundController {
+    pub port_name: String,
+    /// USB vendor and product ids
+    pub usb_ids: Option<(u16, u16)>,
+    /// protocol found out by the handshake, or why the handshake failed
+    pub probe: Result<ProtocolMode, String>,
+}
+
+impl FoundController {
+    fn is_known_usb_device(&self) -> bool {
+        self.usb_ids.is_some_and(|x| KNOWN_USB_IDS.contains(&x))
+    }
+
+    /// Whether it's probably the janggu controller
+    ///
+    /// The controllers with the legacy firmware (or without the answer of the handshake)
+    /// can't tell what they are, so they're trusted only with the known USB ids.
+    pub fn is_janggu_controller(&self) -> bool {
+        match self.probe {
+            Ok(ProtocolMode::Framed(Some(info))) => info.has_capability(CAPABILITY_JANGGU),
+            Ok(ProtocolMode::Framed(None)) | Ok(ProtocolMode::Legacy) => self.is_known_usb_device(),
+            Err(_) => false,
+        }
+    }
+
+    /// Information reported by the handshake
+    pub fn device_info(&self) -> Option<DeviceInfo> {
+        match self.probe {
+            Ok(ProtocolMode::Framed(info)) => info,
+            _ => None,
+        }
+    }
+}
+
+fn usb_ids(port: &SerialPortInfo) -> Option<(u16, u16)> {
+    match &port.port_type {
+        SerialPortType::UsbPort(usb) => Some((usb.vid, usb.pid)),
+        _ => None,
+    }
+}
+
+/// Probes the USB serial ports at the same time, as the handshake takes up to a few seconds
+///
+/// The other serial ports are not probed, which are rarely the controller
+/// but can take the whole timeout of the handshake.
+pub fn find_controllers() -> Vec<FoundController> {
+    let ports = serialport::available_ports().unwrap_or_default();
+    let probes: Vec<_> = ports
+        .into_iter()
+        .filter(|x| usb_ids(x).is_some())
+        .map(|port| {
+            thread::spawn(move || FoundController {
+                probe: open_port(&port.port_name).map(|(_, _, mode)| mode),
+                usb_ids: usb_ids(&port),
+                port_name: port.port_name,
+            })
+        })
+        .collect();
+
+    probes
+        .into_iter()
+        .map(|x| x.join().expect("Failed to probe the serial port"))
+        .collect()
+}
+
+/// Port of the janggu controller, preferring the one which answered the handshake
+pub fn find_controller() -> Option<String> {
+    let controllers = find_controllers();
+    controllers
+        .iter()
+        .filter(|x| x.is_janggu_controller())
+        .find(|x| x.device_info().is_some())
+        .or(controllers.iter().find(|x| x.is_janggu_controller()))
+        .map(|x| x.port_name.clone())
+}
@@ -1,8 +1,11 @@
 use std::sync::atomic::Ordering;
 
-use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState};
+use bidrum_data_struct_lib::janggu::JangguInputState;
 
-use crate::JangguDevice;
+use crate::{
+    janggu_event::{bits_to_janggu_input_state, JangguInputEvent},
+    ConnectionState, JangguDevice,
+};
 
 use super::serial_reader::BidrumSerialReader;
 
@@ -16,29 +19,22 @@ impl SerialJangguDevice {
             serial_reader: serial_reader,
         }
     }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        self.serial_reader
+            .connection_state
+            .read()
+            .expect("Connection state is poisoned")
+            .clone()
+    }
 }
 
 impl JangguDevice for SerialJangguDevice {
     fn read_janggu_input_state(&self) -> JangguInputState {
-        return parse_janggu_bits(self.serial_reader.bits.load(Ordering::Relaxed));
+        return bits_to_janggu_input_state(self.serial_reader.bits.load(Ordering::Relaxed));
     }
-}
 
-fn parse_janggu_bits(bits: u8) -> JangguInputState {
-    return JangguInputState {
-        궁채: if bits & 1 != 0 {
-            Some(JangguFace::궁편)
-        } else if bits & 2 != 0 {
-            Some(JangguFace::열편)
-        } else {
-            None
-        },
-        열채: if bits & 4 != 0 {
-            Some(JangguFace::궁편)
-        } else if bits & 8 != 0 {
-            Some(JangguFace::열편)
-        } else {
-            None
-        },
-    };
+    fn read_janggu_events(&self) -> Vec<JangguInputEvent> {
+        self.serial_reader.janggu_events.drain()
+    }
 }
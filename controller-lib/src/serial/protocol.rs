@@ -0,0 +1,328 @@
+//! Framed serial protocol of the janggu controller
+//!
+//! Every frame is
+//! `START_BYTE, version, kind, sequence, payload length, payload..., crc`
+//! where the crc is CRC-8 (polynomial 0x07) of the bytes from the version to the payload.
+//!
+//! The host sends a hello frame with the empty payload after opening the port,
+//! and the controller answers with a hello frame of its firmware version and capabilities.
+//! The controllers with the legacy firmware never answer, and send the raw state bytes
+//! which are always less than `START_BYTE`.
+
+/// First byte of every frame
+pub(crate) const START_BYTE: u8 = 0xA5;
+
+/// Version of the protocol this library speaks
+pub(crate) const PROTOCOL_VERSION: u8 = 1;
+
+/// Longer payloads are corrupted frames
+const MAX_PAYLOAD_LENGTH: usize = 16;
+
+/// Bytes before the payload
+const HEADER_LENGTH: usize = 5;
+
+/// Frame of the janggu and coin bits
+pub(crate) const KIND_STATE: u8 = 0x01;
+
+/// Handshake frame
+pub(crate) const KIND_HELLO: u8 = 0x02;
+
+/// The controller has the janggu
+pub const CAPABILITY_JANGGU: u8 = 1;
+
+/// The controller has the coin/bill acceptor
+pub const CAPABILITY_COIN: u8 = 2;
+
+/// Controller information reported by the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub firmware_version: (u8, u8),
+    /// bitwise or of the `CAPABILITY_` constants
+    pub capabilities: u8,
+}
+
+impl DeviceInfo {
+    pub fn has_capability(&self, capability: u8) -> bool {
+        self.capabilities & capability != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FramePayload {
+    /// janggu and coin bits, same as the legacy single byte
+    State(u8),
+    /// answer of the handshake
+    Hello(DeviceInfo),
+    /// frame of the other version or the unknown kind, which is ignored
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) version: u8,
+    pub(crate) sequence: u8,
+    pub(crate) payload: FramePayload,
+}
+
+/// CRC-8 with the polynomial 0x07
+pub(crate) fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Encodes the frame of the current protocol version
+pub(crate) fn encode_frame(kind: u8, sequence: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![
+        START_BYTE,
+        PROTOCOL_VERSION,
+        kind,
+        sequence,
+        payload.len() as u8,
+    ];
+    frame.extend_from_slice(payload);
+    frame.push(crc8(&frame[1..]));
+
+    frame
+}
+
+fn decode_payload(version: u8, kind: u8, payload: &[u8]) -> FramePayload {
+    if version != PROTOCOL_VERSION {
+        return FramePayload::Unknown;
+    }
+
+    match (kind, payload) {
+        (KIND_STATE, [bits]) => FramePayload::State(*bits),
+        (KIND_HELLO, [major, minor, capabilities, ..]) => FramePayload::Hello(DeviceInfo {
+            firmware_version: (*major, *minor),
+            capabilities: *capabilities,
+        }),
+        _ => FramePayload::Unknown,
+    }
+}
+
+/// Parses the frames from the serial stream
+///
+/// Garbage and corrupted frames are skipped, by searching the next start byte.
+pub(crate) struct FrameParser {
+    buffer: Vec<u8>,
+    last_sequence: Option<u8>,
+    /// frames missing from the sequence numbers
+    pub(crate) lost_frames: u32,
+    /// bytes skipped to find the next valid frame
+    pub(crate) skipped_bytes: u32,
+}
+
+impl FrameParser {
+    pub(crate) fn new() -> FrameParser {
+        FrameParser {
+            buffer: vec![],
+            last_sequence: None,
+            lost_frames: 0,
+            skipped_bytes: 0,
+        }
+    }
+
+    /// Parses the received bytes and returns the completed frames
+    ///
+    /// The incomplete frame at the end is kept until the rest is received.
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut frames = vec![];
+        loop {
+            // drop the bytes before the start byte
+            let start = self
+                .buffer
+                .iter()
+                .position(|x| *x == START_BYTE)
+                .unwrap_or(self.buffer.len());
+            self.skip(start);
+
+            if self.buffer.len() < HEADER_LENGTH {
+                break;
+            }
+            let payload_length = self.buffer[4] as usize;
+            if payload_length > MAX_PAYLOAD_LENGTH {
+                self.skip(1);
+                continue;
+            }
+            let frame_length = HEADER_LENGTH + payload_length + 1;
+            if self.buffer.len() < frame_length {
+                break;
+            }
+            if crc8(&self.buffer[1..frame_length - 1]) != self.buffer[frame_length - 1] {
+                // the start byte was in the garbage or the frame is corrupted
+                self.skip(1);
+                continue;
+            }
+
+            let frame = Frame {
+                version: self.buffer[1],
+                sequence: self.buffer[3],
+                payload: decode_payload(
+                    self.buffer[1],
+                    self.buffer[2],
+                    &self.buffer[HEADER_LENGTH..frame_length - 1],
+                ),
+            };
+            self.buffer.drain(..frame_length);
+
+            if let Some(last_sequence) = self.last_sequence {
+                self.lost_frames +=
+                    frame.sequence.wrapping_sub(last_sequence).wrapping_sub(1) as u32;
+            }
+            self.last_sequence = Some(frame.sequence);
+            frames.push(frame);
+        }
+
+        frames
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.skipped_bytes += count as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        crc8, encode_frame, DeviceInfo, Frame, FrameParser, FramePayload, KIND_HELLO, KIND_STATE,
+        PROTOCOL_VERSION, START_BYTE,
+    };
+
+    fn state_frame(sequence: u8, bits: u8) -> Frame {
+        Frame {
+            version: PROTOCOL_VERSION,
+            sequence: sequence,
+            payload: FramePayload::State(bits),
+        }
+    }
+
+    #[test]
+    fn crc8_of_check_string() {
+        // the check value of CRC-8 with the polynomial 0x07
+        assert_eq!(crc8(b"123456789"), 0xF4);
+    }
+
+    #[test]
+    fn parses_state_and_hello_frames() {
+        let mut parser = FrameParser::new();
+        let mut stream = encode_frame(KIND_HELLO, 0, &[1, 2, 3]);
+        stream.extend(encode_frame(KIND_STATE, 1, &[5]));
+
+        assert_eq!(
+            parser.push(&stream),
+            vec![
+                Frame {
+                    version: PROTOCOL_VERSION,
+                    sequence: 0,
+                    payload: FramePayload::Hello(DeviceInfo {
+                        firmware_version: (1, 2),
+                        capabilities: 3,
+                    }),
+                },
+                state_frame(1, 5),
+            ]
+        );
+        assert_eq!(parser.lost_frames, 0);
+        assert_eq!(parser.skipped_bytes, 0);
+    }
+
+    #[test]
+    fn skips_garbage_between_frames() {
+        let mut parser = FrameParser::new();
+        let mut stream = vec![0x00, 0x13, 0xFF, START_BYTE, 0x42];
+        stream.extend(encode_frame(KIND_STATE, 0, &[1]));
+        stream.extend([START_BYTE, START_BYTE, 0x07]);
+        stream.extend(encode_frame(KIND_STATE, 1, &[2]));
+
+        assert_eq!(
+            parser.push(&stream),
+            vec![state_frame(0, 1), state_frame(1, 2)]
+        );
+        assert_eq!(parser.skipped_bytes, 8);
+    }
+
+    #[test]
+    fn waits_for_truncated_frame() {
+        let mut parser = FrameParser::new();
+        let frame = encode_frame(KIND_STATE, 0, &[4]);
+
+        assert_eq!(parser.push(&frame[..3]), vec![]);
+        assert_eq!(parser.push(&frame[3..5]), vec![]);
+        assert_eq!(parser.push(&frame[5..]), vec![state_frame(0, 4)]);
+    }
+
+    #[test]
+    fn resynchronizes_after_truncated_frame() {
+        let mut parser = FrameParser::new();
+        // the frame is cut by the reset of the controller, and the next frame follows
+        let mut stream = encode_frame(KIND_STATE, 0, &[1])[..4].to_vec();
+        stream.extend(encode_frame(KIND_STATE, 1, &[2]));
+        stream.extend(encode_frame(KIND_STATE, 2, &[3]));
+
+        assert_eq!(
+            parser.push(&stream),
+            vec![state_frame(1, 2), state_frame(2, 3)]
+        );
+    }
+
+    #[test]
+    fn rejects_corrupted_frame() {
+        let mut parser = FrameParser::new();
+        let mut corrupted = encode_frame(KIND_STATE, 0, &[1]);
+        corrupted[5] ^= 0x10;
+        let mut stream = corrupted;
+        stream.extend(encode_frame(KIND_STATE, 1, &[2]));
+
+        assert_eq!(parser.push(&stream), vec![state_frame(1, 2)]);
+    }
+
+    #[test]
+    fn counts_lost_frames_by_sequence() {
+        let mut parser = FrameParser::new();
+        let mut stream = encode_frame(KIND_STATE, 254, &[0]);
+        stream.extend(encode_frame(KIND_STATE, 255, &[0]));
+        // wraps around and skips 0 and 1
+        stream.extend(encode_frame(KIND_STATE, 2, &[0]));
+
+        assert_eq!(parser.push(&stream).len(), 3);
+        assert_eq!(parser.lost_frames, 2);
+    }
+
+    #[test]
+    fn ignores_frame_of_other_version() {
+        let mut parser = FrameParser::new();
+        let mut frame = vec![START_BYTE, PROTOCOL_VERSION + 1, KIND_STATE, 0, 1, 7];
+        frame.push(crc8(&frame[1..]));
+
+        assert_eq!(
+            parser.push(&frame),
+            vec![Frame {
+                version: PROTOCOL_VERSION + 1,
+                sequence: 0,
+                payload: FramePayload::Unknown,
+            }]
+        );
+    }
+
+    #[test]
+    fn legacy_bytes_are_not_frames() {
+        let mut parser = FrameParser::new();
+
+        assert_eq!(parser.push(&[0, 1, 4, 5, 16, 10, 0]), vec![]);
+        assert_eq!(parser.skipped_bytes, 7);
+    }
+}
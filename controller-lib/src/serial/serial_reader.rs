@@ -1,18 +1,58 @@
 use std::{
+    io::{ErrorKind, Write},
     sync::{
         atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
-        Arc,
+        Arc, RwLock,
     },
     thread::{self, sleep},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use serialport::SerialPort;
+use serialport::{SerialPort, SerialPortType, UsbPortInfo};
+
+use crate::{
+    janggu_event::{janggu_events_between, JangguEventQueue},
+    ConnectionState,
+};
+
+use super::protocol::{
+    encode_frame, DeviceInfo, FrameParser, FramePayload, KIND_HELLO, PROTOCOL_VERSION, START_BYTE,
+};
+
+/// Arduino resets when the port is opened, and needs time to boot
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(3000);
+
+/// Interval of the hello frames, as the controller misses them while booting
+const HELLO_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The controller streaming bytes without answering the hello for this long has the legacy firmware,
+/// unless any frame is received
+const LEGACY_DETECTION_TIME: Duration = Duration::from_millis(500);
+
+/// Interval of the retries to open the port
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Nothing received for this long means the controller is gone,
+/// as the controller sends the state at least every 100ms
+const DATA_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// How the controller sends its state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolMode {
+    /// frames of the versioned protocol, with the device information
+    /// if the controller answered the hello
+    Framed(Option<DeviceInfo>),
+    /// one raw byte per update, of the firmware before the framed protocol
+    Legacy,
+}
 
 /// United bidrum controller of Janggu and Coin/Bill acceptor
 pub(super) struct BidrumSerialReader {
     stopping: Arc<AtomicBool>,
     pub(super) bits: Arc<AtomicU8>,
+    /// janggu events timestamped when the bits are received
+    pub(super) janggu_events: JangguEventQueue,
+    pub(super) connection_state: Arc<RwLock<ConnectionState>>,
     counter: Arc<AtomicU32>,
 }
 
@@ -23,6 +63,8 @@ impl Clone for BidrumSerialReader {
         Self {
             stopping: self.stopping.clone(),
             bits: self.bits.clone(),
+            janggu_events: self.janggu_events.clone(),
+            connection_state: self.connection_state.clone(),
             counter: self.counter.clone(),
         }
     }
@@ -39,30 +81,59 @@ impl Drop for BidrumSerialReader {
 }
 
 impl BidrumSerialReader {
+    /// Starts reading the controller, which is reconnected whenever the connection is lost
+    ///
+    /// The port is found again by the USB ids if its path is changed after the reconnection.
     pub(super) fn new(controller_port: String) -> BidrumSerialReader {
-        println!("Openning serial port {}", controller_port);
-
-        let mut port = serialport::new(controller_port, 9600)
-            .timeout(Duration::from_millis(20))
-            .open()
-            .expect("Failed to open port");
-
-        println!("Waiting 3 seconds (Arduino needs time for serial initialization)");
-        sleep(Duration::from_millis(3000));
-        println!("Waited 3 seconds!");
-
         let stopping = Arc::new(AtomicBool::new(false));
         let bits = Arc::new(AtomicU8::new(0));
+        let janggu_events = JangguEventQueue::new();
+        let connection_state = Arc::new(RwLock::new(ConnectionState::Connecting));
         let counter = Arc::new(AtomicU32::new(1));
         {
             let stopping = stopping.clone();
             let bits = bits.clone();
+            let janggu_events = janggu_events.clone();
+            let connection_state = connection_state.clone();
 
-            thread::spawn(move || loop {
-                if stopping.load(Ordering::Relaxed) {
-                    break;
+            thread::spawn(move || {
+                let mut target = PortTarget {
+                    path: controller_port,
+                    usb: None,
+                };
+                let mut connected_once = false;
+                let mut last_failure: Option<String> = None;
+                while !stopping.load(Ordering::Relaxed) {
+                    match Connection::open(&mut target) {
+                        Ok(mut connection) => {
+                            set_connection_state(&connection_state, ConnectionState::Connected);
+                            connected_once = true;
+                            if let Err(reason) =
+                                connection.read_until_disconnected(&stopping, &bits, &janggu_events)
+                            {
+                                eprintln!("Controller disconnected: {}", reason);
+                            }
+                            set_connection_state(&connection_state, ConnectionState::Reconnecting);
+                            // release the sticks held when disconnected
+                            apply_bits(0, Instant::now(), &bits, &janggu_events);
+                        }
+                        Err(reason) if connected_once => {
+                            set_connection_state(
+                                &connection_state,
+                                ConnectionState::Failed(reason),
+                            );
+                            sleep(RECONNECT_INTERVAL);
+                        }
+                        Err(reason) => {
+                            // still connecting, so it's not shown as the lost connection
+                            if last_failure.as_ref() != Some(&reason) {
+                                eprintln!("Controller connection failed: {}", reason);
+                                last_failure = Some(reason);
+                            }
+                            sleep(RECONNECT_INTERVAL);
+                        }
+                    }
                 }
-                read_serial(&mut port, &bits);
             });
         }
 
@@ -70,23 +141,493 @@ impl BidrumSerialReader {
             stopping: stopping,
             counter: counter,
             bits: bits,
+            janggu_events: janggu_events,
+            connection_state: connection_state,
         }
     }
 }
 
-/// Read serial inputs from port and emulates key inputs
-fn read_serial(port: &mut Box<dyn SerialPort>, bits_data: &Arc<AtomicU8>) {
+/// Changes the connection state, and prints the failure if it's new
+fn set_connection_state(connection_state: &RwLock<ConnectionState>, new_state: ConnectionState) {
+    let mut connection_state = connection_state
+        .write()
+        .expect("Connection state is poisoned");
+    if *connection_state == new_state {
+        return;
+    }
+
+    if let ConnectionState::Failed(reason) = &new_state {
+        eprintln!("Controller connection failed: {}", reason);
+    }
+    *connection_state = new_state;
+}
+
+/// Where the controller is
+struct PortTarget {
+    path: String,
+    /// USB ids of the port when it was found, to find it after the path is changed
+    usb: Option<UsbPortInfo>,
+}
+
+fn is_same_usb_device(a: &UsbPortInfo, b: &UsbPortInfo) -> bool {
+    a.vid == b.vid && a.pid == b.pid && a.serial_number == b.serial_number
+}
+
+impl PortTarget {
+    /// Current path of the controller port
+    fn find(&mut self) -> Result<String, String> {
+        let ports = serialport::available_ports().unwrap_or_default();
+        if let Some(port) = ports.iter().find(|x| x.port_name == self.path) {
+            if let SerialPortType::UsbPort(usb) = &port.port_type {
+                self.usb = Some(usb.clone());
+            }
+            return Ok(self.path.clone());
+        }
+
+        match &self.usb {
+            Some(usb) => ports
+                .iter()
+                .find(|x| match &x.port_type {
+                    SerialPortType::UsbPort(x) => is_same_usb_device(x, usb),
+                    _ => false,
+                })
+                .map(|x| x.port_name.clone())
+                .ok_or(format!(
+                    "{} (USB {:04x}:{:04x}) is not found",
+                    self.path, usb.vid, usb.pid
+                )),
+            // the port may not be listed, e.g. pseudo terminals, so try the path anyway
+            None => Ok(self.path.clone()),
+        }
+    }
+}
+
+/// Opened port of the controller after the handshake
+struct Connection {
+    port: Box<dyn SerialPort>,
+    mode: ProtocolMode,
+    parser: FrameParser,
+}
+
+impl Connection {
+    fn open(target: &mut PortTarget) -> Result<Connection, String> {
+        let path = target.find()?;
+        let (port, parser, mode) = open_port(&path)?;
+        match mode {
+            ProtocolMode::Framed(Some(info)) => println!(
+                "Controller connected at {} (firmware {}.{}, capabilities {:#04x})",
+                path, info.firmware_version.0, info.firmware_version.1, info.capabilities
+            ),
+            ProtocolMode::Framed(None) => println!(
+                "Controller connected at {} (frames are received, but no answer to the handshake)",
+                path
+            ),
+            ProtocolMode::Legacy => println!(
+                "Controller connected at {} (no answer to the handshake, using the legacy protocol)",
+                path
+            ),
+        }
+
+        Ok(Connection {
+            port: port,
+            mode: mode,
+            parser: parser,
+        })
+    }
+
+    /// Reads the controller until it's stopped or the connection is lost
+    fn read_until_disconnected(
+        &mut self,
+        stopping: &AtomicBool,
+        bits_data: &Arc<AtomicU8>,
+        janggu_events: &JangguEventQueue,
+    ) -> Result<(), String> {
+        let mut received_at = Instant::now();
+        while !stopping.load(Ordering::Relaxed) {
+            if read_serial(
+                &mut self.port,
+                self.mode,
+                &mut self.parser,
+                bits_data,
+                janggu_events,
+            )? {
+                received_at = Instant::now();
+            } else if received_at.elapsed() > DATA_TIMEOUT {
+                return Err(format!(
+                    "Nothing received for {} ms",
+                    DATA_TIMEOUT.as_millis()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Opens the port and finds out the protocol of the controller with the handshake
+pub(super) fn open_port(
+    path: &str,
+) -> Result<(Box<dyn SerialPort>, FrameParser, ProtocolMode), String> {
+    let mut port = serialport::new(path, 9600)
+        .timeout(Duration::from_millis(20))
+        .open()
+        .map_err(|err| format!("Failed to open port {}: {}", path, err))?;
+
+    let mut parser = FrameParser::new();
+    let mode = handshake(&mut port, &mut parser)?;
+
+    Ok((port, parser, mode))
+}
+
+/// Progress of the handshake, which finds out the protocol from the received bytes
+struct Handshake {
+    started_at: Instant,
+    first_byte_at: Option<Instant>,
+    /// when the first valid frame is parsed
+    first_frame_at: Option<Instant>,
+    /// whether any start byte is received, which the legacy firmware never sends
+    start_byte_received: bool,
+}
+
+impl Handshake {
+    fn new(started_at: Instant) -> Handshake {
+        Handshake {
+            started_at: started_at,
+            first_byte_at: None,
+            first_frame_at: None,
+            start_byte_received: false,
+        }
+    }
+
+    /// Reads the received bytes, and returns the protocol if the controller answered the hello
+    fn receive(
+        &mut self,
+        bytes: &[u8],
+        parser: &mut FrameParser,
+        now: Instant,
+    ) -> Option<ProtocolMode> {
+        if self.first_byte_at.is_none() && !bytes.is_empty() {
+            self.first_byte_at = Some(now);
+        }
+        if bytes.contains(&START_BYTE) {
+            self.start_byte_received = true;
+        }
+
+        for frame in parser.push(bytes) {
+            if frame.version != PROTOCOL_VERSION {
+                eprintln!(
+                    "Controller speaks the protocol version {}, but {} is supported",
+                    frame.version, PROTOCOL_VERSION
+                );
+            }
+            if let FramePayload::Hello(info) = frame.payload {
+                return Some(ProtocolMode::Framed(Some(info)));
+            }
+            if self.first_frame_at.is_none() {
+                self.first_frame_at = Some(now);
+            }
+        }
+
+        None
+    }
+
+    /// Decides the protocol by the time passed without the answer of the hello
+    ///
+    /// The controller sending only the raw bytes has the legacy firmware,
+    /// and the controller sending the frames without the answer (e.g. the answer is lost)
+    /// is regarded as the framed one with the unknown device information.
+    fn check_timeout(&self, now: Instant) -> Result<Option<ProtocolMode>, String> {
+        let first_byte_at = match self.first_byte_at {
+            Some(first_byte_at) => first_byte_at,
+            None if now.duration_since(self.started_at) > HANDSHAKE_TIMEOUT => {
+                return Err(format!(
+                    "Nothing received for {} ms after opening the port",
+                    HANDSHAKE_TIMEOUT.as_millis()
+                ))
+            }
+            None => return Ok(None),
+        };
+
+        if let Some(first_frame_at) = self.first_frame_at {
+            if now.duration_since(first_frame_at) > HANDSHAKE_TIMEOUT {
+                return Ok(Some(ProtocolMode::Framed(None)));
+            }
+            return Ok(None);
+        }
+        if self.start_byte_received {
+            if now.duration_since(first_byte_at) > HANDSHAKE_TIMEOUT {
+                return Err(format!(
+                    "No valid frame received for {} ms",
+                    HANDSHAKE_TIMEOUT.as_millis()
+                ));
+            }
+            return Ok(None);
+        }
+        if now.duration_since(first_byte_at) > LEGACY_DETECTION_TIME {
+            return Ok(Some(ProtocolMode::Legacy));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Sends the hello frames until the controller answers
+///
+/// Falls back to the legacy protocol if the controller streams the raw bytes without answering.
+fn handshake(
+    port: &mut Box<dyn SerialPort>,
+    parser: &mut FrameParser,
+) -> Result<ProtocolMode, String> {
+    let mut handshake = Handshake::new(Instant::now());
+    let mut hello_sent_at: Option<Instant> = None;
+    let mut buffer = [0u8; 64];
     loop {
-        let available_bytes: u32 = port.bytes_to_read().expect("Failed to read buffer size");
-        if available_bytes > 0 {
-            break;
+        let now = Instant::now();
+        if let Some(mode) = handshake.check_timeout(now)? {
+            return Ok(mode);
+        }
+
+        let should_send_hello = match hello_sent_at {
+            Some(hello_sent_at) => now.duration_since(hello_sent_at) > HELLO_INTERVAL,
+            None => true,
+        };
+        if should_send_hello {
+            // the port may not be writable while the controller boots
+            let _ = port.write_all(&encode_frame(KIND_HELLO, 0, &[]));
+            hello_sent_at = Some(now);
+        }
+
+        let length = match port.read(&mut buffer) {
+            Ok(length) => length,
+            Err(err) if err.kind() == ErrorKind::TimedOut => continue,
+            Err(err) => return Err(format!("Controller reading failure! {}", err)),
+        };
+        if length == 0 {
+            continue;
+        }
+        if handshake.first_byte_at.is_none() {
+            // the controller is ready, so it should answer this one
+            hello_sent_at = None;
+        }
+
+        if let Some(mode) = handshake.receive(&buffer[..length], parser, Instant::now()) {
+            return Ok(mode);
+        }
+    }
+}
+
+/// Stores the bits and pushes the janggu events of the change
+fn apply_bits(
+    bits: u8,
+    timestamp: Instant,
+    bits_data: &Arc<AtomicU8>,
+    janggu_events: &JangguEventQueue,
+) {
+    let previous = bits_data.swap(bits, Ordering::Relaxed);
+    for event in janggu_events_between(previous, bits, timestamp) {
+        janggu_events.push(event);
+    }
+}
+
+/// Applies the raw state bytes of the legacy firmware
+///
+/// The bytes from the start byte are the frames (or garbage), not the state.
+fn apply_legacy_bytes(
+    bytes: &[u8],
+    timestamp: Instant,
+    bits_data: &Arc<AtomicU8>,
+    janggu_events: &JangguEventQueue,
+) {
+    for bits in bytes.iter().filter(|x| **x < START_BYTE) {
+        apply_bits(*bits, timestamp, bits_data, janggu_events);
+    }
+}
+
+/// Read serial inputs from port and emulates key inputs
+///
+/// The read blocks until the bytes arrive (or the timeout of the port),
+/// so the events are timestamped as soon as the controller sends them.
+/// Returns whether anything valid is received.
+fn read_serial(
+    port: &mut Box<dyn SerialPort>,
+    mode: ProtocolMode,
+    parser: &mut FrameParser,
+    bits_data: &Arc<AtomicU8>,
+    janggu_events: &JangguEventQueue,
+) -> Result<bool, String> {
+    let mut buffer = [0u8; 64];
+    let length = match port.read(&mut buffer) {
+        Ok(length) => length,
+        Err(err) if err.kind() == ErrorKind::TimedOut => return Ok(false),
+        Err(err) => return Err(format!("Controller reading failure! {}", err)),
+    };
+    let timestamp = Instant::now();
+
+    match mode {
+        ProtocolMode::Framed(_) => {
+            let frames = parser.push(&buffer[..length]);
+            for frame in &frames {
+                if let FramePayload::State(bits) = frame.payload {
+                    apply_bits(bits, timestamp, bits_data, janggu_events);
+                }
+            }
+            Ok(!frames.is_empty())
+        }
+        ProtocolMode::Legacy => {
+            apply_legacy_bytes(&buffer[..length], timestamp, bits_data, janggu_events);
+            Ok(length > 0)
         }
-        sleep(Duration::from_millis(10));
     }
-    let mut message: [u8; 1] = [0];
+}
 
-    port.read_exact(message.as_mut_slice())
-        .expect("Controller reading failure!");
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicU8, Arc},
+        time::{Duration, Instant},
+    };
 
-    bits_data.store(message[0], std::sync::atomic::Ordering::Relaxed);
+    use crate::janggu_event::JangguEventQueue;
+
+    use super::{
+        apply_legacy_bytes, Handshake, ProtocolMode, HANDSHAKE_TIMEOUT, LEGACY_DETECTION_TIME,
+    };
+    use crate::serial::protocol::{
+        encode_frame, DeviceInfo, FrameParser, KIND_HELLO, KIND_STATE, START_BYTE,
+    };
+
+    fn after(started_at: Instant, milliseconds: u64) -> Instant {
+        started_at + Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn frames_without_hello_are_not_legacy() {
+        let started_at = Instant::now();
+        let mut handshake = Handshake::new(started_at);
+        let mut parser = FrameParser::new();
+
+        // the answer of the hello is lost, but the state frames arrive
+        for (index, milliseconds) in [100, 200, 300].into_iter().enumerate() {
+            let frame = encode_frame(KIND_STATE, index as u8, &[0]);
+            assert_eq!(
+                handshake.receive(&frame, &mut parser, after(started_at, milliseconds)),
+                None
+            );
+        }
+
+        let after_legacy_detection = after(started_at, 100) + LEGACY_DETECTION_TIME * 2;
+        assert_eq!(handshake.check_timeout(after_legacy_detection), Ok(None));
+
+        let after_handshake_timeout =
+            after(started_at, 100) + HANDSHAKE_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(
+            handshake.check_timeout(after_handshake_timeout),
+            Ok(Some(ProtocolMode::Framed(None)))
+        );
+    }
+
+    #[test]
+    fn hello_after_frames_gives_device_info() {
+        let started_at = Instant::now();
+        let mut handshake = Handshake::new(started_at);
+        let mut parser = FrameParser::new();
+
+        let frame = encode_frame(KIND_STATE, 0, &[0]);
+        assert_eq!(
+            handshake.receive(&frame, &mut parser, after(started_at, 100)),
+            None
+        );
+
+        // the answer of the re-sent hello
+        let hello = encode_frame(KIND_HELLO, 0, &[1, 2, 3]);
+        assert_eq!(
+            handshake.receive(&hello, &mut parser, after(started_at, 1000)),
+            Some(ProtocolMode::Framed(Some(DeviceInfo {
+                firmware_version: (1, 2),
+                capabilities: 3,
+            })))
+        );
+    }
+
+    #[test]
+    fn frame_split_across_reads_is_not_legacy() {
+        let started_at = Instant::now();
+        let mut handshake = Handshake::new(started_at);
+        let mut parser = FrameParser::new();
+
+        let frame = encode_frame(KIND_STATE, 0, &[0]);
+        let (head, tail) = frame.split_at(2);
+        handshake.receive(head, &mut parser, after(started_at, 100));
+
+        // the start byte is received, so it waits for the rest of the frame
+        assert_eq!(handshake.check_timeout(after(started_at, 700)), Ok(None));
+
+        handshake.receive(tail, &mut parser, after(started_at, 800));
+        assert_eq!(handshake.check_timeout(after(started_at, 1000)), Ok(None));
+    }
+
+    #[test]
+    fn raw_bytes_are_legacy() {
+        let started_at = Instant::now();
+        let mut handshake = Handshake::new(started_at);
+        let mut parser = FrameParser::new();
+
+        assert_eq!(
+            handshake.receive(&[0, 1, 0, 4], &mut parser, after(started_at, 100)),
+            None
+        );
+        assert_eq!(handshake.check_timeout(after(started_at, 300)), Ok(None));
+        assert_eq!(
+            handshake.check_timeout(after(started_at, 100) + LEGACY_DETECTION_TIME * 2),
+            Ok(Some(ProtocolMode::Legacy))
+        );
+    }
+
+    #[test]
+    fn silence_is_error() {
+        let started_at = Instant::now();
+        let handshake = Handshake::new(started_at);
+
+        assert_eq!(handshake.check_timeout(after(started_at, 1000)), Ok(None));
+        assert!(handshake
+            .check_timeout(started_at + HANDSHAKE_TIMEOUT + Duration::from_millis(1))
+            .is_err());
+    }
+
+    #[test]
+    fn garbage_with_start_bytes_is_error() {
+        let started_at = Instant::now();
+        let mut handshake = Handshake::new(started_at);
+        let mut parser = FrameParser::new();
+
+        handshake.receive(
+            &[START_BYTE, 0x07, 0x07],
+            &mut parser,
+            after(started_at, 100),
+        );
+
+        assert_eq!(handshake.check_timeout(after(started_at, 700)), Ok(None));
+        assert!(handshake
+            .check_timeout(after(started_at, 100) + HANDSHAKE_TIMEOUT + Duration::from_millis(1))
+            .is_err());
+    }
+
+    #[test]
+    fn legacy_ignores_frame_bytes() {
+        let bits_data = Arc::new(AtomicU8::new(0));
+        let janggu_events = JangguEventQueue::new();
+
+        // 0xFF would be all the sticks on both faces
+        apply_legacy_bytes(
+            &[START_BYTE, 0xFF, 0xF0],
+            Instant::now(),
+            &bits_data,
+            &janggu_events,
+        );
+        assert!(janggu_events.drain().is_empty());
+
+        // 궁채 on 궁편
+        apply_legacy_bytes(&[1], Instant::now(), &bits_data, &janggu_events);
+        assert_eq!(janggu_events.drain().len(), 1);
+    }
 }
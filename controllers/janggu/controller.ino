@@ -6,15 +6,34 @@
 #define OUTPUT_PIN_2 7  // 궁채
 #define RELAY_DELAY 50  // 딜레이 50μs
 
+// 프레임 프로토콜 (controller-lib/src/serial/protocol.rs 참고)
+#define START_BYTE 0xA5
+#define PROTOCOL_VERSION 1
+#define KIND_STATE 0x01
+#define KIND_HELLO 0x02
+#define FIRMWARE_VERSION_MAJOR 1
+#define FIRMWARE_VERSION_MINOR 0
+#define CAPABILITIES 3          // 장구(1) | 코인기(2)
+#define HEARTBEAT_INTERVAL 100  // 상태가 그대로여도 100ms마다 전송
+
 int step;
 int pin1ConnectedTo, pin2ConnectedTo;
 unsigned int lastTimestamp;
 unsigned int coin_cnt;
+uint8_t sequence;
+uint8_t lastBits;
+unsigned long lastSentMillis;
+uint8_t received[6];
+uint8_t receivedLength;
 
 void setup()
 {
   step = 0;
   coin_cnt = 0;
+  sequence = 0;
+  lastBits = 0;
+  lastSentMillis = 0;
+  receivedLength = 0;
   pinMode(OUTPUT_PIN_1, OUTPUT);
   pinMode(OUTPUT_PIN_2, OUTPUT);
   pinMode(INPUT_PIN_1, INPUT_PULLUP);
@@ -45,8 +64,57 @@ void coin()
   coin_cnt++;
 }
 
+uint8_t crc8(const uint8_t *bytes, uint8_t length)
+{
+  uint8_t crc = 0;
+  for (uint8_t i = 0; i < length; i++) {
+    crc ^= bytes[i];
+    for (uint8_t j = 0; j < 8; j++) {
+      crc = (crc & 0x80) ? (crc << 1) ^ 0x07 : crc << 1;
+    }
+  }
+  return crc;
+}
+
+void sendFrame(uint8_t kind, const uint8_t *payload, uint8_t length)
+{
+  uint8_t frame[5 + 3 + 1];
+  frame[0] = START_BYTE;
+  frame[1] = PROTOCOL_VERSION;
+  frame[2] = kind;
+  frame[3] = sequence++;
+  frame[4] = length;
+  for (uint8_t i = 0; i < length; i++) {
+    frame[5 + i] = payload[i];
+  }
+  frame[5 + length] = crc8(frame + 1, 4 + length);
+  Serial.write(frame, 6 + length);
+}
+
+// 호스트의 hello 프레임(페이로드 없음)에 펌웨어 버전과 기능으로 응답
+void receiveHello()
+{
+  while (Serial.available() > 0) {
+    uint8_t value = Serial.read();
+    if (receivedLength == 0 && value != START_BYTE) {
+      continue;
+    }
+    received[receivedLength++] = value;
+    if (receivedLength < 6) {
+      continue;
+    }
+    receivedLength = 0;
+    if (received[2] == KIND_HELLO && received[4] == 0 && crc8(received + 1, 4) == received[5]) {
+      uint8_t hello[3] = {FIRMWARE_VERSION_MAJOR, FIRMWARE_VERSION_MINOR, CAPABILITIES};
+      sendFrame(KIND_HELLO, hello, 3);
+    }
+  }
+}
+
 void loop()
 {
+  receiveHello();
+
   unsigned int timestamp = micros();
   if (timestamp - lastTimestamp > 100) {
    	step = (step + 1) % 3;
@@ -91,11 +159,17 @@ void loop()
         bits |= (uint8_t)4;
       if (pin2ConnectedTo == INPUT_PIN_2)
         bits |= (uint8_t)8;
-      if (coin_cnt>0){
+      // 코인 비트는 한 프레임 켜지고 다음 프레임에 꺼져야 호스트가 셀 수 있음
+      if (coin_cnt>0 && !(lastBits & 16)){
         bits |= (uint8_t)16;
         coin_cnt--;
       }
-      Serial.write(bits);
+      // 9600 baud로는 매번 보낼 수 없으므로 바뀔 때만 전송
+      if (bits != lastBits || millis() - lastSentMillis > HEARTBEAT_INTERVAL) {
+        sendFrame(KIND_STATE, &bits, 1);
+        lastBits = bits;
+        lastSentMillis = millis();
+      }
     break;
   }
 }
@@ -0,0 +1,56 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the calibration profile which is loaded automatically
+pub const DEFAULT_CALIBRATION_PROFILE_PATH: &str = "calibration.json";
+
+/// Timing offsets of the player and the devices, measured by beep-boop calibration
+///
+/// The offsets are positive if the hits come later than the sound or the screen.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CalibrationProfile {
+    /// offset of the hits from the sound in millisecond
+    pub audio_offset: i64,
+    /// offset of the hits from the flash on the screen in millisecond
+    pub visual_offset: i64,
+}
+
+impl CalibrationProfile {
+    pub fn load(path: &Path) -> Result<CalibrationProfile, String> {
+        let json = fs::read_to_string(path).map_err(|err| {
+            format!(
+                "Failed to read calibration profile {}: {}",
+                path.display(),
+                err
+            )
+        })?;
+        serde_json::from_str(&json).map_err(|err| {
+            format!(
+                "Failed to parse calibration profile {}: {}",
+                path.display(),
+                err
+            )
+        })
+    }
+
+    /// Loads the profile, or the profile without offsets if the file doesn't exist
+    pub fn load_or_default(path: &Path) -> Result<CalibrationProfile, String> {
+        match fs::metadata(path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(CalibrationProfile::default()),
+            _ => CalibrationProfile::load(path),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_string_pretty(self).expect("Calibration profile is always serializable");
+        fs::write(path, json).map_err(|err| {
+            format!(
+                "Failed to write calibration profile {}: {}",
+                path.display(),
+                err
+            )
+        })
+    }
+}
@@ -0,0 +1,334 @@
+//! Converts bidrum charts into the charts of the other rhythm games
+use std::collections::BTreeMap;
+
+use num_rational::Rational64;
+
+use crate::{
+    janggu::JangguFace,
+    song::{GameChart, TempoMap},
+};
+
+/// slider multiplier of the exported beatmap, which is the default value of osu!
+const OSU_SLIDER_MULTIPLIER: f64 = 1.4;
+
+fn to_f64(value: Rational64) -> f64 {
+    *value.numer() as f64 / *value.denom() as f64
+}
+
+fn timing_of(tempo_map: &TempoMap, beat: Rational64) -> i64 {
+    to_f64(tempo_map.precise_timing_in_ms(beat)).round() as i64
+}
+
+fn beat_length_of(tempo_map: &TempoMap, beat: Rational64) -> f64 {
+    60000.0 / to_f64(tempo_map.bpm_at(beat))
+}
+
+/// Exports the chart as osu!taiko beatmap (`.osu`)
+///
+/// The notes are converted as following
+///   - notes on 궁편: don, notes on 열편: kat
+///   - strokes hit by both sticks, or notes at the same position: big don (big kat if only 열편 is hit)
+///   - long notes: drumroll
+///   - hat notes: denden(spinner) of one beat
+///
+/// The audio and the cover image should be copied next to the beatmap.
+pub fn export_osu(
+    chart: &GameChart,
+    title: &str,
+    audio_filename: &str,
+    cover_image_filename: Option<&str>,
+) -> String {
+    let tempo_map = chart.tempo_map();
+    let metadata = &chart.metadata;
+    let version = match (&metadata.difficulty_name, metadata.level) {
+        (Some(difficulty_name), _) => difficulty_name.clone(),
+        (None, Some(level)) => format!("Lv.{}", level),
+        (None, None) => "bidrum".to_string(),
+    };
+
+    let mut lines = vec![
+        "osu file format v14".to_string(),
+        String::new(),
+        "[General]".to_string(),
+        format!("AudioFilename: {}", audio_filename),
+        "AudioLeadIn: 0".to_string(),
+        format!(
+            "PreviewTime: {}",
+            metadata.preview_start_in_ms.map(|x| x as i64).unwrap_or(-1)
+        ),
+        "Mode: 1".to_string(),
+        String::new(),
+        "[Metadata]".to_string(),
+        format!("Title:{}", title),
+        format!("TitleUnicode:{}", title),
+        format!("Artist:{}", chart.artist),
+        format!("ArtistUnicode:{}", chart.artist),
+        format!("Creator:{}", metadata.charter.clone().unwrap_or_default()),
+        format!("Version:{}", version),
+        String::new(),
+        "[Difficulty]".to_string(),
+        "HPDrainRate:5".to_string(),
+        "CircleSize:5".to_string(),
+        "OverallDifficulty:5".to_string(),
+        "ApproachRate:5".to_string(),
+        format!("SliderMultiplier:{}", OSU_SLIDER_MULTIPLIER),
+        "SliderTickRate:1".to_string(),
+        String::new(),
+        "[Events]".to_string(),
+    ];
+    if let Some(cover_image_filename) = cover_image_filename {
+        lines.push(format!("0,0,\"{}\",0,0", cover_image_filename));
+    }
+    lines.push(String::new());
+
+    // red lines at the start, bpm changes and time signature changes
+    lines.push("[TimingPoints]".to_string());
+    let mut timing_point_beats: Vec<Rational64> = chart
+        .bpm_changes
+        .iter()
+        .map(|x| x.beat())
+        .chain(chart.time_signatures.iter().map(|x| x.beat()))
+        .map(|x| x.max(Rational64::from_integer(0)))
+        .chain([Rational64::from_integer(0)])
+        .collect();
+    timing_point_beats.sort();
+    timing_point_beats.dedup();
+    for beat in timing_point_beats {
+        let meter = tempo_map
+            .measure_length_at(beat)
+            .round()
+            .to_integer()
+            .max(1);
+        lines.push(format!(
+            "{},{},{},1,0,100,1,0",
+            timing_of(&tempo_map, beat),
+            beat_length_of(&tempo_map, beat),
+            meter
+        ));
+    }
+    lines.push(String::new());
+
+    // notes at the same position become a big note
+    let mut hits: BTreeMap<Rational64, Vec<JangguFace>> = BTreeMap::new();
+    for note in chart.left_face.iter().chain(&chart.right_face) {
+        hits.entry(note.beat()).or_default().push(note.face);
+    }
+    for stroke in &chart.strokes {
+        let faces = stroke.stroke.faces();
+        let hit = hits.entry(stroke.beat()).or_default();
+        hit.extend(faces.궁채);
+        hit.extend(faces.열채);
+    }
+
+    // (timing, line)
+    let mut hit_objects: Vec<(i64, String)> = vec![];
+    for (beat, faces) in hits {
+        let timing = timing_of(&tempo_map, beat);
+        let is_kat = !faces.contains(&JangguFace::궁편);
+        let hit_sound = if is_kat { 8 } else { 0 } + if faces.len() > 1 { 4 } else { 0 };
+        hit_objects.push((
+            timing,
+            format!("256,192,{},1,{},0:0:0:0:", timing, hit_sound),
+        ));
+    }
+    for long_note in &chart.long_notes {
+        let timing = timing_of(&tempo_map, long_note.beat());
+        let duration = to_f64(
+            tempo_map.precise_timing_in_ms(long_note.end_beat())
+                - tempo_map.precise_timing_in_ms(long_note.beat()),
+        );
+        // the length of the slider in osu!pixels which has the same duration
+        let length =
+            duration / beat_length_of(&tempo_map, long_note.beat()) * OSU_SLIDER_MULTIPLIER * 100.0;
+        hit_objects.push((
+            timing,
+            format!(
+                "256,192,{},2,0,L|{}:192,1,{}",
+                timing,
+                256 + length.round() as i64,
+                length
+            ),
+        ));
+    }
+    for hat in &chart.hats {
+        let timing = timing_of(&tempo_map, hat.beat());
+        let end_timing = timing_of(&tempo_map, hat.beat() + 1);
+        hit_objects.push((
+            timing,
+            format!("256,192,{},12,0,{},0:0:0:0:", timing, end_timing),
+        ));
+    }
+    hit_objects.sort_by_key(|x| x.0);
+
+    lines.push("[HitObjects]".to_string());
+    lines.extend(hit_objects.into_iter().map(|x| x.1));
+
+    let mut beatmap = lines.join("\n");
+    beatmap.push('\n');
+    beatmap
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::export_osu;
+    use crate::{
+        import::{import_osu, ImportOptions},
+        janggu::{JangguFace, JangguStick, JangguStroke},
+        song::{
+            ChartMetadata, GameBpmChange, GameChart, GameHatNote, GameLongNote, GameLongNoteKind,
+            GameNote, GameStrokeNote,
+        },
+    };
+
+    /// Chart at 120 bpm from 1000ms, which changes into 60 bpm at 4th beat
+    fn chart() -> GameChart {
+        let mut chart = GameChart {
+            artist: String::from("Team Overchaos"),
+            metadata: ChartMetadata {
+                difficulty_name: Some(String::from("보통")),
+                charter: Some(String::from("Someone")),
+                ..Default::default()
+            },
+            delay: 1000,
+            bpm: Rational64::from_integer(120),
+            left_face: vec![
+                GameNote::create_raw_note(JangguStick::궁채, 0, 0, 1),
+                GameNote::create_raw_note(JangguStick::궁채, 5, 1, 2),
+            ],
+            right_face: vec![GameNote::create_raw_note(JangguStick::열채, 0, 1, 2)],
+            hats: vec![GameHatNote::create_raw_note(5, 0, 1)],
+            strokes: vec![
+                GameStrokeNote::create_raw_stroke_note(JangguStroke::덩넘겨덕, 1, 0, 1),
+                GameStrokeNote::create_raw_stroke_note(JangguStroke::덩넘겨쿵, 1, 1, 2),
+            ],
+            long_notes: vec![GameLongNote::create_raw_long_note(
+                GameLongNoteKind::Roll,
+                JangguStick::열채,
+                JangguFace::열편,
+                2,
+                0,
+                1,
+                3,
+                0,
+                1,
+            )],
+            bpm_changes: vec![GameBpmChange::create_raw_bpm_change(
+                4,
+                0,
+                1,
+                Rational64::from_integer(60),
+            )],
+            time_signatures: vec![],
+        };
+        chart.assign_note_ids();
+
+        chart
+    }
+
+    /// Lines of the section until the next blank line
+    fn section<'a>(beatmap: &'a str, name: &str) -> Vec<&'a str> {
+        beatmap
+            .lines()
+            .skip_while(|x| *x != name)
+            .skip(1)
+            .take_while(|x| !x.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn exports_osu_taiko_beatmap() {
+        let beatmap = export_osu(&chart(), "Song", "audio.mp3", Some("cover.png"));
+
+        let general = section(&beatmap, "[General]");
+        assert!(general.contains(&"AudioFilename: audio.mp3"));
+        assert!(general.contains(&"Mode: 1"));
+        let metadata = section(&beatmap, "[Metadata]");
+        for line in [
+            "Title:Song",
+            "Artist:Team Overchaos",
+            "Creator:Someone",
+            "Version:보통",
+        ] {
+            assert!(metadata.contains(&line), "{} is not exported", line);
+        }
+        assert_eq!(section(&beatmap, "[Events]"), vec!["0,0,\"cover.png\",0,0"]);
+
+        assert_eq!(
+            section(&beatmap, "[TimingPoints]"),
+            vec!["1000,500,4,1,0,100,1,0", "3000,1000,4,1,0,100,1,0"]
+        );
+        assert_eq!(
+            section(&beatmap, "[HitObjects]"),
+            vec![
+                // don and kat
+                "256,192,1000,1,0,0:0:0:0:",
+                "256,192,1250,1,8,0:0:0:0:",
+                // big don and big kat
+                "256,192,1500,1,4,0:0:0:0:",
+                "256,192,1750,1,12,0:0:0:0:",
+                // drumroll of a beat
+                "256,192,2000,2,0,L|396:192,1,140",
+                // denden of a beat at 60 bpm
+                "256,192,4000,12,0,5000,0:0:0:0:",
+                "256,192,4500,1,0,0:0:0:0:",
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_beatmap_is_imported_back() {
+        let chart = chart();
+        let beatmap = export_osu(&chart, "Song", "audio.mp3", None);
+        let imported = import_osu(&beatmap, &ImportOptions::default()).expect("valid beatmap");
+        let imported_chart = &imported.chart;
+
+        assert_eq!(imported.title, "Song");
+        assert_eq!(imported.audio_filename, Some("audio.mp3".to_string()));
+        assert_eq!(imported_chart.artist, chart.artist);
+        assert_eq!(
+            imported_chart.metadata.difficulty_name,
+            chart.metadata.difficulty_name
+        );
+        assert_eq!(imported_chart.metadata.charter, chart.metadata.charter);
+        assert_eq!(imported_chart.delay, chart.delay);
+        assert_eq!(imported_chart.bpm, chart.bpm);
+        assert_eq!(
+            imported_chart
+                .bpm_changes
+                .iter()
+                .map(|x| (x.beat(), x.bpm))
+                .collect::<Vec<_>>(),
+            vec![(Rational64::from_integer(4), Rational64::from_integer(60))]
+        );
+
+        let notes = |chart: &GameChart| {
+            chart
+                .left_face
+                .iter()
+                .chain(&chart.right_face)
+                .map(|x| (x.stick, x.beat()))
+                .collect::<Vec<_>>()
+        };
+        let strokes = |chart: &GameChart| {
+            chart
+                .strokes
+                .iter()
+                .map(|x| (x.stroke, x.beat()))
+                .collect::<Vec<_>>()
+        };
+        let long_notes = |chart: &GameChart| {
+            chart
+                .long_notes
+                .iter()
+                .map(|x| (x.kind, x.stick, x.face, x.beat(), x.end_beat()))
+                .collect::<Vec<_>>()
+        };
+        let hats = |chart: &GameChart| chart.hats.iter().map(|x| x.beat()).collect::<Vec<_>>();
+        assert_eq!(notes(imported_chart), notes(&chart));
+        assert_eq!(strokes(imported_chart), strokes(&chart));
+        assert_eq!(long_notes(imported_chart), long_notes(&chart));
+        assert_eq!(hats(imported_chart), hats(&chart));
+    }
+}
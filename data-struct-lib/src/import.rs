@@ -0,0 +1,86 @@
+//! Converts the charts of the other rhythm games into bidrum charts
+mod bms;
+mod chart_builder;
+mod dtx;
+mod import_error;
+mod osu;
+
+use std::{fs, path::Path};
+
+use crate::song::GameChart;
+
+pub use self::bms::import_bms;
+pub use self::dtx::import_dtx;
+pub use self::import_error::ImportError;
+pub use self::osu::import_osu;
+
+/// How the drumrolls (notes hit repeatedly for a while) are imported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrumrollImport {
+    /// imported as roll notes
+    Roll,
+    /// not imported
+    Drop,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub drumrolls: DrumrollImport,
+    /// The notes are snapped to the grid of `1 / quantization` beat
+    /// when the timing is stored in millisecond (e.g. osu!)
+    pub quantization: i64,
+}
+
+impl Default for ImportOptions {
+    fn default() -> ImportOptions {
+        ImportOptions {
+            drumrolls: DrumrollImport::Roll,
+            quantization: 48,
+        }
+    }
+}
+
+/// Chart converted from the other rhythm games
+#[derive(Debug, Clone)]
+pub struct ImportedChart {
+    pub title: String,
+    pub chart: GameChart,
+    /// audio file of the song, relative to the chart file
+    pub audio_filename: Option<String>,
+    /// image file to be used as the cover image, relative to the chart file
+    pub cover_image_filename: Option<String>,
+    /// problems which didn't stop the import (e.g. dropped notes)
+    pub warnings: Vec<String>,
+}
+
+/// Reads the chart file, and imports it in the format of its extension
+///
+/// Following formats are supported
+///   - osu!taiko: `.osu`
+///   - BMS: `.bms`, `.bme`, `.bml`, `.pms`
+///   - DTXMania: `.dtx`
+pub fn import_chart(path: &Path, options: &ImportOptions) -> Result<ImportedChart, ImportError> {
+    let extension = path
+        .extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let import = match extension.as_str() {
+        "osu" => import_osu,
+        "bms" | "bme" | "bml" | "pms" => import_bms,
+        "dtx" => import_dtx,
+        _ => {
+            return Err(ImportError::UnknownFormat {
+                path: path.to_path_buf(),
+            })
+        }
+    };
+
+    let bytes = fs::read(path).map_err(|err| ImportError::Io {
+        path: path.to_path_buf(),
+        source: err,
+    })?;
+    // charts which are not in utf-8 (e.g. Shift_JIS) are read as much as possible
+    let source = String::from_utf8_lossy(&bytes);
+
+    import(&source, options)
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use num_rational::Rational64;
+
+use crate::{
+    janggu::JangguFace,
+    song::{ChartMetadata, GameLongNoteKind},
+};
+
+use super::{
+    chart_builder::{quantize, snap_bpm, ChartBuilder, ImportedNote},
+    ImportError, ImportOptions, ImportedChart,
+};
+
+/// Object placed in the measure of BMS (and DTX)
+#[derive(Debug, Clone)]
+pub(super) struct BmsObject {
+    pub(super) channel: String,
+    /// position of the object in unit of beat
+    pub(super) beat: Rational64,
+    /// two-digit base-36 value of the object (e.g. `01`, `ZZ`)
+    pub(super) value: String,
+}
+
+/// Headers and objects of BMS (and DTX)
+pub(super) struct BmsSource {
+    /// values of the headers with their names in uppercase (e.g. `TITLE`, `WAV01`)
+    pub(super) headers: HashMap<String, String>,
+    /// objects sorted by the position
+    pub(super) objects: Vec<BmsObject>,
+    /// positions where the measures start in unit of beat
+    measure_starts: Vec<Rational64>,
+    /// lengths of the measures in unit of beat
+    measure_lengths: Vec<Rational64>,
+}
+
+/// The measure lengths are snapped to the grid of `1 / MEASURE_LENGTH_QUANTIZATION` beat
+const MEASURE_LENGTH_QUANTIZATION: i64 = 48;
+
+/// Parses the measure length of channel 02, which is the ratio to 4/4, into the length in unit of beat
+///
+/// The length is snapped to the grid, so that `0.333333` becomes 4/3 beats.
+fn parse_measure_length(value: &str) -> Option<Rational64> {
+    let value = value.trim().parse::<f64>().ok()?;
+    if !value.is_finite() || value <= 0.0 {
+        return None;
+    }
+
+    Some(
+        quantize(value * 4.0, MEASURE_LENGTH_QUANTIZATION)
+            .max(Rational64::new(1, MEASURE_LENGTH_QUANTIZATION)),
+    )
+}
+
+/// Parses the bpm of `#BPM` and `#BPMxx`, which is snapped to 3 digits after the decimal point
+fn parse_bms_bpm(value: &str) -> Option<Rational64> {
+    snap_bpm(value.trim().parse::<f64>().ok()?)
+}
+
+impl BmsSource {
+    /// Parses the lines of BMS and DTX, which are in one of the following forms
+    ///   - header: `#TITLE song`, `#TITLE: song`
+    ///   - objects: `#00111:01000100` (measure 001, channel 11)
+    ///
+    /// Only the first branch of `#RANDOM` is read.
+    pub(super) fn parse(
+        source: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<BmsSource, ImportError> {
+        let mut headers = HashMap::new();
+        // (measure, channel, position in the measure, value)
+        let mut placed_objects = vec![];
+        let mut measure_lengths: HashMap<usize, Rational64> = HashMap::new();
+        let mut skipping = false;
+        let mut has_random = false;
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim_start_matches('\u{feff}').trim();
+            let line = match line.strip_prefix('#') {
+                Some(line) => line,
+                None => continue,
+            };
+
+            let is_object = line.len() > 6
+                && line.is_char_boundary(6)
+                && line[..3].chars().all(|x| x.is_ascii_digit())
+                && line[3..5].chars().all(|x| x.is_ascii_alphanumeric())
+                && &line[5..6] == ":";
+            if !is_object {
+                let (name, value) = match line.find(|x: char| x.is_whitespace() || x == ':') {
+                    Some(position) => (&line[..position], line[position..].trim_start()),
+                    None => (line, ""),
+                };
+                let name = name.to_uppercase();
+                let value = value.strip_prefix(':').unwrap_or(value).trim();
+                match name.as_str() {
+                    "RANDOM" | "SETRANDOM" | "ENDRANDOM" => has_random = true,
+                    "IF" => skipping = value != "1",
+                    "ELSEIF" | "ELSE" => skipping = true,
+                    "ENDIF" | "ENDIFS" => skipping = false,
+                    _ if !skipping => {
+                        headers.insert(name, value.to_string());
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if skipping {
+                continue;
+            }
+
+            let measure: usize = line[..3].parse().expect("Measure consists of digits");
+            let channel = line[3..5].to_uppercase();
+            let data = line[6..].trim();
+            if channel == "02" {
+                let length = parse_measure_length(data).ok_or_else(|| {
+                    ImportError::parse(line_number, format!("Invalid measure length: {}", data))
+                })?;
+                measure_lengths.insert(measure, length);
+                continue;
+            }
+
+            let data: Vec<char> = data.chars().filter(|x| !x.is_whitespace()).collect();
+            if data.len() % 2 == 1 || !data.iter().all(|x| x.is_ascii_alphanumeric()) {
+                return Err(ImportError::parse(
+                    line_number,
+                    format!("Invalid objects of channel {}", channel),
+                ));
+            }
+            let count = data.len() / 2;
+            for (position, value) in data.chunks(2).enumerate() {
+                let value: String = value.iter().collect::<String>().to_uppercase();
+                if value != "00" {
+                    placed_objects.push((
+                        measure,
+                        channel.clone(),
+                        Rational64::new(position as i64, count as i64),
+                        value,
+                    ));
+                }
+            }
+        }
+
+        if has_random {
+            warnings.push("Only the first branch of #RANDOM is imported".to_string());
+        }
+
+        let measure_count = placed_objects
+            .iter()
+            .map(|x| x.0 + 1)
+            .chain(measure_lengths.keys().map(|x| x + 1))
+            .max()
+            .unwrap_or(0);
+        let mut measure_starts = vec![];
+        let mut lengths = vec![];
+        let mut start = Rational64::from_integer(0);
+        for measure in 0..measure_count {
+            let length = measure_lengths
+                .get(&measure)
+                .cloned()
+                .unwrap_or(Rational64::from_integer(4));
+            measure_starts.push(start);
+            lengths.push(length);
+            start += length;
+        }
+
+        let mut objects: Vec<BmsObject> = placed_objects
+            .into_iter()
+            .map(|(measure, channel, position, value)| BmsObject {
+                channel: channel,
+                beat: measure_starts[measure] + position * lengths[measure],
+                value: value,
+            })
+            .collect();
+        objects.sort_by_key(|x| x.beat);
+
+        Ok(BmsSource {
+            headers: headers,
+            objects: objects,
+            measure_starts: measure_starts,
+            measure_lengths: lengths,
+        })
+    }
+
+    pub(super) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(name)
+            .map(|x| x.as_str())
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Creates the chart builder with the bpm changes and the time signatures
+    ///
+    /// The bpm changes are read from channel 03 (hexadecimal bpm) and 08 (`#BPMxx`).
+    pub(super) fn chart_builder(&self) -> Result<ChartBuilder, ImportError> {
+        let bpm = match self.header("BPM") {
+            Some(bpm) => parse_bms_bpm(bpm)
+                .ok_or_else(|| ImportError::unsupported(format!("Invalid bpm: {}", bpm)))?,
+            None => {
+                return Err(ImportError::unsupported(
+                    "There's no #BPM in the chart".to_string(),
+                ))
+            }
+        };
+        let mut builder = ChartBuilder::new(bpm);
+
+        for object in &self.objects {
+            let bpm = match object.channel.as_str() {
+                "03" => i64::from_str_radix(&object.value, 16)
+                    .ok()
+                    .map(Rational64::from_integer),
+                "08" => self
+                    .header(&format!("BPM{}", object.value))
+                    .and_then(parse_bms_bpm),
+                _ => continue,
+            };
+            match bpm {
+                Some(bpm) if bpm > Rational64::from_integer(0) => {
+                    builder.bpm_change(object.beat, bpm)
+                }
+                _ => builder
+                    .warnings
+                    .push(format!("Invalid bpm change: {}", object.value)),
+            }
+        }
+        if self.objects.iter().any(|x| x.channel == "09") {
+            builder
+                .warnings
+                .push("Stops (channel 09) are not supported".to_string());
+        }
+
+        for (start, length) in self.measure_starts.iter().zip(&self.measure_lengths) {
+            builder.time_signature(*start, *length);
+        }
+
+        Ok(builder)
+    }
+
+    /// Finds the audio file, which is the first background sound (channel 01)
+    ///
+    /// Returns the file name and its position in unit of beat.
+    pub(super) fn background_audio(&self) -> Option<(String, Rational64)> {
+        let object = self.objects.iter().find(|x| x.channel == "01")?;
+        let filename = self.header(&format!("WAV{}", object.value))?;
+
+        Some((filename.to_string(), object.beat))
+    }
+}
+
+/// face of the key lane of BMS
+///
+/// Keys 1~3 are 궁편 and keys 4~7 are 열편, while the scratch is a hat note.
+fn bms_lane(lane: char) -> Option<ImportedNote> {
+    match lane {
+        '1' | '2' | '3' => Some(ImportedNote::Tap(JangguFace::궁편)),
+        '4' | '5' | '8' | '9' => Some(ImportedNote::Tap(JangguFace::열편)),
+        '6' => Some(ImportedNote::Hat),
+        _ => None,
+    }
+}
+
+fn difficulty_name(difficulty: &str) -> Option<String> {
+    let name = match difficulty {
+        "1" => "BEGINNER",
+        "2" => "NORMAL",
+        "3" => "HYPER",
+        "4" => "ANOTHER",
+        "5" => "INSANE",
+        _ => return None,
+    };
+
+    Some(name.to_string())
+}
+
+/// Imports BMS (`.bms`, `.bme`, `.bml`, `.pms`)
+///
+/// The key lanes of both players are converted as following,
+/// since there's no drum in BMS
+///   - keys 1~3: 궁편
+///   - keys 4~7: 열편
+///   - scratch: hat note
+///   - long notes (channel 5x/6x and `#LNOBJ`): hold notes
+///
+/// The first background sound is regarded as the audio of the song,
+/// and the notes before it are dropped.
+pub fn import_bms(source: &str, _options: &ImportOptions) -> Result<ImportedChart, ImportError> {
+    let mut warnings = vec![];
+    let source = BmsSource::parse(source, &mut warnings)?;
+    let mut builder = source.chart_builder()?;
+
+    let ln_object = source.header("LNOBJ").map(|x| x.to_uppercase());
+    // last tap note of each lane, which becomes a hold note with #LNOBJ
+    let mut last_taps: HashMap<String, (Rational64, JangguFace)> = HashMap::new();
+    // start of the long note of each lane (channel 5x/6x)
+    let mut long_note_starts: HashMap<String, Rational64> = HashMap::new();
+    for object in &source.objects {
+        let mut chars = object.channel.chars();
+        let (player, lane) = match (chars.next(), chars.next()) {
+            (Some(player), Some(lane)) => (player, lane),
+            _ => continue,
+        };
+        let note = match bms_lane(lane) {
+            Some(note) => note,
+            None => continue,
+        };
+
+        match player {
+            '1' | '2' => {
+                if let (Some(ln_object), ImportedNote::Tap(_)) = (&ln_object, &note) {
+                    if object.value == *ln_object {
+                        if let Some((beat, face)) = last_taps.remove(&object.channel) {
+                            builder.note(
+                                beat,
+                                ImportedNote::LongNote {
+                                    kind: GameLongNoteKind::Hold,
+                                    face: face,
+                                    end_beat: object.beat,
+                                    required_hits: None,
+                                },
+                            );
+                        }
+                        continue;
+                    }
+                }
+
+                if let ImportedNote::Tap(face) = note {
+                    // the previous one is not the start of the long note
+                    if let Some((beat, face)) =
+                        last_taps.insert(object.channel.clone(), (object.beat, face))
+                    {
+                        builder.note(beat, ImportedNote::Tap(face));
+                    }
+                } else {
+                    builder.note(object.beat, note);
+                }
+            }
+            '5' | '6' => {
+                let face = match note {
+                    ImportedNote::Tap(face) => face,
+                    _ => continue,
+                };
+                match long_note_starts.remove(&object.channel) {
+                    Some(start) => builder.note(
+                        start,
+                        ImportedNote::LongNote {
+                            kind: GameLongNoteKind::Hold,
+                            face: face,
+                            end_beat: object.beat,
+                            required_hits: None,
+                        },
+                    ),
+                    None => {
+                        long_note_starts.insert(object.channel.clone(), object.beat);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (beat, face) in last_taps.into_values() {
+        builder.note(beat, ImportedNote::Tap(face));
+    }
+    if !long_note_starts.is_empty() {
+        warnings.push(format!(
+            "{} long note(s) without the end are dropped",
+            long_note_starts.len()
+        ));
+    }
+
+    let (audio_filename, origin) = match source.background_audio() {
+        Some((filename, beat)) => (Some(filename), beat),
+        None => {
+            warnings.push("There's no background sound to be used as the audio".to_string());
+            (None, Rational64::from_integer(0))
+        }
+    };
+    let metadata = ChartMetadata {
+        difficulty_name: source.header("DIFFICULTY").and_then(difficulty_name),
+        level: source.header("PLAYLEVEL").and_then(|x| x.parse().ok()),
+        ..Default::default()
+    };
+    let (chart, builder_warnings) = builder.build(
+        source.header("ARTIST").unwrap_or_default().to_string(),
+        metadata,
+        0,
+        origin,
+    );
+    warnings.extend(builder_warnings);
+
+    let title = match source.header("SUBTITLE") {
+        Some(subtitle) => format!(
+            "{} {}",
+            source.header("TITLE").unwrap_or_default(),
+            subtitle
+        ),
+        None => source.header("TITLE").unwrap_or_default().to_string(),
+    };
+
+    Ok(ImportedChart {
+        title: title,
+        chart: chart,
+        audio_filename: audio_filename,
+        cover_image_filename: source
+            .header("STAGEFILE")
+            .or(source.header("BANNER"))
+            .map(|x| x.to_string()),
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::{import_bms, parse_measure_length};
+    use crate::{import::ImportOptions, janggu::JangguStroke};
+
+    #[test]
+    fn imports_keys_and_bpm_changes() {
+        let source = "\
+#TITLE Song
+#SUBTITLE [ANOTHER]
+#ARTIST Someone
+#BPM 150.5
+#PLAYLEVEL 7
+#DIFFICULTY 4
+#WAV01 song.ogg
+#BPM01 155.5555556
+#00001:01
+#00011:01000100
+#00014:00000001
+#00008:0001
+#00016:00000001
+#00102:0.75
+#00111:01
+#00114:01
+";
+        let imported = import_bms(source, &ImportOptions::default()).expect("valid chart");
+        let chart = &imported.chart;
+
+        assert_eq!(imported.title, "Song [ANOTHER]");
+        assert_eq!(imported.audio_filename, Some("song.ogg".to_string()));
+        assert_eq!(chart.metadata.level, Some(7));
+        assert_eq!(chart.metadata.difficulty_name, Some("ANOTHER".to_string()));
+        assert_eq!(chart.bpm, Rational64::new(301, 2));
+        assert_eq!(
+            chart.left_face.iter().map(|x| x.beat()).collect::<Vec<_>>(),
+            vec![Rational64::from_integer(0), Rational64::from_integer(2)]
+        );
+        assert_eq!(
+            chart
+                .right_face
+                .iter()
+                .map(|x| x.beat())
+                .collect::<Vec<_>>(),
+            vec![Rational64::from_integer(3)]
+        );
+        // keys on both faces at the same position
+        assert_eq!(chart.strokes.len(), 1);
+        assert_eq!(chart.strokes[0].stroke, JangguStroke::덩);
+        assert_eq!(chart.strokes[0].beat(), Rational64::from_integer(4));
+        assert_eq!(chart.hats.len(), 1);
+
+        // 155.5555556 is snapped to 3 digits after the decimal point
+        assert_eq!(chart.bpm_changes.len(), 1);
+        assert_eq!(chart.bpm_changes[0].beat(), Rational64::from_integer(2));
+        assert_eq!(chart.bpm_changes[0].bpm, Rational64::new(155556, 1000));
+        assert_eq!(chart.time_signatures.len(), 1);
+        assert_eq!(chart.time_signatures[0].beat(), Rational64::from_integer(4));
+        assert_eq!(chart.time_signatures[0].beats_per_measure, 3);
+        assert_eq!(chart.time_signatures[0].beat_unit, 4);
+    }
+
+    #[test]
+    fn imports_long_notes() {
+        let source = "\
+#BPM 120
+#WAV01 song.ogg
+#LNOBJ ZZ
+#00001:01
+#00051:01000100
+#00014:0100ZZ00
+";
+        let imported = import_bms(source, &ImportOptions::default()).expect("valid chart");
+        let long_notes = &imported.chart.long_notes;
+
+        assert_eq!(long_notes.len(), 2);
+        assert!(long_notes
+            .iter()
+            .any(|x| x.beat() == Rational64::from_integer(0)
+                && x.end_beat() == Rational64::from_integer(2)));
+        assert!(imported.chart.left_face.is_empty());
+        assert!(imported.chart.right_face.is_empty());
+    }
+
+    #[test]
+    fn measure_length_is_snapped() {
+        assert_eq!(
+            parse_measure_length("0.75"),
+            Some(Rational64::from_integer(3))
+        );
+        assert_eq!(
+            parse_measure_length("0.333333"),
+            Some(Rational64::new(4, 3))
+        );
+        assert_eq!(parse_measure_length("0.0001"), Some(Rational64::new(1, 48)));
+        assert_eq!(parse_measure_length("0"), None);
+        assert_eq!(parse_measure_length("NaN"), None);
+        assert_eq!(parse_measure_length("long"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_chart() {
+        let options = ImportOptions::default();
+
+        assert!(import_bms("#TITLE no bpm\n#00011:01", &options).is_err());
+        assert!(import_bms("#BPM 120\n#00102:zero", &options).is_err());
+        assert!(import_bms("#BPM 120\n#00011:010", &options).is_err());
+    }
+}
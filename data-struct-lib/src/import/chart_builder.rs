@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use num_rational::Rational64;
+
+use crate::{
+    janggu::{JangguFace, JangguStick, JangguStroke},
+    song::{
+        split_beat, ChartMetadata, GameBpmChange, GameChart, GameHatNote, GameLongNote,
+        GameLongNoteKind, GameNote, GameStrokeNote, GameTimeSignature,
+    },
+};
+
+/// Note converted from the other rhythm games, which is not placed in the chart yet
+#[derive(Debug, Clone)]
+pub(super) enum ImportedNote {
+    /// note hit by the stick of the same side (궁채 on 궁편, 열채 on 열편)
+    ///
+    /// The taps on both faces at the same position become 덩.
+    Tap(JangguFace),
+    Stroke(JangguStroke),
+    Hat,
+    LongNote {
+        kind: GameLongNoteKind,
+        face: JangguFace,
+        end_beat: Rational64,
+        required_hits: Option<u32>,
+    },
+}
+
+/// stick which hits the face in the imported charts
+fn stick_of(face: JangguFace) -> JangguStick {
+    match face {
+        JangguFace::궁편 => JangguStick::궁채,
+        JangguFace::열편 => JangguStick::열채,
+    }
+}
+
+/// Snaps the bpm to 3 digits after the decimal point
+///
+/// The bpm of the other rhythm games can have many digits (e.g. `155.5555556`, `60000 / 346.5734`),
+/// which overflow the timings of the tempo map in rational number.
+/// Returns `None` if the bpm is not positive or too large.
+pub(super) fn snap_bpm(bpm: f64) -> Option<Rational64> {
+    let bpm = (bpm * 1000.0).round();
+    if !(1.0..=1e12).contains(&bpm) {
+        return None;
+    }
+
+    Some(Rational64::new(bpm as i64, 1000))
+}
+
+/// Snaps the position in unit of beat to the grid of `1 / quantization` beat
+pub(super) fn quantize(beat: f64, quantization: i64) -> Rational64 {
+    Rational64::new((beat * quantization as f64).round() as i64, quantization)
+}
+
+/// Converts the length of the measure in unit of beat into time signature
+fn time_signature_of(measure_length: Rational64) -> (u32, u32) {
+    // 3 beats -> 3/4, 3.5 beats -> 7/8
+    (
+        *measure_length.numer() as u32,
+        *measure_length.denom() as u32 * 4,
+    )
+}
+
+/// Collects the events of the imported chart in unit of beat, and converts them into the chart
+pub(super) struct ChartBuilder {
+    bpm: Rational64,
+    bpm_changes: Vec<(Rational64, Rational64)>,
+    /// positions and lengths of the measures in unit of beat
+    time_signatures: Vec<(Rational64, Rational64)>,
+    notes: Vec<(Rational64, ImportedNote)>,
+    /// problems which didn't stop the import
+    pub(super) warnings: Vec<String>,
+}
+
+impl ChartBuilder {
+    pub(super) fn new(bpm: Rational64) -> ChartBuilder {
+        ChartBuilder {
+            bpm: bpm,
+            bpm_changes: vec![],
+            time_signatures: vec![],
+            notes: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub(super) fn bpm_change(&mut self, beat: Rational64, bpm: Rational64) {
+        self.bpm_changes.push((beat, bpm));
+    }
+
+    /// A new measure of the given length in unit of beat is started from the position
+    pub(super) fn time_signature(&mut self, beat: Rational64, measure_length: Rational64) {
+        self.time_signatures.push((beat, measure_length));
+    }
+
+    pub(super) fn note(&mut self, beat: Rational64, note: ImportedNote) {
+        self.notes.push((beat, note));
+    }
+
+    /// Creates the chart whose first beat is at `origin`
+    ///
+    /// The notes before `origin` are dropped,
+    /// and the bpm and the time signature at `origin` are applied from the first beat.
+    pub(super) fn build(
+        mut self,
+        artist: String,
+        metadata: ChartMetadata,
+        delay: u64,
+        origin: Rational64,
+    ) -> (GameChart, Vec<String>) {
+        let zero = Rational64::from_integer(0);
+
+        // tempo
+        self.bpm_changes.sort_by_key(|x| x.0);
+        let mut bpm = self.bpm;
+        let mut bpm_changes: Vec<GameBpmChange> = vec![];
+        let mut last_bpm = bpm;
+        for (beat, new_bpm) in &self.bpm_changes {
+            if *beat <= origin {
+                bpm = *new_bpm;
+                last_bpm = *new_bpm;
+            } else if *new_bpm != last_bpm {
+                let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat - origin);
+                bpm_changes.push(GameBpmChange::create_raw_bpm_change(
+                    beat_index,
+                    tick_nomiator,
+                    tick_denomiator,
+                    *new_bpm,
+                ));
+                last_bpm = *new_bpm;
+            }
+        }
+
+        // meter
+        self.time_signatures.sort_by_key(|x| x.0);
+        let mut time_signatures: Vec<GameTimeSignature> = vec![];
+        let mut last_measure_length = Rational64::from_integer(4);
+        for (beat, measure_length) in &self.time_signatures {
+            let beat = (beat - origin).max(zero);
+            if beat == zero {
+                time_signatures.clear();
+                last_measure_length = Rational64::from_integer(4);
+            }
+            if *measure_length == last_measure_length {
+                continue;
+            }
+            let (beats_per_measure, beat_unit) = time_signature_of(*measure_length);
+            let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+            time_signatures.push(GameTimeSignature::create_raw_time_signature(
+                beat_index,
+                tick_nomiator,
+                tick_denomiator,
+                beats_per_measure,
+                beat_unit,
+            ));
+            last_measure_length = *measure_length;
+        }
+
+        // notes
+        let note_count = self.notes.len();
+        self.notes.retain(|x| x.0 >= origin);
+        if self.notes.len() < note_count {
+            self.warnings.push(format!(
+                "{} note(s) before the start of the audio are dropped",
+                note_count - self.notes.len()
+            ));
+        }
+        self.notes.sort_by_key(|x| x.0);
+
+        let mut taps: BTreeMap<Rational64, (bool, bool)> = BTreeMap::new();
+        let mut strokes: BTreeMap<Rational64, JangguStroke> = BTreeMap::new();
+        let mut hats: BTreeSet<Rational64> = BTreeSet::new();
+        let mut long_notes: Vec<GameLongNote> = vec![];
+        let mut overlapped = 0;
+        for (beat, note) in &self.notes {
+            let beat = beat - origin;
+            match note {
+                ImportedNote::Tap(face) => {
+                    let faces = taps.entry(beat).or_insert((false, false));
+                    match face {
+                        JangguFace::궁편 => faces.0 = true,
+                        JangguFace::열편 => faces.1 = true,
+                    }
+                }
+                ImportedNote::Stroke(stroke) => {
+                    if strokes.insert(beat, *stroke).is_some() {
+                        overlapped += 1;
+                    }
+                }
+                ImportedNote::Hat => {
+                    hats.insert(beat);
+                }
+                ImportedNote::LongNote {
+                    kind,
+                    face,
+                    end_beat,
+                    required_hits,
+                } => {
+                    let end_beat = end_beat - origin;
+                    let stick = stick_of(*face);
+                    let is_overlapped = long_notes
+                        .iter()
+                        .any(|x| x.stick == stick && x.end_beat() >= beat);
+                    if end_beat <= beat || is_overlapped {
+                        overlapped += 1;
+                        continue;
+                    }
+
+                    let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+                    let (end_beat_index, end_tick_nomiator, end_tick_denomiator) =
+                        split_beat(end_beat);
+                    let mut long_note = GameLongNote::create_raw_long_note(
+                        *kind,
+                        stick,
+                        *face,
+                        beat_index,
+                        tick_nomiator,
+                        tick_denomiator,
+                        end_beat_index,
+                        end_tick_nomiator,
+                        end_tick_denomiator,
+                    );
+                    long_note.required_hits = *required_hits;
+                    long_notes.push(long_note);
+                }
+            }
+        }
+
+        // the stick can't hit the other notes while it's on the long note
+        let is_on_long_note = |stick: JangguStick, beat: Rational64| {
+            long_notes
+                .iter()
+                .any(|x| x.stick == stick && x.beat() <= beat && beat <= x.end_beat())
+        };
+
+        let mut left_face = vec![];
+        let mut right_face = vec![];
+        for (beat, (궁편, 열편)) in taps {
+            if strokes.contains_key(&beat) {
+                overlapped += 1;
+                continue;
+            }
+            if 궁편 && 열편 {
+                strokes.insert(beat, JangguStroke::덩);
+                continue;
+            }
+
+            let face = if 궁편 {
+                JangguFace::궁편
+            } else {
+                JangguFace::열편
+            };
+            let stick = stick_of(face);
+            if is_on_long_note(stick, beat) {
+                overlapped += 1;
+                continue;
+            }
+
+            let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+            let note = GameNote::create_raw_note(stick, beat_index, tick_nomiator, tick_denomiator);
+            match face {
+                JangguFace::궁편 => left_face.push(note),
+                JangguFace::열편 => right_face.push(note),
+            }
+        }
+
+        let mut stroke_notes = vec![];
+        for (beat, stroke) in strokes {
+            let faces = stroke.faces();
+            if (faces.궁채.is_some() && is_on_long_note(JangguStick::궁채, beat))
+                || (faces.열채.is_some() && is_on_long_note(JangguStick::열채, beat))
+            {
+                overlapped += 1;
+                continue;
+            }
+
+            let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+            stroke_notes.push(GameStrokeNote::create_raw_stroke_note(
+                stroke,
+                beat_index,
+                tick_nomiator,
+                tick_denomiator,
+            ));
+        }
+        if overlapped > 0 {
+            self.warnings.push(format!(
+                "{} note(s) overlapping the other notes are dropped",
+                overlapped
+            ));
+        }
+
+        let hats = hats
+            .into_iter()
+            .map(|beat| {
+                let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+                GameHatNote::create_raw_note(beat_index, tick_nomiator, tick_denomiator)
+            })
+            .collect();
+
+        let mut chart = GameChart {
+            artist: artist,
+            metadata: metadata,
+            delay: delay,
+            bpm: bpm,
+            left_face: left_face,
+            right_face: right_face,
+            hats: hats,
+            strokes: stroke_notes,
+            long_notes: long_notes,
+            bpm_changes: bpm_changes,
+            time_signatures: time_signatures,
+        };
+        chart.assign_note_ids();
+
+        (chart, self.warnings)
+    }
+}
@@ -0,0 +1,163 @@
+use num_rational::Rational64;
+
+use crate::{janggu::JangguFace, song::ChartMetadata};
+
+use super::{
+    bms::BmsSource, chart_builder::ImportedNote, ImportError, ImportOptions, ImportedChart,
+};
+
+/// note of the drum lane of DTX
+fn drum_lane(channel: &str) -> Option<ImportedNote> {
+    match channel {
+        // bass drum, low tom, floor tom, left bass drum
+        "13" | "15" | "17" | "1C" => Some(ImportedNote::Tap(JangguFace::궁편)),
+        // snare drum, high tom
+        "12" | "14" => Some(ImportedNote::Tap(JangguFace::열편)),
+        // crash cymbals
+        "16" | "1A" => Some(ImportedNote::Hat),
+        _ => None,
+    }
+}
+
+/// Imports DTXMania drum chart (`.dtx`)
+///
+/// The drum lanes are converted as following
+///   - bass drum, low tom, floor tom: 궁편
+///   - snare drum, high tom: 열편
+///   - crash cymbals: hat note
+///   - hi-hats, ride cymbal and pedal: dropped
+///
+/// The first background sound is regarded as the audio of the song,
+/// and the notes before it are dropped.
+pub fn import_dtx(source: &str, _options: &ImportOptions) -> Result<ImportedChart, ImportError> {
+    let mut warnings = vec![];
+    let source = BmsSource::parse(source, &mut warnings)?;
+    let mut builder = source.chart_builder()?;
+
+    let mut dropped = 0;
+    for object in &source.objects {
+        match drum_lane(&object.channel) {
+            Some(note) => builder.note(object.beat, note),
+            None => {
+                // hi-hat close, hi-hat open, ride cymbal, left pedal
+                if ["11", "18", "19", "1B"].contains(&object.channel.as_str()) {
+                    dropped += 1;
+                }
+            }
+        }
+    }
+    if dropped > 0 {
+        warnings.push(format!(
+            "{} note(s) of hi-hats, ride cymbal and pedal are dropped",
+            dropped
+        ));
+    }
+
+    let (audio_filename, origin) = match source.background_audio() {
+        Some((filename, beat)) => (Some(filename), beat),
+        None => {
+            warnings.push("There's no background sound to be used as the audio".to_string());
+            (None, Rational64::from_integer(0))
+        }
+    };
+    let metadata = ChartMetadata {
+        level: source.header("DLEVEL").and_then(|x| x.parse().ok()),
+        description: source.header("COMMENT").map(|x| x.to_string()),
+        ..Default::default()
+    };
+    let (chart, builder_warnings) = builder.build(
+        source.header("ARTIST").unwrap_or_default().to_string(),
+        metadata,
+        0,
+        origin,
+    );
+    warnings.extend(builder_warnings);
+
+    Ok(ImportedChart {
+        title: source.header("TITLE").unwrap_or_default().to_string(),
+        chart: chart,
+        audio_filename: audio_filename,
+        cover_image_filename: source
+            .header("PREIMAGE")
+            .or(source.header("STAGEFILE"))
+            .map(|x| x.to_string()),
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::import_dtx;
+    use crate::import::ImportOptions;
+
+    #[test]
+    fn imports_drum_lanes() {
+        let source = "\
+#TITLE: Song
+#ARTIST: Someone
+#BPM: 120
+#DLEVEL: 50
+#COMMENT: test chart
+#WAV01: bgm.ogg
+#00001: 01
+#00013: 01000000
+#00012: 00010000
+#00016: 00000100
+#00011: 01010101
+";
+        let imported = import_dtx(source, &ImportOptions::default()).expect("valid chart");
+        let chart = &imported.chart;
+
+        assert_eq!(imported.title, "Song");
+        assert_eq!(imported.audio_filename, Some("bgm.ogg".to_string()));
+        assert_eq!(chart.artist, "Someone");
+        assert_eq!(chart.metadata.level, Some(50));
+        assert_eq!(chart.metadata.description, Some("test chart".to_string()));
+        assert_eq!(
+            chart.left_face.iter().map(|x| x.beat()).collect::<Vec<_>>(),
+            vec![Rational64::from_integer(0)]
+        );
+        assert_eq!(
+            chart
+                .right_face
+                .iter()
+                .map(|x| x.beat())
+                .collect::<Vec<_>>(),
+            vec![Rational64::from_integer(1)]
+        );
+        assert_eq!(
+            chart.hats.iter().map(|x| x.beat()).collect::<Vec<_>>(),
+            vec![Rational64::from_integer(2)]
+        );
+        assert!(imported
+            .warnings
+            .contains(&"4 note(s) of hi-hats, ride cymbal and pedal are dropped".to_string()));
+    }
+
+    #[test]
+    fn drops_notes_before_the_audio() {
+        let source = "\
+#BPM: 120
+#WAV01: bgm.ogg
+#00013: 01000000
+#00101: 01
+#00113: 00000001
+";
+        let imported = import_dtx(source, &ImportOptions::default()).expect("valid chart");
+
+        assert_eq!(
+            imported
+                .chart
+                .left_face
+                .iter()
+                .map(|x| x.beat())
+                .collect::<Vec<_>>(),
+            vec![Rational64::from_integer(3)]
+        );
+        assert!(imported
+            .warnings
+            .contains(&"1 note(s) before the start of the audio are dropped".to_string()));
+    }
+}
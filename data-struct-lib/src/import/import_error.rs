@@ -0,0 +1,60 @@
+use std::{error::Error, fmt, path::PathBuf};
+
+/// Error while importing the charts of other rhythm games
+#[derive(Debug)]
+pub enum ImportError {
+    /// Failed to read the chart file
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The chart file is broken
+    Parse {
+        /// line where the error occurred (starting from 1)
+        line: usize,
+        message: String,
+    },
+    /// The chart can't be converted (e.g. unsupported game mode, no bpm)
+    Unsupported { message: String },
+    /// The format of the file is not known from its extension
+    UnknownFormat { path: PathBuf },
+}
+
+impl ImportError {
+    pub(super) fn parse(line: usize, message: String) -> ImportError {
+        ImportError::Parse {
+            line: line,
+            message: message,
+        }
+    }
+
+    pub(super) fn unsupported(message: String) -> ImportError {
+        ImportError::Unsupported { message: message }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io { path, source } => {
+                write!(f, "Failed to read {}: {}", path.display(), source)
+            }
+            ImportError::Parse { line, message } => {
+                write!(f, "Failed to parse line {}: {}", line, message)
+            }
+            ImportError::Unsupported { message } => write!(f, "{}", message),
+            ImportError::UnknownFormat { path } => {
+                write!(f, "Unknown chart format: {}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
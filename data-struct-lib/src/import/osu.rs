@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use num_rational::Rational64;
+
+use crate::{
+    janggu::{JangguFace, JangguInputState, JangguStroke},
+    song::{ChartMetadata, GameLongNoteKind},
+};
+
+use super::{
+    chart_builder::{quantize, snap_bpm, ChartBuilder, ImportedNote},
+    DrumrollImport, ImportError, ImportOptions, ImportedChart,
+};
+
+/// Red(uninherited) or green(inherited) line of the beatmap
+#[derive(Debug, Clone)]
+struct TimingPoint {
+    time: f64,
+    /// millisecond per beat of red line, or negative inverse slider velocity percentage of green line
+    beat_length: f64,
+    meter: u32,
+    uninherited: bool,
+}
+
+/// Red line with its position in unit of beat
+struct RedLine {
+    time: f64,
+    beat_length: f64,
+    bpm: Rational64,
+    beat: Rational64,
+}
+
+fn parse_field<T: std::str::FromStr>(
+    fields: &[impl AsRef<str>],
+    index: usize,
+    line: usize,
+) -> Result<T, ImportError> {
+    fields
+        .get(index)
+        .and_then(|x| x.as_ref().trim().parse::<T>().ok())
+        .ok_or_else(|| ImportError::parse(line, format!("Invalid field #{}", index + 1)))
+}
+
+/// Imports osu!taiko beatmap (`.osu`)
+///
+/// The notes are converted as following
+///   - don: 궁편, kat: 열편
+///   - big don/kat: both sticks on the face (덩(넘겨덕)/덩(넘겨쿵))
+///   - drumroll: roll note on 열편, or dropped
+///   - denden(spinner): hat note
+///
+/// osu!standard beatmaps are converted in the same way.
+pub fn import_osu(source: &str, options: &ImportOptions) -> Result<ImportedChart, ImportError> {
+    let mut warnings = vec![];
+    let mut section = String::new();
+    let mut values: HashMap<String, String> = HashMap::new();
+    let mut background = None;
+    let mut timing_points = vec![];
+    // (time, type, hit sound, fields, line)
+    let mut hit_objects = vec![];
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if index == 0 {
+            if !line.starts_with("osu file format") {
+                return Err(ImportError::parse(
+                    line_number,
+                    "Not an osu! beatmap".to_string(),
+                ));
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+
+        match section.as_str() {
+            "General" | "Metadata" | "Difficulty" => {
+                if let Some((key, value)) = line.split_once(':') {
+                    values.insert(key.trim().to_string(), value.trim().to_string());
+                }
+            }
+            "Events" => {
+                let fields: Vec<&str> = line.split(',').collect();
+                if background.is_none()
+                    && fields.len() >= 3
+                    && (fields[0] == "0" || fields[0] == "Background")
+                {
+                    background = Some(fields[2].trim().trim_matches('"').to_string());
+                }
+            }
+            "TimingPoints" => {
+                let fields: Vec<&str> = line.split(',').collect();
+                let beat_length: f64 = parse_field(&fields, 1, line_number)?;
+                timing_points.push(TimingPoint {
+                    time: parse_field(&fields, 0, line_number)?,
+                    beat_length: beat_length,
+                    meter: parse_field(&fields, 2, line_number).unwrap_or(4),
+                    uninherited: fields
+                        .get(6)
+                        .map(|x| x.trim() == "1")
+                        .unwrap_or(beat_length > 0.0),
+                });
+            }
+            "HitObjects" => {
+                let fields: Vec<&str> = line.split(',').collect();
+                let time: f64 = parse_field(&fields, 2, line_number)?;
+                let object_type: u32 = parse_field(&fields, 3, line_number)?;
+                let hit_sound: u32 = parse_field(&fields, 4, line_number)?;
+                hit_objects.push((
+                    time,
+                    object_type,
+                    hit_sound,
+                    fields.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+                    line_number,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    match values.get("Mode").map(|x| x.as_str()).unwrap_or("0") {
+        "1" => {}
+        "0" => warnings.push("osu!standard beatmap is converted as osu!taiko".to_string()),
+        mode => {
+            return Err(ImportError::unsupported(format!(
+                "Unsupported game mode of osu!: {}",
+                mode
+            )))
+        }
+    }
+
+    // red lines
+    timing_points.sort_by(|a, b| a.time.total_cmp(&b.time));
+    let mut red_lines: Vec<RedLine> = vec![];
+    let mut meters = vec![];
+    for point in timing_points.iter().filter(|x| x.uninherited) {
+        let bpm = match snap_bpm(60000.0 / point.beat_length) {
+            Some(bpm) => bpm,
+            None => {
+                return Err(ImportError::unsupported(format!(
+                    "Invalid beat length of the timing point at {}ms",
+                    point.time
+                )))
+            }
+        };
+        let beat = match red_lines.last() {
+            Some(last) => {
+                last.beat
+                    + quantize(
+                        (point.time - last.time) / last.beat_length,
+                        options.quantization,
+                    )
+            }
+            None => Rational64::from_integer(0),
+        };
+        red_lines.push(RedLine {
+            time: point.time,
+            beat_length: point.beat_length,
+            bpm: bpm,
+            beat: beat,
+        });
+        meters.push(point.meter);
+    }
+    if red_lines.is_empty() {
+        return Err(ImportError::unsupported(
+            "There's no uninherited timing point".to_string(),
+        ));
+    }
+
+    let red_line_at = |time: f64| {
+        red_lines
+            .iter()
+            .rev()
+            .find(|x| x.time <= time)
+            .unwrap_or(&red_lines[0])
+    };
+    let beat_at = |time: f64| {
+        let red_line = red_line_at(time);
+        red_line.beat
+            + quantize(
+                (time - red_line.time) / red_line.beat_length,
+                options.quantization,
+            )
+    };
+    // slider velocity multiplier, which is reset by red lines
+    let slider_velocity_at = |time: f64| match timing_points.iter().rev().find(|x| x.time <= time) {
+        Some(point) if !point.uninherited && point.beat_length < 0.0 => {
+            (-100.0 / point.beat_length).clamp(0.1, 10.0)
+        }
+        _ => 1.0,
+    };
+
+    let mut builder = ChartBuilder::new(red_lines[0].bpm);
+    for (red_line, meter) in red_lines.iter().zip(meters) {
+        builder.bpm_change(red_line.beat, red_line.bpm);
+        builder.time_signature(red_line.beat, Rational64::from_integer(meter.max(1) as i64));
+    }
+
+    // the first beat of the chart should be after the start of the audio
+    let first_red_line = &red_lines[0];
+    let beats_before_audio = if first_red_line.time < 0.0 {
+        (-first_red_line.time / first_red_line.beat_length).ceil()
+    } else {
+        0.0
+    };
+    let delay = (first_red_line.time + beats_before_audio * first_red_line.beat_length).round();
+    let origin = Rational64::from_integer(beats_before_audio as i64);
+
+    let slider_multiplier: f64 = values
+        .get("SliderMultiplier")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(1.4);
+    let mut dropped_drumrolls = 0;
+    for (time, object_type, hit_sound, fields, line_number) in hit_objects {
+        let beat = beat_at(time);
+        let face = if hit_sound & (2 | 8) != 0 {
+            JangguFace::열편
+        } else {
+            JangguFace::궁편
+        };
+        let is_big = hit_sound & 4 != 0;
+
+        if object_type & 1 != 0 {
+            // circle
+            if is_big {
+                let stroke = JangguStroke::from_faces(JangguInputState {
+                    궁채: Some(face),
+                    열채: Some(face),
+                })
+                .expect("Every combination of the faces is a stroke");
+                builder.note(beat, ImportedNote::Stroke(stroke));
+            } else {
+                builder.note(beat, ImportedNote::Tap(face));
+            }
+        } else if object_type & 2 != 0 {
+            // slider (drumroll)
+            if options.drumrolls == DrumrollImport::Drop {
+                dropped_drumrolls += 1;
+                continue;
+            }
+            let slides: f64 = parse_field(&fields, 6, line_number)?;
+            let length: f64 = parse_field(&fields, 7, line_number)?;
+            let duration = length / (slider_multiplier * 100.0 * slider_velocity_at(time))
+                * red_line_at(time).beat_length
+                * slides;
+            builder.note(
+                beat,
+                ImportedNote::LongNote {
+                    kind: GameLongNoteKind::Roll,
+                    face: JangguFace::열편,
+                    end_beat: beat_at(time + duration),
+                    required_hits: None,
+                },
+            );
+        } else if object_type & 8 != 0 {
+            // spinner (denden)
+            builder.note(beat, ImportedNote::Hat);
+        }
+    }
+    if dropped_drumrolls > 0 {
+        warnings.push(format!("{} drumroll(s) are dropped", dropped_drumrolls));
+    }
+
+    let text = |unicode_key: &str, key: &str| {
+        values
+            .get(unicode_key)
+            .filter(|x| !x.is_empty())
+            .or(values.get(key))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let metadata = ChartMetadata {
+        difficulty_name: values.get("Version").cloned(),
+        charter: values.get("Creator").cloned(),
+        preview_start_in_ms: values
+            .get("PreviewTime")
+            .and_then(|x| x.parse::<i64>().ok())
+            .filter(|x| *x >= 0)
+            .map(|x| x as u64),
+        ..Default::default()
+    };
+    let (chart, builder_warnings) = builder.build(
+        text("ArtistUnicode", "Artist"),
+        metadata,
+        delay as u64,
+        origin,
+    );
+    warnings.extend(builder_warnings);
+
+    Ok(ImportedChart {
+        title: text("TitleUnicode", "Title"),
+        chart: chart,
+        audio_filename: values.get("AudioFilename").cloned(),
+        cover_image_filename: background,
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::import_osu;
+    use crate::{import::ImportOptions, janggu::JangguFace};
+
+    fn beatmap(timing_points: &str, hit_objects: &str) -> String {
+        format!(
+            "osu file format v14\n\n[General]\nAudioFilename: audio.mp3\nMode: 1\n\n\
+             [Metadata]\nTitle:Song\nArtist:Someone\n\n\
+             [TimingPoints]\n{}\n\n[HitObjects]\n{}\n",
+            timing_points, hit_objects
+        )
+    }
+
+    #[test]
+    fn imports_taiko_notes() {
+        let source = beatmap(
+            "1000,500,4,1,0,100,1,0\n3000,346.5734,3,1,0,100,1,0",
+            "256,192,1000,1,0,0:0:0:0:\n256,192,1250,1,2,0:0:0:0:\n256,192,1500,1,4,0:0:0:0:",
+        );
+        let imported = import_osu(&source, &ImportOptions::default()).expect("valid beatmap");
+        let chart = &imported.chart;
+
+        assert_eq!(imported.title, "Song");
+        assert_eq!(imported.audio_filename, Some("audio.mp3".to_string()));
+        assert_eq!(chart.artist, "Someone");
+        assert_eq!(chart.delay, 1000);
+        assert_eq!(chart.bpm, Rational64::from_integer(120));
+        assert_eq!(
+            chart.left_face.iter().map(|x| x.beat()).collect::<Vec<_>>(),
+            vec![Rational64::from_integer(0)]
+        );
+        assert_eq!(
+            chart
+                .right_face
+                .iter()
+                .map(|x| x.beat())
+                .collect::<Vec<_>>(),
+            vec![Rational64::new(1, 2)]
+        );
+        // big don is hit by both sticks on 궁편
+        assert_eq!(chart.strokes.len(), 1);
+        assert_eq!(chart.strokes[0].beat(), Rational64::from_integer(1));
+        assert_eq!(chart.strokes[0].stroke.faces().궁채, Some(JangguFace::궁편));
+        assert_eq!(chart.strokes[0].stroke.faces().열채, Some(JangguFace::궁편));
+
+        // 60000 / 346.5734 = 173.1234...
+        assert_eq!(chart.bpm_changes.len(), 1);
+        assert_eq!(chart.bpm_changes[0].beat(), Rational64::from_integer(4));
+        assert_eq!(chart.bpm_changes[0].bpm, Rational64::new(173123, 1000));
+        assert_eq!(chart.time_signatures.len(), 1);
+        assert_eq!(chart.time_signatures[0].beats_per_measure, 3);
+    }
+
+    #[test]
+    fn many_fractional_red_lines_keep_the_tempo_map_in_range() {
+        let timing_points: Vec<String> = (0..500)
+            .map(|i| format!("{},{},4,1,0,100,1,0", i * 1000, 300.0 + i as f64 * 0.0137))
+            .collect();
+        let source = beatmap(&timing_points.join("\n"), "256,192,499000,1,0,0:0:0:0:");
+        let chart = import_osu(&source, &ImportOptions::default())
+            .expect("valid beatmap")
+            .chart;
+
+        assert!(chart.bpm_changes.iter().all(|x| 1000 % x.bpm.denom() == 0));
+        // the positions are quantized, so the timing drifts a little
+        let timing = chart.tempo_map().timing_in_ms(chart.left_face[0].beat());
+        assert!(timing.abs_diff(499000) < 2000, "{}", timing);
+    }
+
+    #[test]
+    fn rejects_beatmap_without_red_line() {
+        let source = beatmap("0,-100,4,1,0,100,0,0", "256,192,0,1,0,0:0:0:0:");
+
+        assert!(import_osu(&source, &ImportOptions::default()).is_err());
+        assert!(import_osu("not a beatmap", &ImportOptions::default()).is_err());
+    }
+}
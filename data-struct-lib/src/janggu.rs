@@ -27,3 +27,75 @@ impl JangguFace {
         JangguFace::궁편
     }
 }
+
+/// Named stroke, which is the combination of the faces hit by the sticks
+///
+/// See `docs/glossary.md` for the strokes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum JangguStroke {
+    덩,
+    덕,
+    쿵,
+    넘겨쿵,
+    넘겨덕,
+    /// 덩(넘겨쿵)
+    덩넘겨쿵,
+    /// 덩(넘겨덕)
+    덩넘겨덕,
+    넘겨덩,
+}
+
+impl JangguStroke {
+    const ALL: [JangguStroke; 8] = [
+        JangguStroke::덩,
+        JangguStroke::덕,
+        JangguStroke::쿵,
+        JangguStroke::넘겨쿵,
+        JangguStroke::넘겨덕,
+        JangguStroke::덩넘겨쿵,
+        JangguStroke::덩넘겨덕,
+        JangguStroke::넘겨덩,
+    ];
+
+    /// faces which are hit by each stick
+    pub fn faces(&self) -> JangguInputState {
+        let (궁채, 열채) = match self {
+            JangguStroke::덩 => (Some(JangguFace::궁편), Some(JangguFace::열편)),
+            JangguStroke::덕 => (None, Some(JangguFace::열편)),
+            JangguStroke::쿵 => (Some(JangguFace::궁편), None),
+            JangguStroke::넘겨쿵 => (Some(JangguFace::열편), None),
+            JangguStroke::넘겨덕 => (None, Some(JangguFace::궁편)),
+            JangguStroke::덩넘겨쿵 => (Some(JangguFace::열편), Some(JangguFace::열편)),
+            JangguStroke::덩넘겨덕 => (Some(JangguFace::궁편), Some(JangguFace::궁편)),
+            JangguStroke::넘겨덩 => (Some(JangguFace::열편), Some(JangguFace::궁편)),
+        };
+
+        JangguInputState {
+            궁채: 궁채,
+            열채: 열채,
+        }
+    }
+
+    /// face hit by the given stick
+    pub fn face_of(&self, stick: JangguStick) -> Option<JangguFace> {
+        match stick {
+            JangguStick::궁채 => self.faces().궁채,
+            JangguStick::열채 => self.faces().열채,
+        }
+    }
+
+    /// Finds the stroke of the given faces
+    ///
+    /// Returns None if no stick hits the face.
+    pub fn from_faces(faces: JangguInputState) -> Option<JangguStroke> {
+        JangguStroke::ALL
+            .into_iter()
+            .find(|x| x.faces().궁채 == faces.궁채 && x.faces().열채 == faces.열채)
+    }
+
+    /// Whether the stroke is hit by both sticks
+    pub fn is_chord(&self) -> bool {
+        let faces = self.faces();
+        faces.궁채.is_some() && faces.열채.is_some()
+    }
+}
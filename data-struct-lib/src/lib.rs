@@ -1,2 +1,5 @@
+pub mod calibration;
+pub mod export;
+pub mod import;
 pub mod janggu;
-pub mod song;
\ No newline at end of file
+pub mod song;
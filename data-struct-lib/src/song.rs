@@ -1,17 +1,35 @@
 mod beat_and_timing;
+mod bpm;
+mod chart_summary;
+mod content_hash;
+mod load_error;
+mod long_note;
+mod notation;
+mod stroke_note;
+mod tempo_map;
+pub mod validation;
 
 use std::{
     fs::{self, File},
-    path::Path,
+    io::{BufReader, ErrorKind},
+    path::{Path, PathBuf},
 };
 
 use num_rational::Rational64;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use crate::janggu::{JangguFace, JangguStick};
+use crate::janggu::{JangguFace, JangguStick, JangguStroke};
 
-use self::beat_and_timing::{beat, get_position, timing_in_ms};
+use self::beat_and_timing::{beat, get_position};
+
+pub(crate) use self::beat_and_timing::split_beat;
+
+pub use self::bpm::{bpm_to_string, parse_bpm};
+pub use self::chart_summary::ChartSummary;
+pub use self::load_error::SongLoadError;
+pub use self::tempo_map::TempoMap;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum GameSongCategory {
@@ -25,15 +43,38 @@ pub enum GameSongCategory {
 pub struct GameSong {
     #[serde(skip)]
     path: String,
+    /// stable identifier of the song, which doesn't change when the directory is renamed
+    ///
+    /// If it's not given in info.json, the id is generated from the title, the artist
+    /// and the charts when the song is loaded. info.json is never changed by loading.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub title: String,
     pub artist: String,
     pub category: GameSongCategory,
     pub audio_filename: String,
     pub video_filename: Option<String>,
     pub cover_image_filename: String,
+    /// levels of the charts, which are the numbers of the chart files (e.g. 1 of 1.json)
+    ///
+    /// If it's not given in info.json, all the chart files in the song directory are used.
+    #[serde(default)]
     pub levels: Vec<u32>,
 }
 
+/// Songs loaded from the music library roots
+#[derive(Debug)]
+pub struct LoadedSongs {
+    pub songs: Vec<GameSong>,
+    /// song directories which failed to load
+    pub failures: Vec<(PathBuf, SongLoadError)>,
+    /// song directories which are skipped because the song with the same id is already loaded
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Max depth of category directories in the music library root
+const MAX_CATEGORY_DEPTH: u32 = 8;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameNote {
     pub stick: JangguStick,
@@ -55,39 +96,171 @@ pub struct GameHatNote {
     pub id: u64,
 }
 
+/// Note which is hit by a named stroke (e.g. 덩, 넘겨덩), judged as one note
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameStrokeNote {
+    pub stroke: JangguStroke,
+    beat_index: u64,
+    tick_nomiator: i64,
+    tick_denomiator: i64,
+    #[serde(skip)]
+    pub id: u64,
+}
+
+/// Kind of the long note
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GameLongNoteKind {
+    /// The stick should be kept on the face until the end of the note
+    Hold,
+    /// The face should be hit repeatedly until the end of the note (굴림채)
+    Roll,
+}
+
+/// Note which has the start and the end
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameLongNote {
+    pub kind: GameLongNoteKind,
+    pub stick: JangguStick,
+    pub face: JangguFace,
+    beat_index: u64,
+    tick_nomiator: i64,
+    tick_denomiator: i64,
+    end_beat_index: u64,
+    end_tick_nomiator: i64,
+    end_tick_denomiator: i64,
+    /// number of hits required to clear the roll note, including the first hit
+    ///
+    /// If it's not given, two hits per beat are required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_hits: Option<u32>,
+    #[serde(skip)]
+    pub id: u64,
+}
+
+/// BPM change event
+///
+/// The bpm is changed from the position of the event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameBpmChange {
+    beat_index: u64,
+    tick_nomiator: i64,
+    tick_denomiator: i64,
+    #[serde(with = "bpm")]
+    pub bpm: Rational64,
+}
+
+/// Time signature(meter) change event
+///
+/// A new measure is started from the position of the event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameTimeSignature {
+    beat_index: u64,
+    tick_nomiator: i64,
+    tick_denomiator: i64,
+    /// numerator of the time signature (e.g. 3 of 3/4)
+    pub beats_per_measure: u32,
+    /// denominator of the time signature (e.g. 4 of 3/4)
+    pub beat_unit: u32,
+}
+
+/// Information of the chart which is displayed to the player
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChartMetadata {
+    /// name of the difficulty (e.g. 쉬움, 보통, 어려움)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty_name: Option<String>,
+    /// numeric level which is displayed, if it's different from the chart file number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+    /// person who made the chart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// timing where the preview of the song starts in millisecond
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_start_in_ms: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameChart {
     pub artist: String,
+    #[serde(default)]
+    pub metadata: ChartMetadata,
     pub delay: u64,
-    pub bpm: u32,
+    /// bpm at the start of the song
+    ///
+    /// Stored as integer or string of decimal/fraction (e.g. `120`, `"127.5"`, `"400/3"`)
+    #[serde(with = "bpm")]
+    pub bpm: Rational64,
     pub left_face: Vec<GameNote>,
     pub right_face: Vec<GameNote>,
     #[serde(default)]
     pub hats: Vec<GameHatNote>,
+    /// notes of the named strokes, which can be hit by both sticks at once
+    #[serde(default)]
+    pub strokes: Vec<GameStrokeNote>,
+    #[serde(default)]
+    pub long_notes: Vec<GameLongNote>,
+    #[serde(default)]
+    pub bpm_changes: Vec<GameBpmChange>,
+    #[serde(default)]
+    pub time_signatures: Vec<GameTimeSignature>,
 }
 
 impl GameChart {
     /// Creates chart
     pub fn to_json_string(
         artist: String,
+        metadata: ChartMetadata,
         delay: u64,
-        bpm: u32,
+        bpm: Rational64,
         left_face: Vec<GameNote>,
         right_face: Vec<GameNote>,
         hats: Vec<GameHatNote>,
+        strokes: Vec<GameStrokeNote>,
+        long_notes: Vec<GameLongNote>,
+        bpm_changes: Vec<GameBpmChange>,
+        time_signatures: Vec<GameTimeSignature>,
     ) -> Result<String, serde_json::Error> {
         let chart = GameChart {
             artist: artist,
+            metadata: metadata,
             delay: delay,
             bpm: bpm,
             left_face: left_face,
             right_face: right_face,
             hats: hats,
+            strokes: strokes,
+            long_notes: long_notes,
+            bpm_changes: bpm_changes,
+            time_signatures: time_signatures,
         };
 
         serde_json::to_string(&chart)
     }
 
+    /// Reads the chart file
+    pub fn from_file(path: &Path) -> Result<GameChart, SongLoadError> {
+        let file = File::open(path).map_err(|err| SongLoadError::io(path.to_path_buf(), err))?;
+        let mut result: GameChart = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| SongLoadError::json(path.to_path_buf(), err))?;
+
+        result.assign_note_ids();
+
+        Ok(result)
+    }
+
+    /// Creates tempo map of the chart
+    pub fn tempo_map(&self) -> TempoMap {
+        TempoMap::new(
+            self.bpm,
+            self.delay,
+            &self.bpm_changes,
+            &self.time_signatures,
+        )
+    }
+
     /// Creates example chart for tutorial
     pub fn create_example_chart_for_tutorial(
         stick: JangguStick,
@@ -107,8 +280,9 @@ impl GameChart {
 
         let chart = GameChart {
             artist: String::from("Team Overchaos"),
+            metadata: ChartMetadata::default(),
             delay: 0,
-            bpm: bpm,
+            bpm: Rational64::from_integer(bpm as i64),
             left_face: if matches!(face, JangguFace::궁편) {
                 notes.clone()
             } else {
@@ -120,6 +294,10 @@ impl GameChart {
                 vec![]
             },
             hats: vec![],
+            strokes: vec![],
+            long_notes: vec![],
+            bpm_changes: vec![],
+            time_signatures: vec![],
         };
 
         return chart;
@@ -151,8 +329,8 @@ impl GameHatNote {
     }
 
     /// calculate the timing of the note
-    pub fn timing_in_ms(&self, track_bpm: u32, track_delay: u64) -> u64 {
-        timing_in_ms(self.beat(), track_bpm, track_delay)
+    pub fn timing_in_ms(&self, tempo_map: &TempoMap) -> u64 {
+        tempo_map.timing_in_ms(self.beat())
     }
 }
 
@@ -184,8 +362,8 @@ impl GameNote {
     }
 
     /// calculate the timing of the note
-    pub fn timing_in_ms(&self, track_bpm: u32, track_delay: u64) -> u64 {
-        timing_in_ms(self.beat(), track_bpm, track_delay)
+    pub fn timing_in_ms(&self, tempo_map: &TempoMap) -> u64 {
+        tempo_map.timing_in_ms(self.beat())
     }
 
     /// Get the position of the note in the display.
@@ -199,13 +377,12 @@ impl GameNote {
     ///    - `2.0` : before the judgement line the width of the two notes
     pub fn get_position(
         &self,
-        track_bpm: u32,
-        track_delay: u64,
-        display_bpm: u32,
+        tempo_map: &TempoMap,
+        display_bpm: Rational64,
         current_time_in_ms: u64,
     ) -> f64 {
         get_position(
-            self.timing_in_ms(track_bpm, track_delay),
+            self.timing_in_ms(tempo_map),
             display_bpm,
             current_time_in_ms,
         )
@@ -214,36 +391,27 @@ impl GameNote {
 
 impl GameSong {
     /// Get the chart of the given level
-    pub fn get_chart(&self, level: u32) -> Result<GameChart, serde_json::Error> {
+    pub fn get_chart(&self, level: u32) -> Result<GameChart, SongLoadError> {
         let level_file_path = Path::join(Path::new(&self.path), format!("{}.json", level));
-        let level_file = File::open(level_file_path).expect("Failed to open level file");
-
-        let mut result: Result<GameChart, serde_json::Error> = serde_json::from_reader(level_file);
-
-        // Assign note indexes
-        if let Ok(result_unwrapped) = &mut result {
-            let mut note_index: u64 = 0;
-            for note in &mut result_unwrapped.left_face {
-                note.face = JangguFace::궁편;
-                note.id = note_index;
-                note_index += 1;
-            }
-            for note in &mut result_unwrapped.right_face {
-                note.face = JangguFace::열편;
-                note.id = note_index;
-                note_index += 1;
-            }
-            for note in &mut result_unwrapped.hats {
-                note.id = note_index;
-                note_index += 1;
+        match GameChart::from_file(&level_file_path) {
+            Err(SongLoadError::Io { source, .. }) if source.kind() == ErrorKind::NotFound => {
+                Err(SongLoadError::UnknownLevel {
+                    path: PathBuf::from(&self.path),
+                    level: level,
+                })
             }
+            result => result,
         }
+    }
 
-        return result;
+    /// Get the summary of the chart of the given level
+    pub fn get_chart_summary(&self, level: u32) -> Result<ChartSummary, SongLoadError> {
+        Ok(ChartSummary::new(&self.get_chart(level)?, level))
     }
 
-    pub fn get_chart_levels(&self) -> Result<Vec<u32>, std::io::Error> {
-        let entries = fs::read_dir(&self.path)?;
+    pub fn get_chart_levels(&self) -> Result<Vec<u32>, SongLoadError> {
+        let entries = fs::read_dir(&self.path)
+            .map_err(|err| SongLoadError::io(PathBuf::from(&self.path), err))?;
         let pattern = Regex::new("^([0-9]+)\\.json$").unwrap();
         let mut result = Vec::<u32>::new();
 
@@ -251,7 +419,7 @@ impl GameSong {
             if let Ok(entry) = i {
                 if let Ok(file_type) = entry.file_type() {
                     let file_name = entry.file_name();
-                    let file_name_str = file_name.to_str().unwrap();
+                    let file_name_str = file_name.to_str().unwrap_or_default();
                     if file_type.is_file() && pattern.is_match(file_name_str) {
                         let level_str = pattern
                             .captures(file_name_str)
@@ -260,7 +428,10 @@ impl GameSong {
                             .unwrap()
                             .as_str();
 
-                        result.push(level_str.to_string().parse::<u32>().unwrap())
+                        // ignore too big levels
+                        if let Ok(level) = level_str.parse::<u32>() {
+                            result.push(level);
+                        }
                     }
                 }
             }
@@ -269,49 +440,159 @@ impl GameSong {
         Ok(result)
     }
 
-    pub fn get_song(path: &Path) -> Option<GameSong> {
+    /// Generates the id of the song which doesn't have the id in info.json
+    ///
+    /// The id is made from the title, the artist and the content hashes of the charts,
+    /// so it doesn't depend on the path, and the songs which only share the title and the artist
+    /// get the different ids. The id changes when the charts are edited,
+    /// so `id` should be given in info.json to keep the records of the song.
+    fn generate_id(&self) -> Result<String, SongLoadError> {
+        let mut hasher = Sha256::new();
+        for field in [&self.title, &self.artist] {
+            hasher.update(field.as_bytes());
+            hasher.update(b"\n");
+        }
+        for level in &self.levels {
+            hasher.update(
+                format!("{} {}\n", level, self.get_chart(*level)?.content_hash()).as_bytes(),
+            );
+        }
+
+        Ok(hasher.finalize()[..8]
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect())
+    }
+
+    /// Get the song in the directory
+    pub fn get_song(path: &Path) -> Result<GameSong, SongLoadError> {
         let info_file_path = Path::join(path, Path::new("info.json"));
-        if info_file_path.exists() {
-            let info_file = File::open(info_file_path).expect("Failed to open info json file");
-            let mut deserialized: GameSong =
-                serde_json::from_reader(info_file).expect("Failed to parse json file");
-
-            // Convert the paths into the absolute path
-            deserialized.audio_filename = Path::join(path, deserialized.audio_filename)
-                .to_str()
-                .unwrap()
-                .to_string();
-            if let Some(video_filename) = deserialized.video_filename {
-                deserialized.video_filename = Some(
-                    Path::join(path, video_filename)
-                        .to_str()
-                        .unwrap()
-                        .to_string(),
-                );
+        let info_text = fs::read_to_string(&info_file_path)
+            .map_err(|err| SongLoadError::io(info_file_path.clone(), err))?;
+        let mut deserialized: GameSong = serde_json::from_str(&info_text)
+            .map_err(|err| SongLoadError::json(info_file_path.clone(), err))?;
+
+        // Convert the paths into the absolute path
+        let existing_asset_path = |filename: &str| -> Result<String, SongLoadError> {
+            let asset_path = Path::join(path, filename);
+            if !asset_path.is_file() {
+                return Err(SongLoadError::MissingAsset { path: asset_path });
             }
-            deserialized.cover_image_filename = Path::join(path, deserialized.cover_image_filename)
-                .to_str()
-                .unwrap()
-                .to_string();
 
-            // Set the song directory path
-            deserialized.path = path.to_str().unwrap().to_string();
+            Ok(asset_path.to_string_lossy().to_string())
+        };
+        deserialized.audio_filename = existing_asset_path(&deserialized.audio_filename)?;
+        if let Some(video_filename) = deserialized.video_filename {
+            deserialized.video_filename = Some(existing_asset_path(&video_filename)?);
+        }
+        deserialized.cover_image_filename =
+            existing_asset_path(&deserialized.cover_image_filename)?;
+
+        // Set the song directory path
+        deserialized.path = path.to_string_lossy().to_string();
+
+        // Check the charts of the levels
+        let mut chart_levels = deserialized.get_chart_levels()?;
+        if deserialized.levels.is_empty() {
+            chart_levels.sort();
+            deserialized.levels = chart_levels.clone();
+        }
+        if deserialized.levels.is_empty() {
+            return Err(SongLoadError::NoCharts {
+                path: path.to_path_buf(),
+            });
+        }
+        if let Some(level) = deserialized
+            .levels
+            .iter()
+            .find(|x| !chart_levels.contains(x))
+        {
+            return Err(SongLoadError::UnknownLevel {
+                path: path.to_path_buf(),
+                level: *level,
+            });
+        }
 
-            Some(deserialized)
-        } else {
-            None
+        if deserialized.id.is_empty() {
+            deserialized.id = deserialized.generate_id()?;
         }
+
+        Ok(deserialized)
     }
 
-    /// Get the songs in the directory
-    pub fn get_songs() -> Vec<GameSong> {
-        let directories = fs::read_dir(Path::new("music")).expect("Failed to read music directory");
-        let mut result = Vec::<GameSong>::new();
+    /// Get the song directories in the directory recursively
+    ///
+    /// Directories which have info.json are song directories,
+    /// and other directories are regarded as category directories.
+    fn find_song_directories(
+        directory: &Path,
+        depth: u32,
+        song_directories: &mut Vec<PathBuf>,
+        failures: &mut Vec<(PathBuf, SongLoadError)>,
+    ) {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(err) => {
+                failures.push((
+                    directory.to_path_buf(),
+                    SongLoadError::io(directory.to_path_buf(), err),
+                ));
+                return;
+            }
+        };
 
-        for i in directories {
-            if let Ok(entry) = i {
-                if let Some(song) = GameSong::get_song(&entry.path()) {
-                    result.push(song);
+        // sort the entries to load the songs in the same order every time
+        let mut paths = vec![];
+        for i in entries {
+            match i {
+                Ok(entry) => paths.push(entry.path()),
+                Err(err) => failures.push((
+                    directory.to_path_buf(),
+                    SongLoadError::io(directory.to_path_buf(), err),
+                )),
+            }
+        }
+        paths.sort();
+
+        for path in paths {
+            if !path.is_dir() {
+                continue;
+            }
+
+            if Path::join(&path, "info.json").exists() {
+                song_directories.push(path);
+            } else if depth < MAX_CATEGORY_DEPTH {
+                GameSong::find_song_directories(&path, depth + 1, song_directories, failures);
+            }
+        }
+    }
+
+    /// Get the songs in the music library roots
+    ///
+    /// Category directories in the roots are scanned recursively.
+    /// If the songs with the same id are in several roots, the song in the earlier root is used.
+    /// The songs which failed to load are returned with the errors.
+    pub fn get_songs(library_roots: &[PathBuf]) -> LoadedSongs {
+        let mut result = LoadedSongs {
+            songs: vec![],
+            failures: vec![],
+            duplicates: vec![],
+        };
+
+        for root in library_roots {
+            let mut song_directories = vec![];
+            GameSong::find_song_directories(root, 0, &mut song_directories, &mut result.failures);
+
+            for path in song_directories {
+                match GameSong::get_song(&path) {
+                    Ok(song) => {
+                        if result.songs.iter().any(|x| x.id == song.id) {
+                            result.duplicates.push(path);
+                        } else {
+                            result.songs.push(song);
+                        }
+                    }
+                    Err(err) => result.failures.push((path, err)),
                 }
             }
         }
@@ -319,3 +600,149 @@ impl GameSong {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, slice};
+
+    use super::{GameSong, SongLoadError};
+
+    /// Creates the empty music library root in the temp directory
+    fn library_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("bidrum-song-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("Failed to create the test directory");
+
+        root
+    }
+
+    /// Writes the song without the id, and returns the content of info.json
+    fn write_song(directory: &PathBuf) -> String {
+        fs::create_dir_all(directory).expect("Failed to create the song directory");
+        let info = r#"{
+  "title": "Song",
+  "artist": "Artist",
+  "category": "Kpop",
+  "audio_filename": "audio.mp3",
+  "video_filename": null,
+  "cover_image_filename": "cover.png"
+}"#;
+        fs::write(directory.join("info.json"), info).expect("Failed to write info.json");
+        fs::write(directory.join("audio.mp3"), "").expect("Failed to write the audio");
+        fs::write(directory.join("cover.png"), "").expect("Failed to write the cover");
+        fs::write(
+            directory.join("1.json"),
+            r#"{"artist": "", "delay": 0, "bpm": 120, "left_face": [], "right_face": []}"#,
+        )
+        .expect("Failed to write the chart");
+
+        info.to_string()
+    }
+
+    #[test]
+    fn generated_id_is_not_saved_into_info_json() {
+        let root = library_root("unsaved-id");
+        let info = write_song(&root.join("song"));
+
+        let song = GameSong::get_song(&root.join("song")).expect("Failed to load the song");
+        assert_eq!(song.id.len(), 16);
+        assert_eq!(
+            fs::read_to_string(root.join("song/info.json")).unwrap(),
+            info
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn id_is_same_in_both_loaders_and_after_rename() {
+        let root = library_root("same-id");
+        write_song(&root.join("kpop/song"));
+        let id = GameSong::get_song(&root.join("kpop/song")).unwrap().id;
+
+        let loaded = GameSong::get_songs(slice::from_ref(&root));
+        assert!(loaded.failures.is_empty());
+        assert_eq!(loaded.songs[0].id, id);
+
+        fs::rename(root.join("kpop"), root.join("k-pop")).unwrap();
+        fs::rename(root.join("k-pop/song"), root.join("k-pop/renamed")).unwrap();
+        assert_eq!(
+            GameSong::get_song(&root.join("k-pop/renamed")).unwrap().id,
+            id
+        );
+        assert_eq!(GameSong::get_songs(slice::from_ref(&root)).songs[0].id, id);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn songs_with_same_title_and_artist_are_both_loaded() {
+        let root = library_root("same-title");
+        write_song(&root.join("original"));
+        write_song(&root.join("remix"));
+        fs::write(
+            root.join("remix/1.json"),
+            r#"{"artist": "", "delay": 0, "bpm": 140, "left_face": [], "right_face": []}"#,
+        )
+        .unwrap();
+
+        let loaded = GameSong::get_songs(slice::from_ref(&root));
+        assert_eq!(loaded.songs.len(), 2);
+        assert!(loaded.duplicates.is_empty());
+        assert_ne!(loaded.songs[0].id, loaded.songs[1].id);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn explicit_id_is_used() {
+        let root = library_root("explicit-id");
+        let info = write_song(&root.join("song"));
+        let info = info.replacen('{', "{\"id\": \"my-song\",", 1);
+        fs::write(root.join("song/info.json"), &info).unwrap();
+
+        assert_eq!(
+            GameSong::get_song(&root.join("song")).unwrap().id,
+            "my-song"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("song/info.json")).unwrap(),
+            info
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn song_without_charts_is_not_loaded() {
+        let root = library_root("no-charts");
+        write_song(&root.join("song"));
+        fs::remove_file(root.join("song/1.json")).unwrap();
+
+        assert!(matches!(
+            GameSong::get_song(&root.join("song")),
+            Err(SongLoadError::NoCharts { .. })
+        ));
+        let loaded = GameSong::get_songs(slice::from_ref(&root));
+        assert!(loaded.songs.is_empty());
+        assert_eq!(loaded.failures.len(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn same_song_in_later_root_is_duplicate() {
+        let first_root = library_root("first-root");
+        let second_root = library_root("second-root");
+        write_song(&first_root.join("song"));
+        write_song(&second_root.join("copied/song"));
+
+        let loaded = GameSong::get_songs(&[first_root.clone(), second_root.clone()]);
+        assert_eq!(loaded.songs.len(), 1);
+        assert_eq!(loaded.duplicates, vec![second_root.join("copied/song")]);
+
+        fs::remove_dir_all(&first_root).unwrap();
+        fs::remove_dir_all(&second_root).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+
+use num_rational::Rational64;
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+/// Parses bpm string into rational number
+///
+/// Following formats are supported
+///   - integer: `120`
+///   - decimal: `127.5`, `93.75` (up to 6 digits after the decimal point)
+///   - fraction: `255/2`
+pub fn parse_bpm(bpm: &str) -> Result<Rational64, String> {
+    let bpm = bpm.trim();
+    if bpm.starts_with('-') {
+        return Err(format!("Bpm should be positive: {}", bpm));
+    }
+
+    let parsed = if let Some((numerator, denominator)) = bpm.split_once('/') {
+        let numerator = numerator
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid numerator of bpm: {}", bpm))?;
+        let denominator = denominator
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid denominator of bpm: {}", bpm))?;
+        if denominator == 0 {
+            return Err(format!("Denominator of bpm is zero: {}", bpm));
+        }
+
+        Rational64::new(numerator, denominator)
+    } else if let Some((integer_part, fractional_part)) = bpm.split_once('.') {
+        if fractional_part.is_empty()
+            || fractional_part.len() > 6
+            || !fractional_part.chars().all(|x| x.is_ascii_digit())
+        {
+            return Err(format!("Invalid decimal bpm: {}", bpm));
+        }
+
+        let integer_part = if integer_part.is_empty() {
+            0
+        } else {
+            integer_part
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid decimal bpm: {}", bpm))?
+        };
+        let denominator = 10_i64.pow(fractional_part.len() as u32);
+        let fractional_part = fractional_part
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid decimal bpm: {}", bpm))?;
+
+        let numerator = integer_part
+            .checked_mul(denominator)
+            .and_then(|x| x.checked_add(fractional_part))
+            .ok_or_else(|| format!("Too large bpm: {}", bpm))?;
+
+        Rational64::new(numerator, denominator)
+    } else {
+        Rational64::from_integer(
+            bpm.parse::<i64>()
+                .map_err(|_| format!("Invalid bpm: {}", bpm))?,
+        )
+    };
+
+    if parsed <= Rational64::from_integer(0) {
+        return Err(format!("Bpm should be positive: {}", bpm));
+    }
+
+    Ok(parsed)
+}
+
+/// Converts bpm into string
+///
+/// The result is the decimal if bpm can be written as the finite decimal,
+/// otherwise the fraction. (e.g. `127.5`, `400/3`)
+pub fn bpm_to_string(bpm: Rational64) -> String {
+    if bpm.is_integer() {
+        return bpm.to_integer().to_string();
+    }
+
+    // finite decimal has only 2 and 5 as the prime factors of the denominator
+    let mut denominator = *bpm.denom();
+    let mut digits = 0;
+    while denominator % 10 == 0 || denominator % 2 == 0 || denominator % 5 == 0 {
+        if denominator % 10 == 0 {
+            denominator /= 10;
+        } else if denominator % 2 == 0 {
+            denominator /= 2;
+        } else {
+            denominator /= 5;
+        }
+        digits += 1;
+    }
+
+    if denominator != 1 || digits > 6 {
+        return format!("{}/{}", bpm.numer(), bpm.denom());
+    }
+
+    let scale = 10_i64.pow(digits);
+    let scaled = (bpm * scale).to_integer();
+    format!(
+        "{}.{:0width$}",
+        scaled / scale,
+        scaled % scale,
+        width = digits as usize
+    )
+}
+
+/// Serializes bpm as integer if possible, otherwise as string
+pub(crate) fn serialize<S>(bpm: &Rational64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if bpm.is_integer() {
+        serializer.serialize_i64(bpm.to_integer())
+    } else {
+        serializer.serialize_str(&bpm_to_string(*bpm))
+    }
+}
+
+struct BpmVisitor;
+
+impl<'de> Visitor<'de> for BpmVisitor {
+    type Value = Rational64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("positive number or string of decimal or fraction")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        parse_bpm(&v.to_string()).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        parse_bpm(&v.to_string()).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        // shortest representation of f64 is used, so that 127.3 is parsed as 1273/10
+        parse_bpm(&format!("{}", v)).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        parse_bpm(v).map_err(E::custom)
+    }
+}
+
+/// Deserializes bpm from integer, floating point number or string
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Rational64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(BpmVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::{bpm_to_string, parse_bpm};
+
+    #[test]
+    fn parses_integer_decimal_and_fraction() {
+        assert_eq!(parse_bpm("120"), Ok(Rational64::from_integer(120)));
+        assert_eq!(parse_bpm(" 127.5 "), Ok(Rational64::new(255, 2)));
+        assert_eq!(parse_bpm(".5"), Ok(Rational64::new(1, 2)));
+        assert_eq!(
+            parse_bpm("93.000001"),
+            Ok(Rational64::new(93_000_001, 1_000_000))
+        );
+        assert_eq!(parse_bpm("400/3"), Ok(Rational64::new(400, 3)));
+    }
+
+    #[test]
+    fn rejects_invalid_bpm() {
+        for bpm in [
+            "",
+            "0",
+            "-120",
+            "0.0",
+            "12.",
+            "1.2345678",
+            "1.2e3",
+            "120/0",
+            "abc",
+            "1/-2",
+        ] {
+            assert!(parse_bpm(bpm).is_err(), "{}", bpm);
+        }
+    }
+
+    #[test]
+    fn rejects_too_long_bpm() {
+        assert!(parse_bpm("999999999999999999.5").is_err());
+        assert!(parse_bpm("9223372036854.775808").is_err());
+        assert!(parse_bpm("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn converts_bpm_into_string() {
+        assert_eq!(bpm_to_string(Rational64::from_integer(120)), "120");
+        assert_eq!(bpm_to_string(Rational64::new(255, 2)), "127.5");
+        assert_eq!(bpm_to_string(Rational64::new(375, 4)), "93.75");
+        assert_eq!(bpm_to_string(Rational64::new(400, 3)), "400/3");
+        assert_eq!(bpm_to_string(Rational64::new(1, 1 << 10)), "1/1024");
+    }
+
+    #[test]
+    fn string_is_parsed_back() {
+        for bpm in [
+            Rational64::new(1273, 10),
+            Rational64::new(400, 3),
+            Rational64::new(180_123, 1000),
+        ] {
+            assert_eq!(parse_bpm(&bpm_to_string(bpm)), Ok(bpm));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+
+use super::{ChartMetadata, GameChart};
+
+/// Length of the window to calculate the note density in millisecond
+const DENSITY_WINDOW_IN_MS: u64 = 1000;
+
+/// Statistics of the chart, which are displayed when selecting the song
+#[derive(Debug, Clone)]
+pub struct ChartSummary {
+    /// number of the chart file (e.g. 1 of 1.json)
+    pub level: u32,
+    pub metadata: ChartMetadata,
+    /// number of the janggu notes, where a stroke note hit by both sticks is counted once
+    pub note_count: usize,
+    /// number of the hat notes
+    pub hat_count: usize,
+    /// number of the hold and roll notes
+    pub long_note_count: usize,
+    /// timing of the end of the last note in millisecond, including the delay
+    pub duration_in_ms: u64,
+    /// max number of the notes (including hat notes and long notes) which start in a second
+    pub peak_density: usize,
+}
+
+impl ChartSummary {
+    /// Calculates the summary of the chart
+    pub fn new(chart: &GameChart, level: u32) -> ChartSummary {
+        let tempo_map = chart.tempo_map();
+
+        let mut timings: Vec<u64> = chart
+            .left_face
+            .iter()
+            .chain(&chart.right_face)
+            .map(|x| x.timing_in_ms(&tempo_map))
+            .chain(chart.hats.iter().map(|x| x.timing_in_ms(&tempo_map)))
+            .chain(chart.long_notes.iter().map(|x| x.timing_in_ms(&tempo_map)))
+            .chain(chart.strokes.iter().map(|x| x.timing_in_ms(&tempo_map)))
+            .collect();
+        timings.sort();
+
+        // count the notes in the sliding window
+        let mut peak_density = 0;
+        let mut window = VecDeque::new();
+        for timing in &timings {
+            window.push_back(*timing);
+            while let Some(first) = window.front() {
+                if *first + DENSITY_WINDOW_IN_MS <= *timing {
+                    window.pop_front();
+                } else {
+                    break;
+                }
+            }
+            peak_density = peak_density.max(window.len());
+        }
+
+        ChartSummary {
+            level: level,
+            metadata: chart.metadata.clone(),
+            note_count: chart.left_face.len() + chart.right_face.len() + chart.strokes.len(),
+            hat_count: chart.hats.len(),
+            long_note_count: chart.long_notes.len(),
+            duration_in_ms: chart
+                .long_notes
+                .iter()
+                .map(|x| x.end_timing_in_ms(&tempo_map))
+                .chain(timings.last().copied())
+                .max()
+                .unwrap_or(0),
+            peak_density: peak_density,
+        }
+    }
+
+    /// level which is displayed to the player
+    pub fn display_level(&self) -> u32 {
+        self.metadata.level.unwrap_or(self.level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::ChartSummary;
+    use crate::{
+        janggu::{JangguFace, JangguStick, JangguStroke},
+        song::{
+            ChartMetadata, GameBpmChange, GameChart, GameHatNote, GameLongNote, GameLongNoteKind,
+            GameNote, GameStrokeNote,
+        },
+    };
+
+    /// Chart at 120 bpm from 200ms, which changes into 60 bpm at 4th beat
+    fn chart() -> GameChart {
+        GameChart {
+            artist: String::from("Team Overchaos"),
+            metadata: ChartMetadata::default(),
+            delay: 200,
+            bpm: Rational64::from_integer(120),
+            // 700, 950, 1200 and 3700ms
+            left_face: vec![
+                GameNote::create_raw_note(JangguStick::궁채, 1, 0, 1),
+                GameNote::create_raw_note(JangguStick::궁채, 1, 1, 2),
+                GameNote::create_raw_note(JangguStick::궁채, 2, 0, 1),
+                GameNote::create_raw_note(JangguStick::궁채, 5, 1, 2),
+            ],
+            // 1450ms
+            right_face: vec![GameNote::create_raw_note(JangguStick::열채, 2, 1, 2)],
+            // 1950ms
+            hats: vec![GameHatNote::create_raw_note(3, 1, 2)],
+            // 1700ms
+            strokes: vec![GameStrokeNote::create_raw_stroke_note(
+                JangguStroke::덩,
+                3,
+                0,
+                1,
+            )],
+            // 3200 to 5200ms
+            long_notes: vec![GameLongNote::create_raw_long_note(
+                GameLongNoteKind::Hold,
+                JangguStick::열채,
+                JangguFace::열편,
+                5,
+                0,
+                1,
+                7,
+                0,
+                1,
+            )],
+            bpm_changes: vec![GameBpmChange::create_raw_bpm_change(
+                4,
+                0,
+                1,
+                Rational64::from_integer(60),
+            )],
+            time_signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn counts_notes() {
+        let summary = ChartSummary::new(&chart(), 2);
+
+        // the stroke note hit by both sticks is counted once
+        assert_eq!(summary.note_count, 6);
+        assert_eq!(summary.hat_count, 1);
+        assert_eq!(summary.long_note_count, 1);
+    }
+
+    #[test]
+    fn duration_ends_with_long_note() {
+        assert_eq!(ChartSummary::new(&chart(), 2).duration_in_ms, 5200);
+
+        let mut chart = chart();
+        chart.long_notes.clear();
+        assert_eq!(ChartSummary::new(&chart, 2).duration_in_ms, 3700);
+    }
+
+    #[test]
+    fn peak_density_counts_notes_in_a_second() {
+        // the notes exactly a second before are out of the window
+        assert_eq!(ChartSummary::new(&chart(), 2).peak_density, 4);
+
+        // 1200, 1250, 1450, 1700 and 1950ms
+        let mut chart = chart();
+        chart
+            .left_face
+            .push(GameNote::create_raw_note(JangguStick::궁채, 2, 1, 10));
+        assert_eq!(ChartSummary::new(&chart, 2).peak_density, 5);
+    }
+
+    #[test]
+    fn empty_chart() {
+        let mut chart = chart();
+        chart.left_face.clear();
+        chart.right_face.clear();
+        chart.hats.clear();
+        chart.strokes.clear();
+        chart.long_notes.clear();
+        let summary = ChartSummary::new(&chart, 2);
+
+        assert_eq!(summary.note_count, 0);
+        assert_eq!(summary.duration_in_ms, 0);
+        assert_eq!(summary.peak_density, 0);
+    }
+
+    #[test]
+    fn display_level_of_metadata() {
+        let mut chart = chart();
+        assert_eq!(ChartSummary::new(&chart, 2).display_level(), 2);
+
+        chart.metadata.level = Some(7);
+        let summary = ChartSummary::new(&chart, 2);
+        assert_eq!(summary.level, 2);
+        assert_eq!(summary.display_level(), 7);
+    }
+}
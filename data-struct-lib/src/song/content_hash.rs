@@ -0,0 +1,213 @@
+use num_rational::Rational64;
+use sha2::{Digest, Sha256};
+
+use crate::janggu::{JangguFace, JangguStick, JangguStroke};
+
+use super::{GameChart, GameLongNoteKind, GameNote};
+
+/// Writes the normalized position, which is the same for `1 + 2/4` and `1 + 1/2`
+fn position_string(beat: Rational64) -> String {
+    format!("{}/{}", beat.numer(), beat.denom())
+}
+
+fn stick_string(stick: JangguStick) -> &'static str {
+    match stick {
+        JangguStick::궁채 => "gung",
+        JangguStick::열채 => "yeol",
+    }
+}
+
+fn face_string(face: JangguFace) -> &'static str {
+    match face {
+        JangguFace::궁편 => "left",
+        JangguFace::열편 => "right",
+    }
+}
+
+/// Writes the faces hit by 궁채 and 열채, `-` if the stick doesn't hit
+fn stroke_string(stroke: JangguStroke) -> String {
+    let faces = stroke.faces();
+
+    format!(
+        "{} {}",
+        faces.궁채.map(face_string).unwrap_or("-"),
+        faces.열채.map(face_string).unwrap_or("-")
+    )
+}
+
+fn sorted_notes(notes: &[GameNote]) -> Vec<String> {
+    let mut result: Vec<(Rational64, &'static str)> = notes
+        .iter()
+        .map(|x| (x.beat(), stick_string(x.stick)))
+        .collect();
+    result.sort();
+
+    result
+        .iter()
+        .map(|(beat, stick)| format!("{} {}", position_string(*beat), stick))
+        .collect()
+}
+
+impl GameChart {
+    /// Calculates the hash of the chart contents in hexadecimal SHA-256
+    ///
+    /// The chart is normalized before hashing, so that the hash doesn't change
+    /// by the order of the notes, the json formatting, the artist or reduced ticks.
+    /// The notes, hats, stroke notes, long notes, bpm, bpm changes, time signatures and delay are hashed.
+    pub fn content_hash(&self) -> String {
+        let mut lines = vec![
+            "bidrum-chart 1".to_string(),
+            format!("delay {}", self.delay),
+            format!("bpm {}/{}", self.bpm.numer(), self.bpm.denom()),
+        ];
+
+        let mut bpm_changes: Vec<(Rational64, Rational64)> =
+            self.bpm_changes.iter().map(|x| (x.beat(), x.bpm)).collect();
+        // latter one of the bpm changes at the same position is used, so the order is kept
+        bpm_changes.sort_by_key(|x| x.0);
+        for (beat, bpm) in bpm_changes {
+            lines.push(format!(
+                "bpm_change {} {}/{}",
+                position_string(beat),
+                bpm.numer(),
+                bpm.denom()
+            ));
+        }
+
+        let mut time_signatures: Vec<(Rational64, u32, u32)> = self
+            .time_signatures
+            .iter()
+            .map(|x| (x.beat(), x.beats_per_measure, x.beat_unit))
+            .collect();
+        time_signatures.sort_by_key(|x| x.0);
+        for (beat, beats_per_measure, beat_unit) in time_signatures {
+            lines.push(format!(
+                "time_signature {} {}/{}",
+                position_string(beat),
+                beats_per_measure,
+                beat_unit
+            ));
+        }
+
+        for note in sorted_notes(&self.left_face) {
+            lines.push(format!("left {}", note));
+        }
+        for note in sorted_notes(&self.right_face) {
+            lines.push(format!("right {}", note));
+        }
+
+        let mut hats: Vec<Rational64> = self.hats.iter().map(|x| x.beat()).collect();
+        hats.sort();
+        for beat in hats {
+            lines.push(format!("hat {}", position_string(beat)));
+        }
+
+        let mut long_notes: Vec<String> = self
+            .long_notes
+            .iter()
+            .map(|x| {
+                format!(
+                    "long {} {} {} {} {} {}",
+                    position_string(x.beat()),
+                    position_string(x.end_beat()),
+                    match x.kind {
+                        GameLongNoteKind::Hold => "hold",
+                        GameLongNoteKind::Roll => "roll",
+                    },
+                    face_string(x.face),
+                    stick_string(x.stick),
+                    x.required_hit_count()
+                )
+            })
+            .collect();
+        long_notes.sort();
+        lines.extend(long_notes);
+
+        let mut strokes: Vec<(Rational64, String)> = self
+            .strokes
+            .iter()
+            .map(|x| (x.beat(), stroke_string(x.stroke)))
+            .collect();
+        strokes.sort();
+        for (beat, stroke) in strokes {
+            lines.push(format!("stroke {} {}", position_string(beat), stroke));
+        }
+
+        let mut hasher = Sha256::new();
+        for line in lines {
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::song::GameChart;
+
+    fn chart(notes: &str) -> GameChart {
+        serde_json::from_str(&format!(
+            r#"{{"artist": "", "delay": 0, "bpm": 120, "right_face": [], {}}}"#,
+            notes
+        ))
+        .expect("invalid test chart")
+    }
+
+    #[test]
+    fn hash_is_normalized() {
+        let chart_a = chart(
+            r#""left_face": [
+                {"stick": "궁채", "beat_index": 1, "tick_nomiator": 2, "tick_denomiator": 4},
+                {"stick": "열채", "beat_index": 0, "tick_nomiator": 0, "tick_denomiator": 0}]"#,
+        );
+        let mut chart_b = chart(
+            r#""left_face": [
+                {"stick": "열채", "beat_index": 0, "tick_nomiator": 0, "tick_denomiator": 1},
+                {"stick": "궁채", "beat_index": 1, "tick_nomiator": 1, "tick_denomiator": 2}]"#,
+        );
+        chart_b.artist = "Other".to_string();
+
+        assert_eq!(chart_a.content_hash(), chart_b.content_hash());
+        assert_eq!(chart_a.content_hash().len(), 64);
+    }
+
+    #[test]
+    fn hash_depends_on_notes() {
+        let stroke = |stroke: &str| {
+            chart(&format!(
+                r#""left_face": [], "strokes": [{{"stroke": "{}", "beat_index": 1, "tick_nomiator": 0, "tick_denomiator": 0}}]"#,
+                stroke
+            ))
+            .content_hash()
+        };
+
+        assert_ne!(stroke("덩"), stroke("넘겨덩"));
+        assert_ne!(stroke("덩"), chart(r#""left_face": []"#).content_hash());
+        let mut delayed = chart(r#""left_face": []"#);
+        delayed.delay = 10;
+        assert_ne!(
+            delayed.content_hash(),
+            chart(r#""left_face": []"#).content_hash()
+        );
+    }
+
+    #[test]
+    fn hash_of_stroke_is_stable() {
+        // the hash is persisted with the results, so it shouldn't change between versions
+        // sha256 of "bidrum-chart 1\ndelay 0\nbpm 120/1\nstroke 1/1 left right\n"
+        let chart = chart(
+            r#""left_face": [], "strokes": [{"stroke": "덩", "beat_index": 1, "tick_nomiator": 0, "tick_denomiator": 0}]"#,
+        );
+
+        assert_eq!(
+            chart.content_hash(),
+            "ba945c8d074f0bbc4d31ebdc785879751b1a9e25edc547849880a9c7eaa7d745"
+        );
+    }
+}
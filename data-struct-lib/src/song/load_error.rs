@@ -0,0 +1,91 @@
+use std::{error::Error, fmt, path::PathBuf};
+
+/// Error while loading songs and charts
+#[derive(Debug)]
+pub enum SongLoadError {
+    /// Failed to read the file or the directory
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Failed to parse the json file
+    Json {
+        path: PathBuf,
+        /// line where the error occurred (starting from 1)
+        line: usize,
+        /// column where the error occurred (starting from 1)
+        column: usize,
+        source: serde_json::Error,
+    },
+    /// The file which is specified in info.json does not exist
+    MissingAsset { path: PathBuf },
+    /// There's no chart of the level
+    UnknownLevel { path: PathBuf, level: u32 },
+    /// The song directory has no chart to play
+    NoCharts { path: PathBuf },
+}
+
+impl SongLoadError {
+    pub(super) fn io(path: PathBuf, source: std::io::Error) -> SongLoadError {
+        SongLoadError::Io {
+            path: path,
+            source: source,
+        }
+    }
+
+    pub(super) fn json(path: PathBuf, source: serde_json::Error) -> SongLoadError {
+        SongLoadError::Json {
+            path: path,
+            line: source.line(),
+            column: source.column(),
+            source: source,
+        }
+    }
+}
+
+impl fmt::Display for SongLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SongLoadError::Io { path, source } => {
+                write!(f, "Failed to read {}: {}", path.display(), source)
+            }
+            SongLoadError::Json {
+                path,
+                line,
+                column,
+                source,
+            } => write!(
+                f,
+                "Failed to parse {} at line {} column {}: {}",
+                path.display(),
+                line,
+                column,
+                source
+            ),
+            SongLoadError::MissingAsset { path } => {
+                write!(f, "{} does not exist", path.display())
+            }
+            SongLoadError::UnknownLevel { path, level } => {
+                write!(
+                    f,
+                    "There's no chart of level {} in {}",
+                    level,
+                    path.display()
+                )
+            }
+            SongLoadError::NoCharts { path } => {
+                write!(f, "There's no chart in {}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for SongLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SongLoadError::Io { source, .. } => Some(source),
+            SongLoadError::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
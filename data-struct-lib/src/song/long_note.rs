@@ -0,0 +1,99 @@
+use num_rational::Rational64;
+
+use crate::janggu::{JangguFace, JangguStick};
+
+use super::{
+    beat_and_timing::{beat, get_position},
+    GameLongNote, GameLongNoteKind, TempoMap,
+};
+
+impl GameLongNote {
+    pub fn create_raw_long_note(
+        kind: GameLongNoteKind,
+        stick: JangguStick,
+        face: JangguFace,
+        beat_index: u64,
+        tick_nomiator: i64,
+        tick_denomiator: i64,
+        end_beat_index: u64,
+        end_tick_nomiator: i64,
+        end_tick_denomiator: i64,
+    ) -> GameLongNote {
+        GameLongNote {
+            kind: kind,
+            stick: stick,
+            face: face,
+            beat_index: beat_index,
+            tick_nomiator: tick_nomiator,
+            tick_denomiator: tick_denomiator,
+            end_beat_index: end_beat_index,
+            end_tick_nomiator: end_tick_nomiator,
+            end_tick_denomiator: end_tick_denomiator,
+            required_hits: None,
+            // id is useless
+            id: 0,
+        }
+    }
+
+    /// get the start position of the note in unit of beat
+    pub fn beat(&self) -> Rational64 {
+        beat(
+            self.beat_index as i64,
+            self.tick_nomiator,
+            self.tick_denomiator,
+        )
+    }
+
+    /// get the end position of the note in unit of beat
+    pub fn end_beat(&self) -> Rational64 {
+        beat(
+            self.end_beat_index as i64,
+            self.end_tick_nomiator,
+            self.end_tick_denomiator,
+        )
+    }
+
+    /// calculate the start timing of the note
+    pub fn timing_in_ms(&self, tempo_map: &TempoMap) -> u64 {
+        tempo_map.timing_in_ms(self.beat())
+    }
+
+    /// calculate the end timing of the note
+    pub fn end_timing_in_ms(&self, tempo_map: &TempoMap) -> u64 {
+        tempo_map.timing_in_ms(self.end_beat())
+    }
+
+    /// number of hits required to clear the roll note, including the first hit
+    pub fn required_hit_count(&self) -> u32 {
+        if let Some(required_hits) = self.required_hits {
+            return required_hits.max(1);
+        }
+
+        // two hits per beat
+        let length = (self.end_beat() - self.beat()) * 2;
+        (length.ceil().to_integer().max(1)) as u32
+    }
+
+    /// Get the positions of the start and the end of the note in the display.
+    ///
+    /// See [`super::GameNote::get_position`] for the unit of the positions.
+    pub fn get_positions(
+        &self,
+        tempo_map: &TempoMap,
+        display_bpm: Rational64,
+        current_time_in_ms: u64,
+    ) -> (f64, f64) {
+        (
+            get_position(
+                self.timing_in_ms(tempo_map),
+                display_bpm,
+                current_time_in_ms,
+            ),
+            get_position(
+                self.end_timing_in_ms(tempo_map),
+                display_bpm,
+                current_time_in_ms,
+            ),
+        )
+    }
+}
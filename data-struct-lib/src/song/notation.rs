@@ -0,0 +1,901 @@
+use std::collections::BTreeMap;
+
+use num_rational::Rational64;
+
+use crate::janggu::{JangguFace, JangguInputState, JangguStick, JangguStroke};
+
+use super::{
+    beat_and_timing::split_beat, bpm_to_string, parse_bpm, ChartMetadata, GameBpmChange, GameChart,
+    GameHatNote, GameLongNote, GameLongNoteKind, GameNote, GameStrokeNote, GameTimeSignature,
+};
+
+/// first line of the text notation
+const NOTATION_HEADER: &str = "bidrum-notation 1";
+
+/// Measure of the chart, which is one line of the text notation
+struct Measure {
+    start: Rational64,
+    length: Rational64,
+    /// whether the measure is cut by the time signature change
+    truncated: bool,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: i64, b: i64) -> i64 {
+    a / gcd(a, b) * b
+}
+
+fn rational_string(value: Rational64) -> String {
+    if value.is_integer() {
+        value.to_integer().to_string()
+    } else {
+        format!("{}/{}", value.numer(), value.denom())
+    }
+}
+
+fn parse_rational(value: &str) -> Option<Rational64> {
+    match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator = numerator.parse::<i64>().ok()?;
+            let denominator = denominator.parse::<i64>().ok()?;
+            if denominator == 0 {
+                return None;
+            }
+            Some(Rational64::new(numerator, denominator))
+        }
+        None => value.parse::<i64>().ok().map(Rational64::from_integer),
+    }
+}
+
+fn stick_name(stick: JangguStick) -> &'static str {
+    match stick {
+        JangguStick::궁채 => "궁채",
+        JangguStick::열채 => "열채",
+    }
+}
+
+/// character of the face in the stick lanes (uppercase for the notes, lowercase for the strokes)
+fn face_char(face: JangguFace, is_stroke: bool) -> char {
+    match (face, is_stroke) {
+        (JangguFace::궁편, false) => 'L',
+        (JangguFace::열편, false) => 'R',
+        (JangguFace::궁편, true) => 'l',
+        (JangguFace::열편, true) => 'r',
+    }
+}
+
+/// character of the body of the long note, which follows the start of the long note
+fn body_char(kind: GameLongNoteKind) -> char {
+    match kind {
+        GameLongNoteKind::Hold => '=',
+        GameLongNoteKind::Roll => '~',
+    }
+}
+
+/// Writes the text as it is, or quoted in json if it can't be written as it is
+fn text_value(value: &str) -> String {
+    if value.is_empty()
+        || value.trim() != value
+        || value.starts_with('"')
+        || value.chars().any(|x| x.is_control())
+    {
+        serde_json::to_string(value).expect("String is always serializable")
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_text_value(value: &str) -> Result<String, String> {
+    if value.starts_with('"') {
+        serde_json::from_str(value).map_err(|err| format!("Invalid quoted text: {}", err))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// State of the stick lane while parsing the text notation
+enum LaneState {
+    Idle,
+    /// note which can be the start of the long note
+    Note {
+        face: JangguFace,
+        beat: Rational64,
+        is_stroke: bool,
+    },
+    LongNote {
+        kind: GameLongNoteKind,
+        face: JangguFace,
+        start: Rational64,
+        end: Rational64,
+    },
+}
+
+/// Notes read from the text notation
+#[derive(Default)]
+struct ParsedNotes {
+    left_face: Vec<GameNote>,
+    right_face: Vec<GameNote>,
+    /// faces hit by 궁채 and 열채 in the strokes
+    strokes: BTreeMap<Rational64, (Option<JangguFace>, Option<JangguFace>)>,
+    long_notes: Vec<GameLongNote>,
+}
+
+impl ParsedNotes {
+    /// Finishes the note of the lane when the lane is not continued
+    fn finish(&mut self, stick: JangguStick, state: &LaneState) {
+        match *state {
+            LaneState::Idle => {}
+            LaneState::Note {
+                face,
+                beat,
+                is_stroke: true,
+            } => {
+                let faces = self.strokes.entry(beat).or_insert((None, None));
+                match stick {
+                    JangguStick::궁채 => faces.0 = Some(face),
+                    JangguStick::열채 => faces.1 = Some(face),
+                }
+            }
+            LaneState::Note {
+                face,
+                beat,
+                is_stroke: false,
+            } => {
+                let (beat_index, tick_nomiator, tick_denomiator) = split_beat(beat);
+                let note =
+                    GameNote::create_raw_note(stick, beat_index, tick_nomiator, tick_denomiator);
+                match face {
+                    JangguFace::궁편 => self.left_face.push(note),
+                    JangguFace::열편 => self.right_face.push(note),
+                }
+            }
+            LaneState::LongNote {
+                kind,
+                face,
+                start,
+                end,
+            } => {
+                let (beat_index, tick_nomiator, tick_denomiator) = split_beat(start);
+                let (end_beat_index, end_tick_nomiator, end_tick_denomiator) = split_beat(end);
+                self.long_notes.push(GameLongNote::create_raw_long_note(
+                    kind,
+                    stick,
+                    face,
+                    beat_index,
+                    tick_nomiator,
+                    tick_denomiator,
+                    end_beat_index,
+                    end_tick_nomiator,
+                    end_tick_denomiator,
+                ));
+            }
+        }
+    }
+}
+
+impl GameChart {
+    /// Splits the chart into the measures until the given position
+    fn measures(&self, last_beat: Rational64) -> Vec<Measure> {
+        let tempo_map = self.tempo_map();
+        let mut time_signature_beats: Vec<Rational64> =
+            self.time_signatures.iter().map(|x| x.beat()).collect();
+        time_signature_beats.sort();
+
+        let mut measures = vec![];
+        let mut start = Rational64::from_integer(0);
+        loop {
+            let mut end = start + tempo_map.measure_length_at(start);
+            let mut truncated = false;
+            if let Some(next) = time_signature_beats
+                .iter()
+                .find(|x| **x > start && **x < end)
+            {
+                end = *next;
+                truncated = true;
+            }
+            measures.push(Measure {
+                start: start,
+                length: end - start,
+                truncated: truncated,
+            });
+
+            if end > last_beat {
+                return measures;
+            }
+            start = end;
+        }
+    }
+
+    /// Writes the chart in the text notation, which can be read by `from_notation`
+    ///
+    /// See `docs/text-notation.md` for the notation.
+    /// Fails if the notes of the same stick are overlapped, which can't be written.
+    pub fn to_notation(&self) -> Result<String, String> {
+        let mut lines = vec![
+            NOTATION_HEADER.to_string(),
+            format!("artist: {}", text_value(&self.artist)),
+        ];
+        let metadata = &self.metadata;
+        if let Some(difficulty_name) = &metadata.difficulty_name {
+            lines.push(format!("difficulty_name: {}", text_value(difficulty_name)));
+        }
+        if let Some(level) = metadata.level {
+            lines.push(format!("level: {}", level));
+        }
+        if let Some(charter) = &metadata.charter {
+            lines.push(format!("charter: {}", text_value(charter)));
+        }
+        if let Some(description) = &metadata.description {
+            lines.push(format!("description: {}", text_value(description)));
+        }
+        if let Some(preview_start_in_ms) = metadata.preview_start_in_ms {
+            lines.push(format!("preview_start_in_ms: {}", preview_start_in_ms));
+        }
+        lines.push(format!("delay: {}", self.delay));
+        lines.push(format!("bpm: {}", bpm_to_string(self.bpm)));
+        lines.push(String::new());
+        lines.push("# 궁채 열채 hat".to_string());
+
+        let last_beat = self
+            .left_face
+            .iter()
+            .chain(&self.right_face)
+            .map(|x| x.beat())
+            .chain(self.hats.iter().map(|x| x.beat()))
+            .chain(self.strokes.iter().map(|x| x.beat()))
+            .chain(self.long_notes.iter().map(|x| x.end_beat()))
+            .chain(self.bpm_changes.iter().map(|x| x.beat()))
+            .chain(self.time_signatures.iter().map(|x| x.beat()))
+            .max()
+            .unwrap_or(Rational64::from_integer(0));
+
+        for measure in self.measures(last_beat) {
+            let end = measure.start + measure.length;
+            let in_measure = |beat: Rational64| measure.start <= beat && beat < end;
+
+            // directives of the measure
+            if measure.truncated {
+                lines.push(format!("@length {}", rational_string(measure.length)));
+            }
+            for time_signature in self
+                .time_signatures
+                .iter()
+                .filter(|x| x.beat() == measure.start)
+            {
+                lines.push(format!(
+                    "@meter {}/{}",
+                    time_signature.beats_per_measure, time_signature.beat_unit
+                ));
+            }
+            for bpm_change in self.bpm_changes.iter().filter(|x| in_measure(x.beat())) {
+                lines.push(format!(
+                    "@bpm {} {}",
+                    rational_string(bpm_change.beat() - measure.start),
+                    bpm_to_string(bpm_change.bpm)
+                ));
+            }
+            for long_note in self.long_notes.iter().filter(|x| in_measure(x.beat())) {
+                if let Some(required_hits) = long_note.required_hits {
+                    lines.push(format!(
+                        "@hits {} {} {}",
+                        stick_name(long_note.stick),
+                        rational_string(long_note.beat() - measure.start),
+                        required_hits
+                    ));
+                }
+            }
+
+            // the number of the splits is the smallest one which can place all the notes
+            let note_beats = self
+                .left_face
+                .iter()
+                .chain(&self.right_face)
+                .map(|x| x.beat())
+                .chain(self.hats.iter().map(|x| x.beat()))
+                .chain(self.strokes.iter().map(|x| x.beat()))
+                .chain(self.long_notes.iter().map(|x| x.beat()))
+                .chain(self.long_notes.iter().map(|x| x.end_beat()));
+            let splits = note_beats
+                .filter(|x| in_measure(*x))
+                .map(|x| *((x - measure.start) / measure.length).denom())
+                .fold(*measure.length.numer(), lcm);
+            let split_length = measure.length / splits;
+
+            let mut 궁채 = vec!['.'; splits as usize];
+            let mut 열채 = vec!['.'; splits as usize];
+            let mut hats = vec!['.'; splits as usize];
+            let mut place = |stick: Option<JangguStick>, beat: Rational64, character: char| {
+                let lane = match stick {
+                    Some(JangguStick::궁채) => &mut 궁채,
+                    Some(JangguStick::열채) => &mut 열채,
+                    None => &mut hats,
+                };
+                let index = ((beat - measure.start) / split_length).to_integer() as usize;
+                if lane[index] != '.' {
+                    return Err(format!(
+                        "Notes of {} are overlapped at beat {}",
+                        stick.map(stick_name).unwrap_or("hat"),
+                        rational_string(beat)
+                    ));
+                }
+                lane[index] = character;
+
+                Ok(())
+            };
+
+            for note in self.left_face.iter().filter(|x| in_measure(x.beat())) {
+                place(Some(note.stick), note.beat(), 'L')?;
+            }
+            for note in self.right_face.iter().filter(|x| in_measure(x.beat())) {
+                place(Some(note.stick), note.beat(), 'R')?;
+            }
+            for note in self.hats.iter().filter(|x| in_measure(x.beat())) {
+                place(None, note.beat(), 'H')?;
+            }
+            for stroke in self.strokes.iter().filter(|x| in_measure(x.beat())) {
+                for stick in [JangguStick::궁채, JangguStick::열채] {
+                    if let Some(face) = stroke.stroke.face_of(stick) {
+                        place(Some(stick), stroke.beat(), face_char(face, true))?;
+                    }
+                }
+            }
+            for long_note in &self.long_notes {
+                if in_measure(long_note.beat()) {
+                    place(
+                        Some(long_note.stick),
+                        long_note.beat(),
+                        face_char(long_note.face, false),
+                    )?;
+                }
+                // the body is written after the start until the end
+                for index in 0..splits {
+                    let beat = measure.start + split_length * index;
+                    if long_note.beat() < beat && beat <= long_note.end_beat() {
+                        place(Some(long_note.stick), beat, body_char(long_note.kind))?;
+                    }
+                }
+            }
+
+            lines.push(format!(
+                "{} {} {}",
+                궁채.iter().collect::<String>(),
+                열채.iter().collect::<String>(),
+                hats.iter().collect::<String>()
+            ));
+        }
+
+        let mut notation = lines.join("\n");
+        notation.push('\n');
+        Ok(notation)
+    }
+
+    /// Reads the chart from the text notation written by `to_notation`
+    pub fn from_notation(source: &str) -> Result<GameChart, String> {
+        let mut lines = source.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == NOTATION_HEADER => {}
+            _ => return Err(format!("The first line should be `{}`", NOTATION_HEADER)),
+        }
+
+        let mut artist = String::new();
+        let mut metadata = ChartMetadata::default();
+        let mut delay = None;
+        let mut bpm = None;
+
+        let mut measure_start = Rational64::from_integer(0);
+        let mut measure_length = Rational64::from_integer(4);
+        let mut next_measure_length = None;
+        let mut is_header = true;
+        let mut bpm_changes = vec![];
+        let mut time_signatures = vec![];
+        let mut hats = vec![];
+        // (stick, position, required hits)
+        let mut required_hits = vec![];
+        let mut notes = ParsedNotes::default();
+        let mut lane_states = [LaneState::Idle, LaneState::Idle];
+
+        for (index, line) in lines {
+            let line_number = index + 1;
+            let error = |message: String| format!("Line {}: {}", line_number, message);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if is_header && !line.starts_with('@') {
+                if let Some((key, value)) = line.split_once(':') {
+                    let value = value.trim();
+                    let number = || {
+                        value
+                            .parse::<u64>()
+                            .map_err(|_| error(format!("Invalid number: {}", value)))
+                    };
+                    match key.trim() {
+                        "artist" => artist = parse_text_value(value).map_err(error)?,
+                        "difficulty_name" => {
+                            metadata.difficulty_name = Some(parse_text_value(value).map_err(error)?)
+                        }
+                        "level" => metadata.level = Some(number()? as u32),
+                        "charter" => {
+                            metadata.charter = Some(parse_text_value(value).map_err(error)?)
+                        }
+                        "description" => {
+                            metadata.description = Some(parse_text_value(value).map_err(error)?)
+                        }
+                        "preview_start_in_ms" => metadata.preview_start_in_ms = Some(number()?),
+                        "delay" => delay = Some(number()?),
+                        "bpm" => bpm = Some(parse_bpm(value).map_err(error)?),
+                        key => return Err(error(format!("Unknown header: {}", key))),
+                    }
+                    continue;
+                }
+            }
+            is_header = false;
+
+            if let Some(directive) = line.strip_prefix('@') {
+                let arguments: Vec<&str> = directive.split_whitespace().collect();
+                match arguments.as_slice() {
+                    ["length", length] => {
+                        next_measure_length = Some(
+                            parse_rational(length)
+                                .filter(|x| *x > Rational64::from_integer(0))
+                                .ok_or_else(|| {
+                                    error(format!("Invalid length of the measure: {}", length))
+                                })?,
+                        )
+                    }
+                    ["meter", meter] => {
+                        let (beats_per_measure, beat_unit) = meter
+                            .split_once('/')
+                            .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+                            .ok_or_else(|| error(format!("Invalid time signature: {}", meter)))?;
+                        let (beat_index, tick_nomiator, tick_denomiator) =
+                            split_beat(measure_start);
+                        let time_signature = GameTimeSignature::create_raw_time_signature(
+                            beat_index,
+                            tick_nomiator,
+                            tick_denomiator,
+                            beats_per_measure,
+                            beat_unit,
+                        );
+                        measure_length = time_signature.measure_length();
+                        time_signatures.push(time_signature);
+                    }
+                    ["bpm", offset, value] => {
+                        let offset = parse_rational(offset)
+                            .ok_or_else(|| error(format!("Invalid position: {}", offset)))?;
+                        let (beat_index, tick_nomiator, tick_denomiator) =
+                            split_beat(measure_start + offset);
+                        bpm_changes.push(GameBpmChange::create_raw_bpm_change(
+                            beat_index,
+                            tick_nomiator,
+                            tick_denomiator,
+                            parse_bpm(value).map_err(error)?,
+                        ));
+                    }
+                    ["hits", stick, offset, hits] => {
+                        let stick = match *stick {
+                            "궁채" => JangguStick::궁채,
+                            "열채" => JangguStick::열채,
+                            _ => return Err(error(format!("Unknown stick: {}", stick))),
+                        };
+                        let offset = parse_rational(offset)
+                            .ok_or_else(|| error(format!("Invalid position: {}", offset)))?;
+                        let hits = hits
+                            .parse::<u32>()
+                            .map_err(|_| error(format!("Invalid number: {}", hits)))?;
+                        required_hits.push((stick, measure_start + offset, hits, line_number));
+                    }
+                    _ => return Err(error(format!("Unknown directive: {}", line))),
+                }
+                continue;
+            }
+
+            // measure
+            let lanes: Vec<Vec<char>> = line
+                .split_whitespace()
+                .map(|x| x.chars().collect())
+                .collect();
+            if lanes.len() != 3 || lanes.iter().any(|x| x.len() != lanes[0].len()) {
+                return Err(error(
+                    "Measure should have 3 lanes of the same length".to_string(),
+                ));
+            }
+            let length = next_measure_length.take().unwrap_or(measure_length);
+            let split_length = length / lanes[0].len() as i64;
+
+            let [궁채_state, 열채_state] = &mut lane_states;
+            for (stick, lane, state) in [
+                (JangguStick::궁채, &lanes[0], 궁채_state),
+                (JangguStick::열채, &lanes[1], 열채_state),
+            ] {
+                for (index, character) in lane.iter().enumerate() {
+                    let beat = measure_start + split_length * index as i64;
+                    let kind = match character {
+                        '=' => Some(GameLongNoteKind::Hold),
+                        '~' => Some(GameLongNoteKind::Roll),
+                        _ => None,
+                    };
+
+                    if let Some(kind) = kind {
+                        // the body of the long note
+                        *state = match *state {
+                            LaneState::Note {
+                                face,
+                                beat: start,
+                                is_stroke: false,
+                            } => LaneState::LongNote {
+                                kind: kind,
+                                face: face,
+                                start: start,
+                                end: beat,
+                            },
+                            LaneState::LongNote {
+                                kind: current_kind,
+                                face,
+                                start,
+                                ..
+                            } if current_kind == kind => LaneState::LongNote {
+                                kind: kind,
+                                face: face,
+                                start: start,
+                                end: beat,
+                            },
+                            _ => {
+                                return Err(error(format!(
+                                    "The long note of {} has no start",
+                                    stick_name(stick)
+                                )))
+                            }
+                        };
+                        continue;
+                    }
+
+                    notes.finish(stick, state);
+                    *state = match character {
+                        '.' => LaneState::Idle,
+                        'L' | 'R' | 'l' | 'r' => LaneState::Note {
+                            face: if *character == 'L' || *character == 'l' {
+                                JangguFace::궁편
+                            } else {
+                                JangguFace::열편
+                            },
+                            beat: beat,
+                            is_stroke: character.is_lowercase(),
+                        },
+                        _ => return Err(error(format!("Unknown note: {}", character))),
+                    };
+                }
+            }
+
+            for (index, character) in lanes[2].iter().enumerate() {
+                match character {
+                    '.' => {}
+                    'H' => {
+                        let (beat_index, tick_nomiator, tick_denomiator) =
+                            split_beat(measure_start + split_length * index as i64);
+                        hats.push(GameHatNote::create_raw_note(
+                            beat_index,
+                            tick_nomiator,
+                            tick_denomiator,
+                        ));
+                    }
+                    _ => return Err(error(format!("Unknown hat note: {}", character))),
+                }
+            }
+
+            measure_start += length;
+        }
+        notes.finish(JangguStick::궁채, &lane_states[0]);
+        notes.finish(JangguStick::열채, &lane_states[1]);
+
+        for (stick, beat, hits, line_number) in required_hits {
+            match notes
+                .long_notes
+                .iter_mut()
+                .find(|x| x.stick == stick && x.beat() == beat)
+            {
+                Some(long_note) => long_note.required_hits = Some(hits),
+                None => {
+                    return Err(format!(
+                        "Line {}: There's no long note of {} at beat {}",
+                        line_number,
+                        stick_name(stick),
+                        rational_string(beat)
+                    ))
+                }
+            }
+        }
+
+        let strokes = notes
+            .strokes
+            .iter()
+            .map(|(beat, (궁채, 열채))| {
+                let stroke = JangguStroke::from_faces(JangguInputState {
+                    궁채: *궁채,
+                    열채: *열채,
+                })
+                .expect("Stroke is hit by at least one stick");
+                let (beat_index, tick_nomiator, tick_denomiator) = split_beat(*beat);
+                GameStrokeNote::create_raw_stroke_note(
+                    stroke,
+                    beat_index,
+                    tick_nomiator,
+                    tick_denomiator,
+                )
+            })
+            .collect();
+
+        let mut chart = GameChart {
+            artist: artist,
+            metadata: metadata,
+            delay: delay.ok_or("There's no delay in the header")?,
+            bpm: bpm.ok_or("There's no bpm in the header")?,
+            left_face: notes.left_face,
+            right_face: notes.right_face,
+            hats: hats,
+            strokes: strokes,
+            long_notes: notes.long_notes,
+            bpm_changes: bpm_changes,
+            time_signatures: time_signatures,
+        };
+        chart.assign_note_ids();
+
+        Ok(chart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use crate::{
+        janggu::{JangguFace, JangguStick, JangguStroke},
+        song::{GameChart, GameLongNoteKind},
+    };
+
+    /// notation in the form written by `to_notation`
+    const NOTATION: &str = "\
+bidrum-notation 1
+artist: Team Overchaos
+difficulty_name: 보통
+level: 3
+delay: 10
+bpm: 140
+
+# 궁채 열채 hat
+L.L. .R.R H...
+@meter 3/4
+@bpm 3/2 160
+l.. r.. ...
+@hits 열채 1 8
+L== .R~ ...
+... ~~. ..H
+@length 2
+@bpm 1 127.5
+.l r. ..
+@meter 2/4
+R. L. H.
+";
+
+    #[test]
+    fn notation_is_read() {
+        let chart = GameChart::from_notation(NOTATION).expect("valid notation");
+
+        assert_eq!(chart.artist, "Team Overchaos");
+        assert_eq!(chart.metadata.difficulty_name, Some("보통".to_string()));
+        assert_eq!(chart.metadata.level, Some(3));
+        assert_eq!(chart.delay, 10);
+        assert_eq!(chart.bpm, Rational64::from_integer(140));
+
+        assert_eq!(chart.left_face.len(), 3);
+        assert_eq!(chart.right_face.len(), 3);
+        assert_eq!(chart.hats.len(), 3);
+        let strokes: Vec<(Rational64, JangguStroke)> =
+            chart.strokes.iter().map(|x| (x.beat(), x.stroke)).collect();
+        assert_eq!(
+            strokes,
+            vec![
+                (Rational64::from_integer(4), JangguStroke::덩),
+                (Rational64::from_integer(13), JangguStroke::덕),
+                (Rational64::from_integer(14), JangguStroke::쿵),
+            ]
+        );
+
+        assert_eq!(chart.long_notes.len(), 2);
+        let hold = &chart.long_notes[0];
+        assert_eq!(hold.kind, GameLongNoteKind::Hold);
+        assert_eq!(hold.stick, JangguStick::궁채);
+        assert_eq!(hold.face, JangguFace::궁편);
+        assert_eq!(hold.beat(), Rational64::from_integer(7));
+        assert_eq!(hold.end_beat(), Rational64::from_integer(9));
+        assert_eq!(hold.required_hits, None);
+        let roll = &chart.long_notes[1];
+        assert_eq!(roll.kind, GameLongNoteKind::Roll);
+        assert_eq!(roll.stick, JangguStick::열채);
+        assert_eq!(roll.face, JangguFace::열편);
+        assert_eq!(roll.beat(), Rational64::from_integer(8));
+        assert_eq!(roll.end_beat(), Rational64::from_integer(11));
+        assert_eq!(roll.required_hits, Some(8));
+
+        let bpm_changes: Vec<(Rational64, Rational64)> = chart
+            .bpm_changes
+            .iter()
+            .map(|x| (x.beat(), x.bpm))
+            .collect();
+        assert_eq!(
+            bpm_changes,
+            vec![
+                (Rational64::new(11, 2), Rational64::from_integer(160)),
+                (Rational64::from_integer(14), Rational64::new(255, 2)),
+            ]
+        );
+        let time_signatures: Vec<(Rational64, u32, u32)> = chart
+            .time_signatures
+            .iter()
+            .map(|x| (x.beat(), x.beats_per_measure, x.beat_unit))
+            .collect();
+        assert_eq!(
+            time_signatures,
+            vec![
+                (Rational64::from_integer(4), 3, 4),
+                (Rational64::from_integer(15), 2, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn notation_is_written_back_as_it_is() {
+        let chart = GameChart::from_notation(NOTATION).expect("valid notation");
+
+        assert_eq!(chart.to_notation().expect("writable chart"), NOTATION);
+    }
+
+    #[test]
+    fn round_trip_keeps_content_hash() {
+        let chart: GameChart = serde_json::from_str(
+            r#"{
+                "artist": "someone",
+                "metadata": {"charter": " spaced ", "description": "two\nlines", "preview_start_in_ms": 3000},
+                "delay": 120,
+                "bpm": "400/3",
+                "left_face": [
+                    {"stick": "궁채", "beat_index": 0, "tick_nomiator": 0, "tick_denomiator": 1},
+                    {"stick": "열채", "beat_index": 0, "tick_nomiator": 1, "tick_denomiator": 3}
+                ],
+                "right_face": [
+                    {"stick": "열채", "beat_index": 1, "tick_nomiator": 3, "tick_denomiator": 4}
+                ],
+                "hats": [{"beat_index": 2, "tick_nomiator": 0, "tick_denomiator": 1}],
+                "strokes": [
+                    {"stroke": "넘겨덩", "beat_index": 2, "tick_nomiator": 0, "tick_denomiator": 1},
+                    {"stroke": "쿵", "beat_index": 3, "tick_nomiator": 1, "tick_denomiator": 2}
+                ],
+                "long_notes": [
+                    {"kind": "Hold", "stick": "궁채", "face": "열편",
+                     "beat_index": 4, "tick_nomiator": 0, "tick_denomiator": 1,
+                     "end_beat_index": 9, "end_tick_nomiator": 1, "end_tick_denomiator": 6},
+                    {"kind": "Roll", "stick": "열채", "face": "궁편", "required_hits": 12,
+                     "beat_index": 5, "tick_nomiator": 1, "tick_denomiator": 2,
+                     "end_beat_index": 7, "end_tick_nomiator": 0, "end_tick_denomiator": 1}
+                ],
+                "bpm_changes": [
+                    {"beat_index": 6, "tick_nomiator": 1, "tick_denomiator": 3, "bpm": "127.5"}
+                ],
+                "time_signatures": [
+                    {"beat_index": 8, "tick_nomiator": 0, "tick_denomiator": 1, "beats_per_measure": 7, "beat_unit": 8},
+                    {"beat_index": 10, "tick_nomiator": 0, "tick_denomiator": 1, "beats_per_measure": 3, "beat_unit": 4}
+                ]
+            }"#,
+        )
+        .expect("valid chart");
+
+        let notation = chart.to_notation().expect("writable chart");
+        let read = GameChart::from_notation(&notation).expect("valid notation");
+
+        assert_eq!(read.content_hash(), chart.content_hash());
+        assert_eq!(read.artist, chart.artist);
+        assert_eq!(read.metadata.charter, chart.metadata.charter);
+        assert_eq!(read.metadata.description, chart.metadata.description);
+        assert_eq!(
+            read.metadata.preview_start_in_ms,
+            chart.metadata.preview_start_in_ms
+        );
+        let roll = read
+            .long_notes
+            .iter()
+            .find(|x| x.kind == GameLongNoteKind::Roll)
+            .expect("roll note is read");
+        assert_eq!(roll.required_hits, Some(12));
+        assert_eq!(read.to_notation().expect("writable chart"), notation);
+    }
+
+    #[test]
+    fn overlapped_notes_are_not_written() {
+        let chart: GameChart = serde_json::from_str(
+            r#"{"artist": "", "delay": 0, "bpm": 120,
+                "left_face": [{"stick": "궁채", "beat_index": 1, "tick_nomiator": 0, "tick_denomiator": 1}],
+                "right_face": [{"stick": "궁채", "beat_index": 1, "tick_nomiator": 0, "tick_denomiator": 1}]}"#,
+        )
+        .expect("valid chart");
+
+        assert_eq!(
+            chart.to_notation(),
+            Err("Notes of 궁채 are overlapped at beat 1".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_notation_is_rejected() {
+        let error = |body: &str| {
+            GameChart::from_notation(&format!("bidrum-notation 1\ndelay: 0\nbpm: 120\n{}", body))
+                .expect_err("invalid notation")
+        };
+
+        assert_eq!(
+            GameChart::from_notation("delay: 0\nbpm: 120\n").expect_err("no header line"),
+            "The first line should be `bidrum-notation 1`"
+        );
+        assert_eq!(
+            GameChart::from_notation("bidrum-notation 1\nbpm: 120\nL... .... ....\n")
+                .expect_err("no delay"),
+            "There's no delay in the header"
+        );
+        assert_eq!(
+            GameChart::from_notation("bidrum-notation 1\ndelay: 0\nL... .... ....\n")
+                .expect_err("no bpm"),
+            "There's no bpm in the header"
+        );
+        assert_eq!(error("tempo: 120"), "Line 4: Unknown header: tempo");
+        assert_eq!(error("level: high"), "Line 4: Invalid number: high");
+        assert_eq!(
+            error("L... ....\n"),
+            "Line 4: Measure should have 3 lanes of the same length"
+        );
+        assert_eq!(
+            error("L... .. ....\n"),
+            "Line 4: Measure should have 3 lanes of the same length"
+        );
+        assert_eq!(error("X... .... ....\n"), "Line 4: Unknown note: X");
+        assert_eq!(error(".... .... .L..\n"), "Line 4: Unknown hat note: L");
+        assert_eq!(
+            error("=... .... ....\n"),
+            "Line 4: The long note of 궁채 has no start"
+        );
+        assert_eq!(
+            error("L=~. .... ....\n"),
+            "Line 4: The long note of 궁채 has no start"
+        );
+        assert_eq!(
+            error("@swing 2\nL... .... ....\n"),
+            "Line 4: Unknown directive: @swing 2"
+        );
+        assert_eq!(
+            error("@meter 3\nL.. ... ...\n"),
+            "Line 4: Invalid time signature: 3"
+        );
+        assert_eq!(
+            error("@length 0\nL... .... ....\n"),
+            "Line 4: Invalid length of the measure: 0"
+        );
+        assert_eq!(
+            error("@bpm 1/0 120\nL... .... ....\n"),
+            "Line 4: Invalid position: 1/0"
+        );
+        assert_eq!(
+            error("@hits 궁채 0 4\nL... .... ....\n"),
+            "Line 4: There's no long note of 궁채 at beat 0"
+        );
+        assert_eq!(
+            error("@hits 채 0 4\nL~.. .... ....\n"),
+            "Line 4: Unknown stick: 채"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use num_rational::Rational64;
+
+use crate::janggu::{JangguFace, JangguInputState, JangguStick, JangguStroke};
+
+use super::{
+    beat_and_timing::{beat, get_position},
+    GameChart, GameNote, GameStrokeNote, TempoMap,
+};
+
+impl GameStrokeNote {
+    pub fn create_raw_stroke_note(
+        stroke: JangguStroke,
+        beat_index: u64,
+        tick_nomiator: i64,
+        tick_denomiator: i64,
+    ) -> GameStrokeNote {
+        GameStrokeNote {
+            stroke: stroke,
+            beat_index: beat_index,
+            tick_nomiator: tick_nomiator,
+            tick_denomiator: tick_denomiator,
+            // id is useless
+            id: 0,
+        }
+    }
+
+    /// get the position of the note in unit of beat
+    pub fn beat(&self) -> Rational64 {
+        beat(
+            self.beat_index as i64,
+            self.tick_nomiator,
+            self.tick_denomiator,
+        )
+    }
+
+    /// calculate the timing of the note
+    pub fn timing_in_ms(&self, tempo_map: &TempoMap) -> u64 {
+        tempo_map.timing_in_ms(self.beat())
+    }
+
+    /// Get the position of the note in the display.
+    ///
+    /// See [`GameNote::get_position`] for the unit of the position.
+    pub fn get_position(
+        &self,
+        tempo_map: &TempoMap,
+        display_bpm: Rational64,
+        current_time_in_ms: u64,
+    ) -> f64 {
+        get_position(
+            self.timing_in_ms(tempo_map),
+            display_bpm,
+            current_time_in_ms,
+        )
+    }
+
+    /// Splits the note into the notes of each stick
+    ///
+    /// The split notes have the same id with the stroke note.
+    pub fn notes(&self) -> Vec<GameNote> {
+        let mut notes = vec![];
+        for stick in [JangguStick::궁채, JangguStick::열채] {
+            if let Some(face) = self.stroke.face_of(stick) {
+                notes.push(GameNote {
+                    stick: stick,
+                    beat_index: self.beat_index,
+                    tick_nomiator: self.tick_nomiator,
+                    tick_denomiator: self.tick_denomiator,
+                    id: self.id,
+                    face: face,
+                });
+            }
+        }
+
+        notes
+    }
+}
+
+impl GameChart {
+    /// Assigns the ids of the notes
+    ///
+    /// The faces of the notes in `left_face` and `right_face` are set, too.
+    pub fn assign_note_ids(&mut self) {
+        let mut note_index: u64 = 0;
+        for note in &mut self.left_face {
+            note.face = JangguFace::궁편;
+            note.id = note_index;
+            note_index += 1;
+        }
+        for note in &mut self.right_face {
+            note.face = JangguFace::열편;
+            note.id = note_index;
+            note_index += 1;
+        }
+        for note in &mut self.hats {
+            note.id = note_index;
+            note_index += 1;
+        }
+        for note in &mut self.long_notes {
+            note.id = note_index;
+            note_index += 1;
+        }
+        for note in &mut self.strokes {
+            note.id = note_index;
+            note_index += 1;
+        }
+    }
+
+    /// Converts the notes hit by both sticks at the same position into the stroke notes
+    ///
+    /// The note ids are reassigned.
+    pub fn merge_strokes(&mut self) {
+        self.assign_note_ids();
+
+        let notes: Vec<GameNote> = self
+            .left_face
+            .iter()
+            .chain(&self.right_face)
+            .cloned()
+            .collect();
+        let mut merged_ids = vec![];
+        for note in notes.iter().filter(|x| x.stick == JangguStick::궁채) {
+            let other = notes.iter().find(|x| {
+                x.stick == JangguStick::열채
+                    && x.beat() == note.beat()
+                    && !merged_ids.contains(&x.id)
+            });
+            if let Some(other) = other {
+                let stroke = JangguStroke::from_faces(JangguInputState {
+                    궁채: Some(note.face),
+                    열채: Some(other.face),
+                })
+                .expect("Every combination of the faces is a stroke");
+                self.strokes.push(GameStrokeNote {
+                    stroke: stroke,
+                    beat_index: note.beat_index,
+                    tick_nomiator: note.tick_nomiator,
+                    tick_denomiator: note.tick_denomiator,
+                    id: 0,
+                });
+                merged_ids.push(note.id);
+                merged_ids.push(other.id);
+            }
+        }
+
+        self.left_face.retain(|x| !merged_ids.contains(&x.id));
+        self.right_face.retain(|x| !merged_ids.contains(&x.id));
+        self.strokes.sort_by_key(|x| x.beat());
+        self.assign_note_ids();
+    }
+
+    /// Converts the stroke notes into the notes of `left_face` and `right_face`
+    ///
+    /// The note ids are reassigned.
+    pub fn split_strokes(&mut self) {
+        for stroke in &self.strokes {
+            for note in stroke.notes() {
+                match note.face {
+                    JangguFace::궁편 => self.left_face.push(note),
+                    JangguFace::열편 => self.right_face.push(note),
+                }
+            }
+        }
+        self.strokes.clear();
+
+        self.left_face.sort_by_key(|x| x.beat());
+        self.right_face.sort_by_key(|x| x.beat());
+        self.assign_note_ids();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use crate::{
+        janggu::{JangguFace, JangguStick, JangguStroke},
+        song::{GameChart, GameNote, GameStrokeNote},
+    };
+
+    fn notes(notes: &[GameNote]) -> Vec<(JangguStick, JangguFace, Rational64, u64)> {
+        notes
+            .iter()
+            .map(|x| (x.stick, x.face, x.beat(), x.id))
+            .collect()
+    }
+
+    #[test]
+    fn stroke_is_split_into_notes_of_each_stick() {
+        let mut stroke = GameStrokeNote::create_raw_stroke_note(JangguStroke::넘겨덩, 3, 1, 4);
+        stroke.id = 5;
+        let beat = Rational64::new(13, 4);
+
+        assert_eq!(
+            notes(&stroke.notes()),
+            vec![
+                (JangguStick::궁채, JangguFace::열편, beat, 5),
+                (JangguStick::열채, JangguFace::궁편, beat, 5),
+            ]
+        );
+
+        // both sticks on the same face
+        let stroke = GameStrokeNote::create_raw_stroke_note(JangguStroke::덩넘겨덕, 3, 1, 4);
+        assert_eq!(
+            notes(&stroke.notes()),
+            vec![
+                (JangguStick::궁채, JangguFace::궁편, beat, 0),
+                (JangguStick::열채, JangguFace::궁편, beat, 0),
+            ]
+        );
+
+        // a stick only
+        let stroke = GameStrokeNote::create_raw_stroke_note(JangguStroke::덕, 2, 0, 1);
+        assert_eq!(
+            notes(&stroke.notes()),
+            vec![(
+                JangguStick::열채,
+                JangguFace::열편,
+                Rational64::from_integer(2),
+                0
+            )]
+        );
+    }
+
+    #[test]
+    fn strokes_are_merged_and_split() {
+        let mut chart = GameChart::create_example_chart_for_tutorial(
+            JangguStick::궁채,
+            JangguFace::궁편,
+            2,
+            1,
+            120,
+        );
+        chart
+            .right_face
+            .push(GameNote::create_raw_note(JangguStick::열채, 2, 0, 1));
+        chart.assign_note_ids();
+
+        chart.merge_strokes();
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![(
+                JangguStick::궁채,
+                JangguFace::궁편,
+                Rational64::from_integer(1),
+                0
+            )]
+        );
+        assert!(chart.right_face.is_empty());
+        assert_eq!(chart.strokes.len(), 1);
+        assert_eq!(chart.strokes[0].stroke, JangguStroke::덩);
+        assert_eq!(chart.strokes[0].beat(), Rational64::from_integer(2));
+        assert_eq!(chart.strokes[0].id, 1);
+
+        chart.split_strokes();
+        assert!(chart.strokes.is_empty());
+        assert_eq!(
+            notes(&chart.left_face),
+            vec![
+                (
+                    JangguStick::궁채,
+                    JangguFace::궁편,
+                    Rational64::from_integer(1),
+                    0
+                ),
+                (
+                    JangguStick::궁채,
+                    JangguFace::궁편,
+                    Rational64::from_integer(2),
+                    1
+                ),
+            ]
+        );
+        assert_eq!(
+            notes(&chart.right_face),
+            vec![(
+                JangguStick::열채,
+                JangguFace::열편,
+                Rational64::from_integer(2),
+                2
+            )]
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use num_rational::Rational64;
+
+use super::{beat_and_timing::beat, GameBpmChange, GameTimeSignature};
+
+/// Part of the song which has constant tempo
+#[derive(Debug, Clone)]
+struct TempoSegment {
+    /// position where the segment starts in unit of beat
+    start_beat: Rational64,
+    /// timing where the segment starts in millisecond (without track delay)
+    start_timing: Rational64,
+    bpm: Rational64,
+}
+
+/// Part of the song which has constant meter
+#[derive(Debug, Clone)]
+struct MeterSegment {
+    /// position where the segment starts in unit of beat
+    start_beat: Rational64,
+    /// length of one measure in unit of beat
+    measure_length: Rational64,
+}
+
+/// Converts the position in unit of beat into the timing and vice versa,
+/// considering bpm changes and time signature changes of the chart
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    delay: u64,
+    tempo_segments: Vec<TempoSegment>,
+    meter_segments: Vec<MeterSegment>,
+}
+
+/// minute-per-beat in unit of millisecond
+fn milliseconds_per_beat(bpm: Rational64) -> Rational64 {
+    // bpm = beat / minute
+    // minute-per-beat = 1 / bpm
+    // millisecond-per-beat = minute-per-beat (minute) * ( 60000(millisecond) / 1(minute) )
+    Rational64::from_integer(60000) / bpm
+}
+
+/// Rounds the timing to microseconds
+///
+/// The exact timings of the tempo segments have growing denominators as the bpm changes,
+/// which overflow after dozens of the fractional bpm changes.
+fn round_to_microseconds(timing: Rational64) -> Rational64 {
+    Rational64::new((timing * 1000).round().to_integer(), 1000)
+}
+
+impl TempoMap {
+    /// Creates tempo map
+    ///
+    /// # Arguments
+    ///   * `bpm`: bpm at the start of the song
+    ///   * `delay`: timing of the first beat in millisecond
+    ///   * `bpm_changes`: bpm change events, which don't need to be sorted
+    ///   * `time_signatures`: time signature change events, which don't need to be sorted
+    pub fn new(
+        bpm: Rational64,
+        delay: u64,
+        bpm_changes: &[GameBpmChange],
+        time_signatures: &[GameTimeSignature],
+    ) -> TempoMap {
+        let mut sorted_bpm_changes = bpm_changes.to_vec();
+        sorted_bpm_changes.sort_by_key(|x| x.beat());
+
+        let mut tempo_segments = vec![TempoSegment {
+            start_beat: Rational64::new(0, 1),
+            start_timing: Rational64::new(0, 1),
+            bpm: bpm,
+        }];
+        for i in sorted_bpm_changes {
+            // bpm changes before the first beat are applied from the first beat
+            let start_beat = i.beat().max(Rational64::new(0, 1));
+            let last = tempo_segments.last().unwrap();
+            let start_timing = round_to_microseconds(
+                last.start_timing
+                    + (start_beat - last.start_beat) * milliseconds_per_beat(last.bpm),
+            );
+
+            if last.start_beat == start_beat {
+                // the latter one overrides the former one at the same position
+                tempo_segments.pop();
+            }
+            tempo_segments.push(TempoSegment {
+                start_beat: start_beat,
+                start_timing: start_timing,
+                bpm: i.bpm,
+            });
+        }
+
+        let mut sorted_time_signatures = time_signatures.to_vec();
+        sorted_time_signatures.sort_by_key(|x| x.beat());
+
+        // 4/4 is assumed if there's no time signature at the start of the song
+        let mut meter_segments = vec![MeterSegment {
+            start_beat: Rational64::new(0, 1),
+            measure_length: Rational64::new(4, 1),
+        }];
+        for i in sorted_time_signatures {
+            let start_beat = i.beat().max(Rational64::new(0, 1));
+            if meter_segments.last().unwrap().start_beat == start_beat {
+                meter_segments.pop();
+            }
+            meter_segments.push(MeterSegment {
+                start_beat: start_beat,
+                measure_length: i.measure_length(),
+            });
+        }
+
+        TempoMap {
+            delay: delay,
+            tempo_segments: tempo_segments,
+            meter_segments: meter_segments,
+        }
+    }
+
+    /// Creates tempo map of the song which has constant bpm and 4/4 time signature
+    pub fn constant(bpm: Rational64, delay: u64) -> TempoMap {
+        TempoMap::new(bpm, delay, &[], &[])
+    }
+
+    fn tempo_segment_at_beat(&self, beat: Rational64) -> &TempoSegment {
+        self.tempo_segments
+            .iter()
+            .rev()
+            .find(|x| x.start_beat <= beat)
+            .unwrap_or(&self.tempo_segments[0])
+    }
+
+    fn tempo_segment_at_timing(&self, timing_without_delay: Rational64) -> &TempoSegment {
+        self.tempo_segments
+            .iter()
+            .rev()
+            .find(|x| x.start_timing <= timing_without_delay)
+            .unwrap_or(&self.tempo_segments[0])
+    }
+
+    fn meter_segment_at_beat(&self, beat: Rational64) -> &MeterSegment {
+        self.meter_segments
+            .iter()
+            .rev()
+            .find(|x| x.start_beat <= beat)
+            .unwrap_or(&self.meter_segments[0])
+    }
+
+    /// timing of the first beat in millisecond
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+
+    /// bpm at the given position in unit of beat
+    pub fn bpm_at(&self, beat: Rational64) -> Rational64 {
+        self.tempo_segment_at_beat(beat).bpm
+    }
+
+    /// calculate the precise timing of the given position in unit of beat
+    ///
+    /// The timing can be negative when the position is before the first beat
+    /// and the track delay is not enough.
+    pub fn precise_timing_in_ms(&self, beat: Rational64) -> Rational64 {
+        let segment = self.tempo_segment_at_beat(beat);
+        let timing =
+            segment.start_timing + (beat - segment.start_beat) * milliseconds_per_beat(segment.bpm);
+
+        timing + Rational64::new(self.delay as i64, 1)
+    }
+
+    /// calculate the timing of the given position in unit of beat
+    pub fn timing_in_ms(&self, beat: Rational64) -> u64 {
+        let timing = self.precise_timing_in_ms(beat);
+
+        (timing.numer() / timing.denom()).max(0) as u64
+    }
+
+    /// calculate the position in unit of beat at the given timing
+    ///
+    /// The position is negative before the first beat.
+    pub fn beat_at(&self, timing_in_ms: i64) -> Rational64 {
+        let timing_without_delay = Rational64::new(timing_in_ms - self.delay as i64, 1);
+        let segment = self.tempo_segment_at_timing(timing_without_delay);
+
+        segment.start_beat
+            + (timing_without_delay - segment.start_timing) / milliseconds_per_beat(segment.bpm)
+    }
+
+    /// length of the measure containing the given position in unit of beat
+    pub fn measure_length_at(&self, beat: Rational64) -> Rational64 {
+        self.meter_segment_at_beat(beat).measure_length
+    }
+
+    /// Whether a measure starts at the given position
+    pub fn is_measure_start(&self, beat: Rational64) -> bool {
+        let segment = self.meter_segment_at_beat(beat);
+        let measures = (beat - segment.start_beat) / segment.measure_length;
+
+        measures.is_integer()
+    }
+}
+
+impl GameBpmChange {
+    pub fn create_raw_bpm_change(
+        beat_index: u64,
+        tick_nomiator: i64,
+        tick_denomiator: i64,
+        bpm: Rational64,
+    ) -> GameBpmChange {
+        GameBpmChange {
+            beat_index: beat_index,
+            tick_nomiator: tick_nomiator,
+            tick_denomiator: tick_denomiator,
+            bpm: bpm,
+        }
+    }
+
+    /// get the position where the bpm changes in unit of beat
+    pub fn beat(&self) -> Rational64 {
+        beat(
+            self.beat_index as i64,
+            self.tick_nomiator,
+            self.tick_denomiator,
+        )
+    }
+}
+
+impl GameTimeSignature {
+    pub fn create_raw_time_signature(
+        beat_index: u64,
+        tick_nomiator: i64,
+        tick_denomiator: i64,
+        beats_per_measure: u32,
+        beat_unit: u32,
+    ) -> GameTimeSignature {
+        GameTimeSignature {
+            beat_index: beat_index,
+            tick_nomiator: tick_nomiator,
+            tick_denomiator: tick_denomiator,
+            beats_per_measure: beats_per_measure,
+            beat_unit: beat_unit,
+        }
+    }
+
+    /// get the position where the time signature changes in unit of beat
+    pub fn beat(&self) -> Rational64 {
+        beat(
+            self.beat_index as i64,
+            self.tick_nomiator,
+            self.tick_denomiator,
+        )
+    }
+
+    /// length of one measure in unit of beat (quarter note)
+    ///
+    /// e.g. 3 for 3/4, 3 for 6/8, 4 for 4/4
+    pub fn measure_length(&self) -> Rational64 {
+        if self.beats_per_measure == 0 || self.beat_unit == 0 {
+            // broken time signature is regarded as 4/4
+            return Rational64::new(4, 1);
+        }
+
+        Rational64::new(self.beats_per_measure as i64 * 4, self.beat_unit as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+
+    use super::TempoMap;
+    use crate::song::{GameBpmChange, GameTimeSignature};
+
+    #[test]
+    fn timing_of_constant_bpm() {
+        let tempo_map = TempoMap::constant(Rational64::from_integer(120), 1000);
+
+        assert_eq!(tempo_map.timing_in_ms(Rational64::from_integer(0)), 1000);
+        assert_eq!(tempo_map.timing_in_ms(Rational64::new(9, 2)), 3250);
+        assert_eq!(tempo_map.beat_at(3250), Rational64::new(9, 2));
+    }
+
+    #[test]
+    fn timing_after_bpm_changes() {
+        let tempo_map = TempoMap::new(
+            Rational64::from_integer(120),
+            0,
+            &[
+                // unsorted, and the latter one overrides at the same position
+                GameBpmChange::create_raw_bpm_change(8, 0, 1, Rational64::from_integer(60)),
+                GameBpmChange::create_raw_bpm_change(4, 0, 1, Rational64::from_integer(240)),
+                GameBpmChange::create_raw_bpm_change(8, 0, 1, Rational64::from_integer(120)),
+            ],
+            &[],
+        );
+
+        // 4 beats of 500ms, 4 beats of 250ms, then 500ms
+        assert_eq!(tempo_map.timing_in_ms(Rational64::from_integer(4)), 2000);
+        assert_eq!(tempo_map.timing_in_ms(Rational64::from_integer(8)), 3000);
+        assert_eq!(tempo_map.timing_in_ms(Rational64::from_integer(10)), 4000);
+        assert_eq!(tempo_map.bpm_at(Rational64::from_integer(9)), 120.into());
+        assert_eq!(tempo_map.beat_at(2500), Rational64::from_integer(6));
+    }
+
+    #[test]
+    fn many_fractional_bpm_changes_do_not_overflow() {
+        // imported charts have a bpm change on every red line,
+        // whose bpm has 3 digits after the decimal point
+        let bpm_changes: Vec<GameBpmChange> = (0..500)
+            .map(|i| {
+                GameBpmChange::create_raw_bpm_change(
+                    i,
+                    i as i64 % 7,
+                    7 + i as i64 % 5,
+                    Rational64::new(180_001 + 37 * i as i64, 1000),
+                )
+            })
+            .collect();
+        let tempo_map = TempoMap::new(Rational64::from_integer(180), 0, &bpm_changes, &[]);
+
+        // same calculation in floating point
+        let mut expected = 0.0;
+        let mut last_beat = 0.0;
+        let mut last_bpm = 180.0;
+        for i in &bpm_changes {
+            let beat = *i.beat().numer() as f64 / *i.beat().denom() as f64;
+            expected += (beat - last_beat) * 60000.0 / last_bpm;
+            last_beat = beat;
+            last_bpm = *i.bpm.numer() as f64 / *i.bpm.denom() as f64;
+        }
+        expected += (500.0 - last_beat) * 60000.0 / last_bpm;
+
+        let timing = tempo_map.timing_in_ms(Rational64::from_integer(500));
+        assert!(
+            (timing as f64 - expected).abs() < 1.0,
+            "{} {}",
+            timing,
+            expected
+        );
+        let beat = tempo_map.beat_at(timing as i64);
+        assert!(beat > Rational64::new(4995, 10) && beat <= Rational64::from_integer(500));
+    }
+
+    #[test]
+    fn rounding_keeps_timings_monotonic() {
+        let bpm_changes: Vec<GameBpmChange> = (1..200)
+            .map(|i| {
+                GameBpmChange::create_raw_bpm_change(
+                    i,
+                    1,
+                    3,
+                    Rational64::new(100_000 + i as i64 * 997, 999),
+                )
+            })
+            .collect();
+        let tempo_map = TempoMap::new(Rational64::from_integer(100), 0, &bpm_changes, &[]);
+
+        let timings: Vec<Rational64> = (0..1000)
+            .map(|x| tempo_map.precise_timing_in_ms(Rational64::new(x, 5)))
+            .collect();
+        assert!(timings.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    #[test]
+    fn measures_follow_time_signatures() {
+        let tempo_map = TempoMap::new(
+            Rational64::from_integer(120),
+            0,
+            &[],
+            &[GameTimeSignature::create_raw_time_signature(8, 0, 1, 3, 4)],
+        );
+
+        assert_eq!(
+            tempo_map.measure_length_at(Rational64::from_integer(7)),
+            4.into()
+        );
+        assert_eq!(
+            tempo_map.measure_length_at(Rational64::from_integer(8)),
+            3.into()
+        );
+        assert!(tempo_map.is_measure_start(Rational64::from_integer(4)));
+        assert!(tempo_map.is_measure_start(Rational64::from_integer(11)));
+        assert!(!tempo_map.is_measure_start(Rational64::from_integer(12)));
+    }
+}
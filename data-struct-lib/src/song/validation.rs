@@ -0,0 +1,618 @@
+use std::fmt;
+
+use num_rational::Rational64;
+
+use crate::janggu::{JangguFace, JangguStick};
+
+use super::{GameChart, GameLongNoteKind, GameNote};
+
+/// Severity of the chart diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The chart can be played, but it's probably a mistake
+    Warning,
+    /// The chart can't be played as the charter intended
+    Error,
+}
+
+/// Problem found in the chart
+#[derive(Debug, Clone)]
+pub struct ChartDiagnostic {
+    pub severity: Severity,
+    /// id of the note which has the problem
+    pub note_id: Option<u64>,
+    /// position of the problem in unit of beat
+    pub beat: Option<Rational64>,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for ChartDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+        if let Some(note_id) = self.note_id {
+            write!(f, " [note #{}]", note_id)?;
+        }
+        if let Some(beat) = self.beat {
+            write!(f, " [beat {}]", beat)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+fn diagnostic(
+    severity: Severity,
+    note_id: Option<u64>,
+    beat: Option<Rational64>,
+    message: String,
+) -> ChartDiagnostic {
+    ChartDiagnostic {
+        severity: severity,
+        note_id: note_id,
+        beat: beat,
+        message: message,
+    }
+}
+
+/// Checks the position fields of the note-like element
+fn validate_position(
+    beat_index: u64,
+    tick_nomiator: i64,
+    tick_denomiator: i64,
+    note_id: Option<u64>,
+    name: &str,
+    diagnostics: &mut Vec<ChartDiagnostic>,
+) {
+    let beat = Rational64::from_integer(beat_index as i64);
+
+    if tick_denomiator == 0 && tick_nomiator != 0 {
+        diagnostics.push(diagnostic(
+            Severity::Error,
+            note_id,
+            Some(beat),
+            format!(
+                "{} has tick_nomiator {} but tick_denomiator is zero, so the tick is ignored",
+                name, tick_nomiator
+            ),
+        ));
+    } else if tick_denomiator < 0 {
+        diagnostics.push(diagnostic(
+            Severity::Error,
+            note_id,
+            Some(beat),
+            format!("{} has negative tick_denomiator {}", name, tick_denomiator),
+        ));
+    } else if tick_nomiator < 0 {
+        diagnostics.push(diagnostic(
+            Severity::Warning,
+            note_id,
+            Some(beat),
+            format!("{} has negative tick_nomiator {}", name, tick_nomiator),
+        ));
+    } else if tick_denomiator != 0 && tick_nomiator >= tick_denomiator {
+        diagnostics.push(diagnostic(
+            Severity::Warning,
+            note_id,
+            Some(beat),
+            format!(
+                "{} has tick {}/{} which is not less than a beat",
+                name, tick_nomiator, tick_denomiator
+            ),
+        ));
+    }
+}
+
+fn validate_note(note: &GameNote, diagnostics: &mut Vec<ChartDiagnostic>) {
+    validate_position(
+        note.beat_index,
+        note.tick_nomiator,
+        note.tick_denomiator,
+        Some(note.id),
+        "Note",
+        diagnostics,
+    );
+}
+
+/// Notes of each stick, including the split stroke notes
+fn stick_notes(chart: &GameChart) -> Vec<GameNote> {
+    chart
+        .left_face
+        .iter()
+        .cloned()
+        .chain(chart.right_face.iter().cloned())
+        .chain(chart.strokes.iter().flat_map(|x| x.notes()))
+        .collect()
+}
+
+/// Checks the notes which are hit by the same stick at the same position
+fn validate_stick_collisions(chart: &GameChart, diagnostics: &mut Vec<ChartDiagnostic>) {
+    let mut notes = stick_notes(chart);
+    notes.sort_by_key(|x| (x.beat(), x.id));
+
+    for (idx, note) in notes.iter().enumerate() {
+        for other in notes[idx + 1..]
+            .iter()
+            .take_while(|x| x.beat() == note.beat())
+        {
+            if other.stick != note.stick {
+                continue;
+            }
+
+            let stick_name = match note.stick {
+                JangguStick::궁채 => "궁채",
+                JangguStick::열채 => "열채",
+            };
+            if other.face == note.face {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    Some(other.id),
+                    Some(other.beat()),
+                    format!(
+                        "Duplicated {} note of note #{} on {}",
+                        stick_name,
+                        note.id,
+                        match note.face {
+                            JangguFace::궁편 => "궁편",
+                            JangguFace::열편 => "열편",
+                        }
+                    ),
+                ));
+            } else {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    Some(other.id),
+                    Some(other.beat()),
+                    format!(
+                        "{} can't hit both faces at once, but note #{} is on the other face at the same timing",
+                        stick_name, note.id
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Validates the chart
+///
+/// # Arguments
+///   * `chart`: the chart to validate, whose note ids are assigned
+///   * `audio_duration_in_ms`: length of the song audio. If given, notes after the end of the audio are reported.
+pub fn validate_chart(
+    chart: &GameChart,
+    audio_duration_in_ms: Option<u64>,
+) -> Vec<ChartDiagnostic> {
+    let mut diagnostics = vec![];
+
+    for note in chart.left_face.iter().chain(&chart.right_face) {
+        validate_note(note, &mut diagnostics);
+    }
+    for note in &chart.strokes {
+        validate_position(
+            note.beat_index,
+            note.tick_nomiator,
+            note.tick_denomiator,
+            Some(note.id),
+            "Stroke note",
+            &mut diagnostics,
+        );
+    }
+    for note in &chart.left_face {
+        if note.face != JangguFace::궁편 {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                Some(note.id),
+                Some(note.beat()),
+                "Note in left_face is not on 궁편".to_string(),
+            ));
+        }
+    }
+    for note in &chart.right_face {
+        if note.face != JangguFace::열편 {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                Some(note.id),
+                Some(note.beat()),
+                "Note in right_face is not on 열편".to_string(),
+            ));
+        }
+    }
+    for note in &chart.hats {
+        validate_position(
+            note.beat_index,
+            note.tick_nomiator,
+            note.tick_denomiator,
+            Some(note.id),
+            "Hat note",
+            &mut diagnostics,
+        );
+    }
+    for bpm_change in &chart.bpm_changes {
+        validate_position(
+            bpm_change.beat_index,
+            bpm_change.tick_nomiator,
+            bpm_change.tick_denomiator,
+            None,
+            "BPM change",
+            &mut diagnostics,
+        );
+    }
+    for time_signature in &chart.time_signatures {
+        validate_position(
+            time_signature.beat_index,
+            time_signature.tick_nomiator,
+            time_signature.tick_denomiator,
+            None,
+            "Time signature",
+            &mut diagnostics,
+        );
+        if time_signature.beats_per_measure == 0 || time_signature.beat_unit == 0 {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                None,
+                Some(time_signature.beat()),
+                format!(
+                    "Time signature {}/{} is invalid, so it's regarded as 4/4",
+                    time_signature.beats_per_measure, time_signature.beat_unit
+                ),
+            ));
+        }
+    }
+
+    for note in &chart.long_notes {
+        validate_position(
+            note.beat_index,
+            note.tick_nomiator,
+            note.tick_denomiator,
+            Some(note.id),
+            "Long note",
+            &mut diagnostics,
+        );
+        validate_position(
+            note.end_beat_index,
+            note.end_tick_nomiator,
+            note.end_tick_denomiator,
+            Some(note.id),
+            "End of long note",
+            &mut diagnostics,
+        );
+        if note.end_beat() <= note.beat() {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                Some(note.id),
+                Some(note.beat()),
+                format!(
+                    "Long note ends at beat {}, before it starts",
+                    note.end_beat()
+                ),
+            ));
+        }
+        if note.kind == GameLongNoteKind::Hold {
+            // the stick is on the face during the hold note
+            for other in &stick_notes(chart) {
+                if other.stick == note.stick
+                    && note.beat() < other.beat()
+                    && other.beat() < note.end_beat()
+                {
+                    diagnostics.push(diagnostic(
+                        Severity::Error,
+                        Some(other.id),
+                        Some(other.beat()),
+                        format!(
+                            "Note is hit by the stick which is kept on the face by hold note #{}",
+                            note.id
+                        ),
+                    ));
+                }
+            }
+        }
+        if note.kind == GameLongNoteKind::Hold && note.required_hits.is_some() {
+            diagnostics.push(diagnostic(
+                Severity::Warning,
+                Some(note.id),
+                Some(note.beat()),
+                "required_hits of hold note is ignored".to_string(),
+            ));
+        }
+    }
+
+    validate_stick_collisions(chart, &mut diagnostics);
+
+    if let Some(audio_duration_in_ms) = audio_duration_in_ms {
+        if let Some(preview_start_in_ms) = chart.metadata.preview_start_in_ms {
+            if preview_start_in_ms >= audio_duration_in_ms {
+                diagnostics.push(diagnostic(
+                    Severity::Warning,
+                    None,
+                    None,
+                    format!(
+                        "Preview starts at {}ms, after the end of the audio ({}ms)",
+                        preview_start_in_ms, audio_duration_in_ms
+                    ),
+                ));
+            }
+        }
+
+        let tempo_map = chart.tempo_map();
+        for note in &stick_notes(chart) {
+            let timing = note.timing_in_ms(&tempo_map);
+            if timing > audio_duration_in_ms {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    Some(note.id),
+                    Some(note.beat()),
+                    format!(
+                        "Note is at {}ms, after the end of the audio ({}ms)",
+                        timing, audio_duration_in_ms
+                    ),
+                ));
+            }
+        }
+        for note in &chart.long_notes {
+            let timing = note.end_timing_in_ms(&tempo_map);
+            if timing > audio_duration_in_ms {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    Some(note.id),
+                    Some(note.beat()),
+                    format!(
+                        "Long note ends at {}ms, after the end of the audio ({}ms)",
+                        timing, audio_duration_in_ms
+                    ),
+                ));
+            }
+        }
+        for note in &chart.hats {
+            let timing = note.timing_in_ms(&tempo_map);
+            if timing > audio_duration_in_ms {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    Some(note.id),
+                    Some(note.beat()),
+                    format!(
+                        "Hat note is at {}ms, after the end of the audio ({}ms)",
+                        timing, audio_duration_in_ms
+                    ),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational64;
+    use serde_json::json;
+
+    use super::{validate_chart, ChartDiagnostic, Severity};
+    use crate::song::GameChart;
+
+    /// Creates the chart at 120 bpm from the json fields of the notes
+    fn chart(fields: &str) -> GameChart {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&format!("{{{}}}", fields)).expect("invalid test chart");
+        let json_object = json.as_object_mut().expect("invalid test chart");
+        for (key, value) in [
+            ("artist", json!("")),
+            ("delay", json!(0)),
+            ("bpm", json!(120)),
+            ("left_face", json!([])),
+            ("right_face", json!([])),
+        ] {
+            json_object.entry(key).or_insert(value);
+        }
+
+        let mut chart: GameChart = serde_json::from_value(json).expect("invalid test chart");
+        chart.assign_note_ids();
+
+        chart
+    }
+
+    fn note(stick: &str, beat_index: u64, tick_nomiator: i64, tick_denomiator: i64) -> String {
+        format!(
+            r#"{{"stick": "{}", "beat_index": {}, "tick_nomiator": {}, "tick_denomiator": {}}}"#,
+            stick, beat_index, tick_nomiator, tick_denomiator
+        )
+    }
+
+    fn errors(diagnostics: &[ChartDiagnostic]) -> Vec<&ChartDiagnostic> {
+        diagnostics
+            .iter()
+            .filter(|x| x.severity == Severity::Error)
+            .collect()
+    }
+
+    #[test]
+    fn valid_chart_has_no_diagnostics() {
+        let chart = chart(&format!(
+            r#""left_face": [{}, {}], "right_face": [{}],
+            "strokes": [{{"stroke": "덩", "beat_index": 2, "tick_nomiator": 0, "tick_denomiator": 1}}],
+            "bpm_changes": [{{"beat_index": 4, "tick_nomiator": 1, "tick_denomiator": 2, "bpm": "127.5"}}],
+            "time_signatures": [{{"beat_index": 0, "tick_nomiator": 0, "tick_denomiator": 0, "beats_per_measure": 3, "beat_unit": 4}}]"#,
+            note("궁채", 0, 0, 0),
+            note("궁채", 1, 1, 2),
+            note("열채", 0, 0, 0),
+        ));
+
+        assert!(validate_chart(&chart, Some(10000)).is_empty());
+    }
+
+    #[test]
+    fn reports_duplicated_notes() {
+        // 1 + 0/1 is the same position as 1
+        let chart = chart(&format!(
+            r#""left_face": [{}, {}]"#,
+            note("궁채", 1, 0, 0),
+            note("궁채", 1, 0, 1),
+        ));
+        let diagnostics = validate_chart(&chart, None);
+        assert_eq!(errors(&diagnostics).len(), 1);
+        assert_eq!(diagnostics[0].note_id, Some(1));
+        assert_eq!(diagnostics[0].beat, Some(Rational64::from_integer(1)));
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Duplicated 궁채 note of note #0"));
+    }
+
+    #[test]
+    fn reports_same_stick_on_other_face() {
+        let chart = chart(&format!(
+            r#""left_face": [{}], "right_face": [{}]"#,
+            note("열채", 3, 1, 4),
+            note("열채", 3, 1, 4),
+        ));
+        let diagnostics = validate_chart(&chart, None);
+
+        assert_eq!(errors(&diagnostics).len(), 1);
+        assert_eq!(diagnostics[0].note_id, Some(1));
+        assert!(diagnostics[0]
+            .message
+            .contains("can't hit both faces at once"));
+    }
+
+    #[test]
+    fn reports_note_overlapping_stroke() {
+        // 덩 is hit by both sticks, so the 궁채 note at the same position overlaps it
+        let chart = chart(&format!(
+            r#""left_face": [{}],
+            "strokes": [{{"stroke": "덩", "beat_index": 2, "tick_nomiator": 0, "tick_denomiator": 1}}]"#,
+            note("궁채", 2, 0, 0),
+        ));
+
+        assert_eq!(errors(&validate_chart(&chart, None)).len(), 1);
+    }
+
+    #[test]
+    fn reports_note_during_hold_note() {
+        let chart = chart(&format!(
+            r#""left_face": [{}, {}],
+            "long_notes": [{{"kind": "Hold", "stick": "궁채", "face": "궁편",
+                "beat_index": 1, "tick_nomiator": 0, "tick_denomiator": 0,
+                "end_beat_index": 3, "end_tick_nomiator": 0, "end_tick_denomiator": 0}}]"#,
+            note("궁채", 2, 0, 0),
+            // at the end of the hold note
+            note("궁채", 3, 0, 0),
+        ));
+        let diagnostics = validate_chart(&chart, None);
+
+        assert_eq!(errors(&diagnostics).len(), 1);
+        assert_eq!(diagnostics[0].note_id, Some(0));
+        assert!(diagnostics[0].message.contains("hold note #2"));
+    }
+
+    #[test]
+    fn reports_broken_long_notes() {
+        let chart = chart(
+            r#""long_notes": [{"kind": "Hold", "stick": "열채", "face": "열편",
+                "beat_index": 4, "tick_nomiator": 0, "tick_denomiator": 0,
+                "end_beat_index": 4, "end_tick_nomiator": 0, "end_tick_denomiator": 0,
+                "required_hits": 4}]"#,
+        );
+        let diagnostics = validate_chart(&chart, None);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(diagnostics[0].message.contains("before it starts"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(diagnostics[1].message.contains("required_hits"));
+    }
+
+    #[test]
+    fn reports_broken_positions() {
+        let chart = chart(&format!(
+            r#""left_face": [{}, {}, {}]"#,
+            note("궁채", 1, 1, 0),
+            note("궁채", 2, 1, -2),
+            note("궁채", 3, 5, 4),
+        ));
+        let diagnostics = validate_chart(&chart, None);
+
+        assert_eq!(
+            diagnostics.iter().map(|x| x.severity).collect::<Vec<_>>(),
+            vec![Severity::Error, Severity::Error, Severity::Warning]
+        );
+        assert!(diagnostics[0].message.contains("tick_denomiator is zero"));
+        assert!(diagnostics[1].message.contains("negative tick_denomiator"));
+        assert!(diagnostics[2].message.contains("not less than a beat"));
+    }
+
+    #[test]
+    fn reports_bad_tempo_and_meter() {
+        let chart = chart(
+            r#""bpm_changes": [{"beat_index": 4, "tick_nomiator": 1, "tick_denomiator": 0, "bpm": 60}],
+            "time_signatures": [{"beat_index": 8, "tick_nomiator": 0, "tick_denomiator": 0, "beats_per_measure": 0, "beat_unit": 4}]"#,
+        );
+        let diagnostics = validate_chart(&chart, None);
+
+        assert_eq!(errors(&diagnostics).len(), 2);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("BPM change has tick_nomiator 1"));
+        assert_eq!(diagnostics[0].note_id, None);
+        assert_eq!(
+            diagnostics[1].message,
+            "Time signature 0/4 is invalid, so it's regarded as 4/4"
+        );
+        assert_eq!(diagnostics[1].beat, Some(Rational64::from_integer(8)));
+    }
+
+    #[test]
+    fn invalid_bpm_is_rejected_on_load() {
+        for bpm in ["0", "-120", "\"1/0\""] {
+            let json = format!(
+                r#"{{"artist": "", "delay": 0, "bpm": {}, "left_face": [], "right_face": []}}"#,
+                bpm
+            );
+            assert!(serde_json::from_str::<GameChart>(&json).is_err(), "{}", bpm);
+        }
+    }
+
+    #[test]
+    fn reports_notes_after_audio() {
+        let mut chart = chart(&format!(
+            r#""left_face": [{}],
+            "hats": [{{"beat_index": 5, "tick_nomiator": 0, "tick_denomiator": 0}}]"#,
+            // 500ms per beat
+            note("궁채", 4, 0, 0),
+        ));
+        chart.metadata.preview_start_in_ms = Some(3000);
+
+        let diagnostics = validate_chart(&chart, Some(3000));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Preview starts at 3000ms"));
+
+        // the note at 2000ms is not after the end
+        let diagnostics = validate_chart(&chart, Some(2000));
+        assert_eq!(errors(&diagnostics).len(), 1);
+        assert_eq!(errors(&diagnostics)[0].note_id, Some(1));
+
+        assert_eq!(errors(&validate_chart(&chart, Some(1999))).len(), 2);
+        assert!(validate_chart(&chart, None).is_empty());
+    }
+
+    #[test]
+    fn formats_diagnostic() {
+        let chart = chart(&format!(
+            r#""left_face": [{}, {}]"#,
+            note("궁채", 1, 1, 2),
+            note("궁채", 1, 1, 2),
+        ));
+
+        assert_eq!(
+            validate_chart(&chart, None)[0].to_string(),
+            "error [note #1] [beat 3/2]: Duplicated 궁채 note of note #0 on 궁편"
+        );
+    }
+}
@@ -62,6 +62,7 @@ pub const DEFAULT_FONT_OUTLINE_SIZE: u16 = 4;
 // font color
 pub const DEFAULT_FONT_COLOR: Color = Color::WHITE;
 pub const SELECT_SONG_FONT_COLOR: Color = Color::BLACK;
+pub const OPERATOR_MENU_SELECTED_FONT_COLOR: Color = Color::RGB(255, 220, 80);
 
 // font outline color
 pub const DEFAULT_FONT_OUTLINE_COLOR: Color = Color::BLACK;
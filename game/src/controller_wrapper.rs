@@ -6,13 +6,18 @@ use std::{
     thread,
 };
 
-use bidrum_controller_lib::{keyboard, serial, CoinInputDevice, JangguDevice};
+use bidrum_controller_lib::{
+    janggu_event::{JangguEventQueue, JangguInputEvent},
+    keyboard, serial, CoinInputDevice, ConnectionState, JangguDevice,
+};
 use bidrum_data_struct_lib::janggu::{JangguFace, JangguInputState};
 
 /// Wrapper of Coin/Janggu controller
 /// to avoid cumbersome ownership/borrow/lifetime problems
 pub struct ControllerWrapper {
     janggu_state: Arc<AtomicU8>,
+    janggu_events: JangguEventQueue,
+    connection_state: Arc<RwLock<ConnectionState>>,
     coins: Arc<AtomicU32>,
     coins_to_consume: Arc<AtomicU32>,
     stopping: Arc<AtomicBool>,
@@ -62,6 +67,16 @@ impl ControllerWrapper {
     pub fn read_janggu_state(&self) -> JangguInputState {
         u8_to_janggu_state(self.janggu_state.load(Ordering::Relaxed))
     }
+    /// Takes the janggu input events since the last call, in the order they happened
+    pub fn read_janggu_events(&self) -> Vec<JangguInputEvent> {
+        self.janggu_events.drain()
+    }
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state
+            .read()
+            .expect("Connection state is poisoned")
+            .clone()
+    }
     pub fn get_coins(&self) -> u32 {
         self.coins.load(Ordering::Relaxed)
     }
@@ -73,11 +88,14 @@ impl ControllerWrapper {
         let coins_to_consume = Arc::new(AtomicU32::new(0));
         let stopping = Arc::new(AtomicBool::new(false));
         let janggu_state = Arc::new(AtomicU8::new(0));
+        let janggu_events = JangguEventQueue::new();
+        let connection_state = Arc::new(RwLock::new(ConnectionState::Connected));
         {
             let coins = coins.clone();
             let coins_to_consume = coins_to_consume.clone();
             let stopping = stopping.clone();
             let janggu_state = janggu_state.clone();
+            let janggu_events = janggu_events.clone();
 
             thread::spawn(move || {
                 let mut coin_device = keyboard::coin_device::KeyboardCoinDevice::new();
@@ -99,12 +117,17 @@ impl ControllerWrapper {
                         janggu_state_to_u8(janggu_device.read_janggu_input_state()),
                         Ordering::Relaxed,
                     );
+                    for event in janggu_device.read_janggu_events() {
+                        janggu_events.push(event);
+                    }
                 }
             });
         }
 
         ControllerWrapper {
             janggu_state: janggu_state,
+            janggu_events: janggu_events,
+            connection_state: connection_state,
             coins: coins,
             coins_to_consume: coins_to_consume,
             stopping: stopping,
@@ -116,11 +139,15 @@ impl ControllerWrapper {
         let coins_to_consume = Arc::new(AtomicU32::new(0));
         let stopping = Arc::new(AtomicBool::new(false));
         let janggu_state = Arc::new(AtomicU8::new(0));
+        let janggu_events = JangguEventQueue::new();
+        let connection_state = Arc::new(RwLock::new(ConnectionState::Connecting));
         {
             let coins = coins.clone();
             let coins_to_consume = coins_to_consume.clone();
             let stopping = stopping.clone();
             let janggu_state = janggu_state.clone();
+            let janggu_events = janggu_events.clone();
+            let connection_state = connection_state.clone();
 
             thread::spawn(move || {
                 let (janggu_device, mut coin_device) = serial::new(controller_port);
@@ -141,12 +168,28 @@ impl ControllerWrapper {
                         janggu_state_to_u8(janggu_device.read_janggu_input_state()),
                         Ordering::Relaxed,
                     );
+                    for event in janggu_device.read_janggu_events() {
+                        janggu_events.push(event);
+                    }
+
+                    let new_connection_state = janggu_device.connection_state();
+                    if *connection_state
+                        .read()
+                        .expect("Connection state is poisoned")
+                        != new_connection_state
+                    {
+                        *connection_state
+                            .write()
+                            .expect("Connection state is poisoned") = new_connection_state;
+                    }
                 }
             });
         }
 
         ControllerWrapper {
             janggu_state: janggu_state,
+            janggu_events: janggu_events,
+            connection_state: connection_state,
             coins: coins,
             coins_to_consume: coins_to_consume,
             stopping: stopping,
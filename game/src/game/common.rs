@@ -1,9 +1,18 @@
-use sdl2::{event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render::TextureQuery};
+use bidrum_controller_lib::ConnectionState;
+use sdl2::{
+    event::Event,
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, TextureQuery},
+};
 
 use super::{
     game_common_context::GameCommonContext, util::create_outlined_font_texture::create_font_texture,
 };
-use crate::constants::{CREDIT_FONT_SIZE, DEFAULT_FONT_OUTLINE_SIZE};
+use crate::constants::{
+    CREDIT_FONT_SIZE, DEFAULT_FONT_OUTLINE_SIZE, DEFAULT_FONT_SIZE, GAME_RESULT_FONT_SIZE,
+};
 use crate::constants::{
     DEFAULT_FONT_COLOR, DEFAULT_FONT_OUTLINE_COLOR, DEFAULT_FONT_PATH as FONT_PATH,
 };
@@ -70,4 +79,70 @@ pub(crate) fn render_common(context: &mut GameCommonContext) {
         height as u32,
     );
     canvas.copy(&texture, None, Some(target)).expect("Failure");
+
+    // the controller can't be played until it's connected again
+    match context.coin_and_janggu.connection_state() {
+        // the handshake at the boot is not a lost connection
+        ConnectionState::Connecting | ConnectionState::Connected => {}
+        ConnectionState::Reconnecting => render_disconnected(context, None),
+        ConnectionState::Failed(reason) => render_disconnected(context, Some(&reason)),
+    }
+}
+
+/// Overlay on the whole screen while the controller is disconnected
+fn render_disconnected(context: &mut GameCommonContext, reason: Option<&str>) {
+    let canvas = &mut context.canvas;
+    let texture_creator = canvas.texture_creator();
+    let font = context
+        .freetype_library
+        .new_face(
+            FONT_PATH.to_owned() + "/sans.ttf",
+            262144, /* Regular */
+        )
+        .expect("Failed to load sans");
+
+    let previous_color = canvas.draw_color();
+    let previous_blend_mode = canvas.blend_mode();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+    canvas.fill_rect(None).expect("Failed to render overlay");
+    canvas.set_draw_color(previous_color);
+    canvas.set_blend_mode(previous_blend_mode);
+
+    let mut lines = vec![
+        ("컨트롤러 연결이 끊어졌습니다", GAME_RESULT_FONT_SIZE),
+        ("다시 연결하는 중...", DEFAULT_FONT_SIZE),
+    ];
+    if let Some(reason) = reason {
+        lines.push((reason, DEFAULT_FONT_SIZE));
+    }
+
+    let viewport = canvas.viewport();
+    let mut top = viewport.height() as i32 / 2 - GAME_RESULT_FONT_SIZE as i32 * 2;
+    for (text, font_size) in lines {
+        let texture = create_font_texture(
+            &texture_creator,
+            &font,
+            text,
+            font_size,
+            DEFAULT_FONT_OUTLINE_SIZE,
+            DEFAULT_FONT_COLOR,
+            Some(DEFAULT_FONT_OUTLINE_COLOR),
+        )
+        .expect("Font rendering failure");
+        let TextureQuery { width, height, .. } = texture.query();
+        canvas
+            .copy(
+                &texture,
+                None,
+                Some(Rect::new(
+                    (viewport.width() as i32 - width as i32) / 2,
+                    top,
+                    width,
+                    height,
+                )),
+            )
+            .expect("Failed to render text");
+        top += height as i32 + font_size as i32;
+    }
 }
@@ -1,10 +1,11 @@
-use std::time::Instant;
+use std::{path::PathBuf, time::Instant};
 
 use bidrum_hat::BidrumHat;
 use kira::manager::AudioManager;
 use sdl2::{render::Canvas, video::Window, EventPump};
 
-use bidrum_data_struct_lib::janggu::JangguInputState;
+use bidrum_controller_lib::janggu_event::JangguInputEvent;
+use bidrum_data_struct_lib::{calibration::CalibrationProfile, janggu::JangguInputState};
 
 use crate::controller_wrapper::ControllerWrapper;
 
@@ -20,10 +21,18 @@ pub(crate) struct GameCommonContext {
     pub(crate) game_initialized_at: Instant,
     pub(crate) hat: BidrumHat,
     pub(crate) freetype_library: cairo::freetype::Library,
+    /// music library directories
+    pub(crate) music_roots: Vec<PathBuf>,
+    /// timing offsets of the player, measured by beep-boop
+    pub(crate) calibration: CalibrationProfile,
+    pub(crate) calibration_profile_path: PathBuf,
 }
 
 impl GameCommonContext {
     pub(crate) fn read_janggu_state(&self) -> JangguInputState {
         self.coin_and_janggu.read_janggu_state()
     }
+    pub(crate) fn read_janggu_events(&self) -> Vec<JangguInputEvent> {
+        self.coin_and_janggu.read_janggu_events()
+    }
 }
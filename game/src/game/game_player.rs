@@ -3,9 +3,11 @@ pub mod chart_player_ui;
 pub mod effect_sound_player;
 pub mod game_result;
 pub mod janggu_state_with_tick;
+#[cfg(test)]
+mod scripted_play;
 pub mod timing_judge;
 
-use std::{path::Path, thread};
+use std::{path::Path, thread, time::Instant};
 
 use kira::{
     clock::ClockSpeed,
@@ -27,10 +29,24 @@ use self::{
     janggu_state_with_tick::JangguStateWithTick,
 };
 
+use bidrum_controller_lib::janggu_event::JangguInputEvent;
 use bidrum_data_struct_lib::song::GameSong;
 
 use super::render_video::VideoFileRenderer;
 
+/// Tick when the janggu event happened, from the tick of the frame
+///
+/// The tick doesn't go back before the last judge, as the judged notes can't be judged again.
+pub(crate) fn tick_of_janggu_event(
+    event: &JangguInputEvent,
+    now: Instant,
+    tick_now: i128,
+    judged_tick: i128,
+) -> i128 {
+    let event_tick = tick_now - now.duration_since(event.timestamp).as_millis() as i128;
+    event_tick.max(judged_tick).min(tick_now)
+}
+
 pub(crate) fn play_song(
     common_context: &mut game_common_context::GameCommonContext,
     song: &GameSong,
@@ -96,15 +112,21 @@ pub(crate) fn play_song(
         common_context.canvas.present();
     };
 
+    // get judge and create timing judge
+    let chart = match song.get_chart(level) {
+        Ok(chart) => chart,
+        Err(err) => {
+            eprintln!("Failed to load chart: {}", err);
+            return None;
+        }
+    };
+
     // create handle for audio output
     let mut handle = common_context
         .audio_manager
         .play(sound_data)
         .expect("Audio play failure");
 
-    // get judge and create timing judge
-    let chart = song.get_chart(level).unwrap();
-
     // start the clock.
     clock.start().expect("Failed to start clock");
 
@@ -146,10 +168,16 @@ pub(crate) fn play_song(
 
     let mut janggu_state_with_tick = JangguStateWithTick::new();
 
-    let mut chart_player = ChartPlayer::new(chart, &texture_creator);
+    let mut chart_player = ChartPlayer::new(chart, &texture_creator, common_context.calibration);
+
+    // hits before the song are not judged
+    common_context.read_janggu_events();
+    // tick of the last judge, which never goes backward
+    let mut judged_tick: i128 = 0;
 
     'running: loop {
         let tick_now = clock.time().ticks as i128 - start_tick.ticks as i128;
+        let now = Instant::now();
         for event in common_context.event_pump.poll_iter() {
             if event_loop_common(&event) {
                 handle.stop(Tween::default()).expect("Failed to stop song");
@@ -189,22 +217,37 @@ pub(crate) fn play_song(
                 .unwrap();
         }
 
-        // Update janggu state
-        let input_now = common_context.read_janggu_state();
-        janggu_state_with_tick.update(input_now, tick_now);
-
-        effect_sounds.play_janggu_sound(&janggu_state_with_tick, &mut common_context.audio_manager);
+        // Update janggu state with the events, judged at the tick they happened
+        // rather than the tick of the frame
+        for event in common_context.read_janggu_events() {
+            let event_tick = tick_of_janggu_event(&event, now, tick_now, judged_tick);
+            let hit = janggu_state_with_tick.update_with_event(&event, event_tick);
+            effect_sounds
+                .play_janggu_sound(&janggu_state_with_tick, &mut common_context.audio_manager);
+            if event_tick >= 0 {
+                chart_player.judge(
+                    &janggu_state_with_tick,
+                    hit,
+                    common_context.hat.spinning(),
+                    event_tick,
+                );
+                judged_tick = event_tick;
+            }
+        }
         effect_sounds.play_combo_sound(
             &chart_player.game_result(),
             &mut common_context.audio_manager,
         );
         // display notes and accuracy
         if tick_now >= 0 {
+            // judges misses and long notes, as every hit is already judged with its event
             chart_player.judge(
                 &janggu_state_with_tick,
+                None,
                 common_context.hat.spinning(),
                 tick_now,
             );
+            judged_tick = tick_now;
             chart_player.draw(
                 tick_now,
                 &mut common_context.canvas,
@@ -1,6 +1,7 @@
 use bidrum_data_struct_lib::{
-    janggu::JangguFace,
-    song::{GameChart, GameNote},
+    calibration::CalibrationProfile,
+    janggu::{JangguFace, JangguStick},
+    song::{GameChart, GameNote, TempoMap},
 };
 use num_rational::Rational64;
 use sdl2::{render::Canvas, video::Window};
@@ -8,12 +9,16 @@ use sdl2::{render::Canvas, video::Window};
 use crate::constants::{ACCURACY_DISPLAY_DURATION, DEFAULT_BPM};
 
 use crate::game::game_player::{
-    chart_player_ui::displayed_song_note::DisplayedSongNote, timing_judge::NoteAccuracy,
+    chart_player_ui::displayed_song_note::{
+        DisplayedLongNote, DisplayedSongNote, DisplayedStrokeNote,
+    },
+    timing_judge::NoteAccuracy,
 };
 
 use super::{
     chart_player_ui::{
-        disappearing_note_effect::DisapearingNoteEffect, BeatGuideline, ChartPlayerUI,
+        disappearing_note_effect::DisapearingNoteEffect, BeatGuideline, BeatGuidelineLine,
+        ChartPlayerUI,
     },
     game_result::GameResult,
     janggu_state_with_tick::JangguStateWithTick,
@@ -28,32 +33,50 @@ struct ProcessedNote {
 
 pub struct ChartPlayer<'a> {
     chart: GameChart,
+    tempo_map: TempoMap,
     timing_judge: TimingJudge,
     ui: ChartPlayerUI<'a>,
     processed_notes: Vec<ProcessedNote>,
+    /// ids of the long notes whose start is hit
+    started_long_note_ids: Vec<u64>,
     accuracy: Option<(NoteAccuracy, i128)>,
     combo: Option<u64>,
+    /// how far the notes are drawn ahead of the tick in millisecond
+    ///
+    /// The hits along with the screen are judged same as the hits along with the sound,
+    /// as the judge is shifted by the audio offset.
+    display_offset: i64,
 }
 
 impl ChartPlayer<'_> {
     pub fn new(
         chart: GameChart,
         texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        calibration: CalibrationProfile,
     ) -> ChartPlayer {
         ChartPlayer {
             chart: chart.clone(),
-            timing_judge: TimingJudge::new(&chart),
+            tempo_map: chart.tempo_map(),
+            timing_judge: TimingJudge::new(&chart, calibration.audio_offset),
             ui: ChartPlayerUI::new(texture_creator),
             processed_notes: vec![],
+            started_long_note_ids: vec![],
             accuracy: None,
             combo: None,
+            display_offset: calibration.visual_offset - calibration.audio_offset,
         }
     }
 
+    /// tick where the notes are drawn at
+    fn display_tick(&self, tick: i128) -> i128 {
+        tick + self.display_offset as i128
+    }
+
     fn append_disappearing_notes(&mut self, tick: i128, note_ids: Vec<u64>) {
         let left_face = self.chart.left_face.clone();
         let right_face = self.chart.right_face.clone();
-        let faces = [left_face, right_face].concat();
+        let stroke_notes = self.chart.strokes.iter().flat_map(|x| x.notes()).collect();
+        let faces = [left_face, right_face, stroke_notes].concat();
         let disappearing_notes = faces.iter().filter(|i| {
             note_ids.contains(&i.id)
                 && self.processed_notes.iter().any(|j| {
@@ -64,14 +87,38 @@ impl ChartPlayer<'_> {
         });
 
         for i in disappearing_notes {
-            self.ui
-                .disappearing_note_effects
-                .push_note(self.get_display_note(i, tick), tick as i128)
+            self.ui.disappearing_note_effects.push_note(
+                self.get_display_note(i, self.display_tick(tick).max(0)),
+                tick as i128,
+            )
+        }
+
+        // finished long notes disappear on the judgement line
+        let processed_note_ids = self.processed_note_ids();
+        let disappearing_long_notes: Vec<DisplayedSongNote> = self
+            .chart
+            .long_notes
+            .iter()
+            .filter(|i| note_ids.contains(&i.id) && processed_note_ids.contains(&i.id))
+            .map(|i| DisplayedSongNote {
+                face: i.face,
+                stick: i.stick,
+                distance: 0.0,
+            })
+            .collect();
+        for i in disappearing_long_notes {
+            self.ui.disappearing_note_effects.push_note(i, tick)
         }
     }
 
-    pub fn judge(&mut self, janggu: &JangguStateWithTick, spinning: bool, tick: i128) {
-        let new_accuracies = self.timing_judge.judge(janggu, spinning, tick as u64);
+    pub fn judge(
+        &mut self,
+        janggu: &JangguStateWithTick,
+        hit: Option<JangguStick>,
+        spinning: bool,
+        tick: i128,
+    ) {
+        let new_accuracies = self.timing_judge.judge(janggu, hit, spinning, tick as u64);
 
         if !new_accuracies.is_empty() {
             self.accuracy = Some((
@@ -79,6 +126,10 @@ impl ChartPlayer<'_> {
                 tick,
             ));
             for i in new_accuracies.clone() {
+                if !i.finished {
+                    self.started_long_note_ids.push(i.note_id);
+                    continue;
+                }
                 self.processed_notes.push(ProcessedNote {
                     processed_note_id: i.note_id,
                     processed_at_tick: tick,
@@ -104,9 +155,8 @@ impl ChartPlayer<'_> {
             face: note.face,
             stick: note.stick,
             distance: note.get_position(
-                self.chart.bpm,
-                self.chart.delay,
-                self.chart.bpm * DEFAULT_BPM,
+                &self.tempo_map,
+                self.chart.bpm * DEFAULT_BPM as i64,
                 tick as u64,
             ),
         }
@@ -128,9 +178,8 @@ impl ChartPlayer<'_> {
                     face: JangguFace::궁편,
                     stick: i.stick,
                     distance: i.get_position(
-                        self.chart.bpm,
-                        self.chart.delay,
-                        self.chart.bpm * DEFAULT_BPM,
+                        &self.tempo_map,
+                        self.chart.bpm * DEFAULT_BPM as i64,
                         tick_now,
                     ),
                 });
@@ -143,59 +192,120 @@ impl ChartPlayer<'_> {
                     face: JangguFace::열편,
                     stick: i.stick,
                     distance: i.get_position(
-                        self.chart.bpm,
-                        self.chart.delay,
-                        self.chart.bpm * DEFAULT_BPM,
+                        &self.tempo_map,
+                        self.chart.bpm * DEFAULT_BPM as i64,
                         tick_now,
                     ),
                 });
             }
         }
 
+        // stroke notes are drawn as the notes of each stick
+        for i in &self.chart.strokes {
+            if !self.processed_note_ids().contains(&i.id) {
+                for j in i.notes() {
+                    display_notes.push(self.get_display_note(&j, tick_now as i128));
+                }
+            }
+        }
+
+        // draw the start of the long notes as the notes
+        for i in self.get_display_long_notes(tick_now) {
+            display_notes.push(DisplayedSongNote {
+                face: i.face,
+                stick: i.stick,
+                distance: i.distance,
+            });
+        }
+
         display_notes
     }
 
+    fn get_display_strokes(&self, tick_now: u64) -> Vec<DisplayedStrokeNote> {
+        let processed_note_ids = self.processed_note_ids();
+        self.chart
+            .strokes
+            .iter()
+            .filter(|i| !processed_note_ids.contains(&i.id))
+            .map(|i| DisplayedStrokeNote {
+                distance: i.get_position(
+                    &self.tempo_map,
+                    self.chart.bpm * DEFAULT_BPM as i64,
+                    tick_now,
+                ),
+                stroke: i.stroke,
+            })
+            .collect()
+    }
+
+    fn get_display_long_notes(&self, tick_now: u64) -> Vec<DisplayedLongNote> {
+        let processed_note_ids = self.processed_note_ids();
+        let mut display_long_notes = vec![];
+        for i in &self.chart.long_notes {
+            if processed_note_ids.contains(&i.id) {
+                continue;
+            }
+
+            let (mut distance, end_distance) = i.get_positions(
+                &self.tempo_map,
+                self.chart.bpm * DEFAULT_BPM as i64,
+                tick_now,
+            );
+            // started long note stays on the judgement line until the end
+            if self.started_long_note_ids.contains(&i.id) {
+                distance = distance.max(0.0);
+            }
+            display_long_notes.push(DisplayedLongNote {
+                distance: distance,
+                end_distance: end_distance.max(distance),
+                face: i.face,
+                stick: i.stick,
+                kind: i.kind,
+            });
+        }
+
+        display_long_notes
+    }
+
     fn beat_guideline(&self, tick: i128) -> Option<BeatGuideline> {
         if tick < 0 {
             return None;
         }
 
-        // bpm = beat / minute
-        // minute-per-beat = 1 / bpm
-        // timing-in-minute = beat * minute-per-beat
-        // timing-in-millisecond = timing-in-minute (minute) * ( 60000(millisecond) / 1(minute) )
-        // timing = timing-in-millisecond
-        let timing_of_one_beat = Rational64::new(60000, self.chart.bpm as i64);
-
         // beat_per_millisecond = (display_bpm / 60000)
         // millisecond_per_beat = 1/ beat_per_millisecond
         // speed = 1 / millisecond_per_beat
-        let speed_ratio = Rational64::new((self.chart.bpm * DEFAULT_BPM) as i64, 60000);
+        let speed_ratio = self.chart.bpm * DEFAULT_BPM as i64 / 60000;
+
+        // lines farther than this (in unit of the note width) are not visible
+        let max_distance = Rational64::new(40, 1);
 
-        let length_ratio = timing_of_one_beat * speed_ratio;
-        let length = *length_ratio.numer() as f64 / *length_ratio.denom() as f64;
+        // draw the lines on every beat, from the first beat after the current position
+        let mut lines = vec![];
+        let mut beat = self.tempo_map.beat_at(tick as i64).ceil();
+        loop {
+            let timing = self.tempo_map.precise_timing_in_ms(beat);
+            let position_ratio = (timing - Rational64::new(tick as i64, 1)) * speed_ratio;
+            if position_ratio > max_distance {
+                break;
+            }
 
-        let even_beat =
-            ((*timing_of_one_beat.denom() * tick as i64) / *timing_of_one_beat.numer()) % 2 == 0;
+            // emphasize the start of the measures if the chart has time signatures,
+            // otherwise emphasize the even beats
+            let emphasized = if self.chart.time_signatures.is_empty() {
+                beat.to_integer() % 2 == 0
+            } else {
+                self.tempo_map.is_measure_start(beat)
+            };
 
-        let position = {
-            //position_ratio = tick % timing_of_one_beat as i128;
-            let mut position_ratio = Rational64::new(
-                (*timing_of_one_beat.denom() * tick as i64) % *timing_of_one_beat.numer(),
-                *timing_of_one_beat.denom(),
-            );
+            lines.push(BeatGuidelineLine {
+                position: *position_ratio.numer() as f64 / *position_ratio.denom() as f64,
+                emphasized: emphasized,
+            });
+            beat += 1;
+        }
 
-            position_ratio = length_ratio - position_ratio;
-            position_ratio = position_ratio / timing_of_one_beat;
-            position_ratio *= length_ratio;
-            *position_ratio.numer() as f64 / *position_ratio.denom() as f64
-        };
-
-        Some(BeatGuideline {
-            length,
-            position,
-            even_beat,
-        })
+        Some(BeatGuideline { lines: lines })
     }
 
     pub fn draw(
@@ -223,18 +333,21 @@ impl ChartPlayer<'_> {
 
         // draw game play ui
         if tick >= 0 {
+            let display_tick = self.display_tick(tick).max(0);
             self.ui.disappearing_note_effects.update_base_tick(tick);
             self.ui.input_effect.update(janggu_state_with_tick, tick);
-            self.ui.notes = self.get_display_notes(tick as u64);
-            self.ui.beat_guideline = self.beat_guideline(tick);
+            self.ui.notes = self.get_display_notes(display_tick as u64);
+            self.ui.long_notes = self.get_display_long_notes(display_tick as u64);
+            self.ui.strokes = self.get_display_strokes(display_tick as u64);
+            self.ui.beat_guideline = self.beat_guideline(display_tick);
         }
         self.ui.overall_effect_tick = overall_tick;
         self.ui.remaining_hat_ticks = self
             .chart
             .hats
             .iter()
-            .map(|x| x.timing_in_ms(self.chart.bpm, self.chart.delay))
-            .map(|x| x as i128 - tick)
+            .map(|x| x.timing_in_ms(&self.tempo_map))
+            .map(|x| x as i128 - self.display_tick(tick))
             .filter(|x| *x >= -3000)
             .map(|x| x as i64)
             .collect();
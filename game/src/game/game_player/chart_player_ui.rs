@@ -20,23 +20,35 @@ use crate::{
     game::util::create_outlined_font_texture::create_font_texture,
 };
 
-use bidrum_data_struct_lib::janggu::{JangguFace, JangguStick};
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::GameLongNoteKind,
+};
 
 use self::{
-    disappearing_note_effect::DisapearingNoteEffect, displayed_song_note::DisplayedSongNote,
-    input_effect::InputEffect, resources::ChartPlayerUIResources,
+    disappearing_note_effect::DisapearingNoteEffect,
+    displayed_song_note::{DisplayedLongNote, DisplayedSongNote, DisplayedStrokeNote},
+    input_effect::InputEffect,
+    resources::ChartPlayerUIResources,
 };
 
 use super::timing_judge::NoteAccuracy;
 
-pub struct BeatGuideline {
+pub struct BeatGuidelineLine {
+    /// distance from the judgement line in unit of the note width
     pub position: f64,
-    pub length: f64,
-    pub even_beat: bool,
+    /// whether the line is drawn brighter than others (e.g. start of the measure)
+    pub emphasized: bool,
+}
+
+pub struct BeatGuideline {
+    pub lines: Vec<BeatGuidelineLine>,
 }
 
 pub struct ChartPlayerUI<'a> {
     pub notes: Vec<DisplayedSongNote>,
+    pub long_notes: Vec<DisplayedLongNote>,
+    pub strokes: Vec<DisplayedStrokeNote>,
     pub remaining_hat_ticks: Vec<i64>,
     pub accuracy: Option<NoteAccuracy>,
     pub combo: Option<u64>,
@@ -58,6 +70,8 @@ impl ChartPlayerUI<'_> {
     pub fn with_resources(resources: ChartPlayerUIResources) -> ChartPlayerUI {
         return ChartPlayerUI {
             notes: vec![],
+            long_notes: vec![],
+            strokes: vec![],
             remaining_hat_ticks: vec![],
             accuracy: None,
             combo: None,
@@ -229,25 +243,21 @@ impl ChartPlayerUI<'_> {
 
         // draw beat guideline
         if let Some(beat_guideline) = &self.beat_guideline {
-            let mut position = beat_guideline.position % beat_guideline.length;
-            while position < 0.0 {
-                position += beat_guideline.length;
-            }
             let thicknesses: [u32; 2] = [4, 4];
-            let mut white = beat_guideline.even_beat;
-            loop {
-                let distance_between_centers = (position * note_width_max as f64) as i32;
-                let thickness = thicknesses[if white { 0 } else { 1 }];
-                let color = if white {
+            for line in &beat_guideline.lines {
+                let distance_between_centers = (line.position * note_width_max as f64) as i32;
+                let thickness = thicknesses[if line.emphasized { 0 } else { 1 }];
+                let color = if line.emphasized {
                     Color::RGBA(255, 255, 255, 60)
                 } else {
                     Color::RGBA(255, 255, 255, 30)
                 };
 
-                if distance_between_centers
-                    > (background_width + (judgement_line_width + thickness) / 2) as i32
+                if distance_between_centers < 0
+                    || distance_between_centers
+                        > (background_width + (judgement_line_width + thickness) / 2) as i32
                 {
-                    break;
+                    continue;
                 }
                 for line_x in [
                     judgement_line_xposes[0] - distance_between_centers
@@ -266,9 +276,6 @@ impl ChartPlayerUI<'_> {
                         ))
                         .unwrap();
                 }
-
-                position += beat_guideline.length;
-                white = !white;
             }
         }
 
@@ -288,6 +295,96 @@ impl ChartPlayerUI<'_> {
                 .unwrap();
         }
 
+        // draw long notes as bars from the start to the end
+        let long_note_bar_height = max_stick_note_height / 2;
+        let long_note_bar_y = background_y
+            + (background_height_without_border as i32 - long_note_bar_height as i32) / 2;
+        for i in &self.long_notes {
+            let judgement_line_center_x = match i.face {
+                JangguFace::궁편 => judgement_line_xposes[0],
+                JangguFace::열편 => judgement_line_xposes[1],
+            } + (judgement_line_width / 2) as i32;
+
+            // range of the distance (in pixel) which is on the background
+            let (min_distance, max_distance) = match i.face {
+                JangguFace::궁편 => (
+                    judgement_line_center_x - background_width as i32,
+                    judgement_line_center_x,
+                ),
+                JangguFace::열편 => (
+                    background_width as i32 + janggu_width_min as i32 - judgement_line_center_x,
+                    viewport.width() as i32 - judgement_line_center_x,
+                ),
+            };
+            let start = ((i.distance * note_width_max as f64) as i32).max(min_distance);
+            let end = ((i.end_distance * note_width_max as f64) as i32).min(max_distance);
+
+            // roll notes are drawn as dashed bars
+            let segment_length = match i.kind {
+                GameLongNoteKind::Hold => (end - start).max(0),
+                GameLongNoteKind::Roll => (note_width_max as i32 / 4).max(1),
+            };
+            canvas.set_draw_color(match i.stick {
+                JangguStick::궁채 => Color::RGBA(255, 190, 90, 150),
+                JangguStick::열채 => Color::RGBA(90, 190, 255, 150),
+            });
+            let mut segment_start = start;
+            while segment_start < end {
+                let segment_end = (segment_start + segment_length).min(end);
+                let x = match i.face {
+                    JangguFace::궁편 => judgement_line_center_x - segment_end,
+                    JangguFace::열편 => judgement_line_center_x + segment_start,
+                };
+                canvas
+                    .fill_rect(Rect::new(
+                        x,
+                        long_note_bar_y,
+                        (segment_end - segment_start) as u32,
+                        long_note_bar_height,
+                    ))
+                    .unwrap();
+                segment_start = segment_end + segment_length;
+            }
+        }
+
+        // draw stroke notes as frames, so that the notes of the sticks look like one note
+        let stroke_frame_width = note_width_max + 16;
+        let stroke_frame_height = max_stick_note_height + 16;
+        canvas.set_draw_color(Color::RGBA(255, 215, 0, 90));
+        for i in &self.strokes {
+            let faces = i.stroke.faces();
+            for face in [JangguFace::궁편, JangguFace::열편] {
+                if faces.궁채 != Some(face) && faces.열채 != Some(face) {
+                    continue;
+                }
+
+                let distance_between_centers = (i.distance * note_width_max as f64) as i32;
+                let center_x = match face {
+                    JangguFace::궁편 => judgement_line_xposes[0] - distance_between_centers,
+                    JangguFace::열편 => judgement_line_xposes[1] + distance_between_centers,
+                } + (judgement_line_width / 2) as i32;
+
+                // Do not render frame if the frame is on janggu icon
+                if (center_x - (viewport.width() / 2) as i32).unsigned_abs()
+                    <= (janggu_width_min + stroke_frame_width) / 2
+                {
+                    continue;
+                }
+
+                canvas
+                    .fill_rect(Rect::new(
+                        center_x - (stroke_frame_width / 2) as i32,
+                        background_y
+                            + (background_height_without_border as i32
+                                - stroke_frame_height as i32)
+                                / 2,
+                        stroke_frame_width,
+                        stroke_frame_height,
+                    ))
+                    .unwrap();
+            }
+        }
+
         // draw note
         let mut draw_note = |i: &DisplayedSongNote, disappearing_effect: Option<f32>| {
             let note_texture = match i.stick {
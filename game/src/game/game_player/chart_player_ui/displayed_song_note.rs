@@ -1,4 +1,7 @@
-use bidrum_data_struct_lib::janggu::{JangguFace, JangguStick};
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick, JangguStroke},
+    song::GameLongNoteKind,
+};
 
 #[derive(Clone)]
 pub struct DisplayedSongNote {
@@ -6,3 +9,22 @@ pub struct DisplayedSongNote {
     pub face: JangguFace,
     pub stick: JangguStick,
 }
+
+/// Hold or roll note, which is drawn as a bar from the start to the end
+#[derive(Clone)]
+pub struct DisplayedLongNote {
+    /// distance of the start of the note
+    pub distance: f64,
+    /// distance of the end of the note
+    pub end_distance: f64,
+    pub face: JangguFace,
+    pub stick: JangguStick,
+    pub kind: GameLongNoteKind,
+}
+
+/// Stroke note, which is drawn as a frame around the notes of the sticks
+#[derive(Clone)]
+pub struct DisplayedStrokeNote {
+    pub distance: f64,
+    pub stroke: JangguStroke,
+}
@@ -1,3 +1,4 @@
+use bidrum_controller_lib::janggu_event::{JangguEdge, JangguInputEvent};
 use bidrum_data_struct_lib::janggu::JangguInputState;
 use bidrum_data_struct_lib::janggu::{JangguFace, JangguStick};
 
@@ -98,4 +99,36 @@ impl JangguStateWithTick {
             }
         };
     }
+
+    /// Applies a janggu input event which happened at the time
+    ///
+    /// Only the stick of the event can be keydown now, as the events are processed one by one.
+    /// Returns the stick which started to touch the face by the event, which is the hit to be judged.
+    pub(crate) fn update_with_event(
+        &mut self,
+        event: &JangguInputEvent,
+        time: i128,
+    ) -> Option<JangguStick> {
+        self.궁채 = self.궁채.toggle_keydown(false);
+        self.열채 = self.열채.toggle_keydown(false);
+
+        let stick_state = match event.stick {
+            JangguStick::궁채 => &mut self.궁채,
+            JangguStick::열채 => &mut self.열채,
+        };
+        *stick_state = match event.edge {
+            JangguEdge::Down => stick_state
+                .toggle_keydown(true)
+                .change_keydown_timing_and_face(time, Some(event.face)),
+            JangguEdge::Up if stick_state.face == Some(event.face) => {
+                stick_state.change_keydown_timing_and_face(time, None)
+            }
+            JangguEdge::Up => *stick_state,
+        };
+
+        match event.edge {
+            JangguEdge::Down => Some(event.stick),
+            JangguEdge::Up => None,
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! Plays the charts with the scripted janggu, to test the judge without the controller
+use bidrum_controller_lib::{
+    janggu_event::{JangguEdge, JangguInputEvent},
+    scripted::{self, InputScript, ScriptClock},
+    JangguDevice,
+};
+use bidrum_data_struct_lib::{
+    janggu::{JangguFace, JangguStick},
+    song::{GameChart, GameHatNote, GameLongNote, GameLongNoteKind},
+};
+
+use crate::constants::{HAT_SCORE, HAT_TIMING};
+
+use super::{
+    game_result::GameResult, janggu_state_with_tick::JangguStateWithTick, tick_of_janggu_event,
+    timing_judge::TimingJudge,
+};
+
+/// Length of a frame in millisecond (60fps)
+const FRAME_LENGTH: i128 = 16;
+
+/// Judges the script like `play_song` does, frame by frame until the length
+fn play_script(
+    chart: &GameChart,
+    script: InputScript,
+    audio_offset: i64,
+    length_in_ms: i128,
+) -> GameResult {
+    let clock = ScriptClock::manual();
+    let (janggu_device, _) = scripted::new(script, clock.clone());
+    let mut timing_judge = TimingJudge::new(chart, audio_offset);
+    let mut janggu_state_with_tick = JangguStateWithTick::new();
+    let mut judged_tick: i128 = 0;
+
+    let mut tick_now = 0;
+    while tick_now <= length_in_ms {
+        clock.set_time(tick_now as u64);
+        let now = clock.instant_at(tick_now as u64);
+        for event in janggu_device.read_janggu_events() {
+            let event_tick = tick_of_janggu_event(&event, now, tick_now, judged_tick);
+            let hit = janggu_state_with_tick.update_with_event(&event, event_tick);
+            timing_judge.judge(&janggu_state_with_tick, hit, false, event_tick as u64);
+            judged_tick = event_tick;
+        }
+
+        timing_judge.judge(&janggu_state_with_tick, None, false, tick_now as u64);
+        judged_tick = tick_now;
+        tick_now += FRAME_LENGTH;
+    }
+
+    timing_judge.get_game_result()
+}
+
+/// 4 notes of 궁채 on 궁편 at 500, 1000, 1500 and 2000ms
+fn chart() -> GameChart {
+    GameChart::create_example_chart_for_tutorial(JangguStick::궁채, JangguFace::궁편, 4, 1, 120)
+}
+
+/// Hits 궁편 with 궁채 at the timings
+fn hits(timings: &[u64]) -> InputScript {
+    timings.iter().fold(InputScript::new(), |script, timing| {
+        script.hit(*timing, JangguStick::궁채, JangguFace::궁편, 50)
+    })
+}
+
+#[test]
+fn judges_hits_on_time() {
+    let result = play_script(&chart(), hits(&[500, 1000, 1500, 2000]), 0, 3000);
+
+    assert_eq!(result.overchaos_count, 4);
+    assert_eq!(result.miss_count, 0);
+    assert_eq!(result.combo, 4);
+}
+
+#[test]
+fn judges_hits_between_frames_at_their_timing() {
+    // the frames are at 496 and 512, which are more than 10ms from 503
+    let result = play_script(&chart(), hits(&[503, 1005, 1493, 2008]), 0, 3000);
+
+    assert_eq!(result.overchaos_count, 4);
+}
+
+#[test]
+fn judges_hits_shorter_than_frame() {
+    let script = InputScript::new()
+        .hit(500, JangguStick::궁채, JangguFace::궁편, 2)
+        .hit(1000, JangguStick::궁채, JangguFace::궁편, 2);
+    let result = play_script(&chart(), script, 0, 3000);
+
+    assert_eq!(result.overchaos_count, 2);
+    assert_eq!(result.miss_count, 2);
+}
+
+#[test]
+fn misses_notes_without_hits() {
+    let result = play_script(&chart(), InputScript::new(), 0, 3000);
+
+    assert_eq!(result.miss_count, 4);
+    assert_eq!(result.combo, 0);
+}
+
+#[test]
+fn judges_hit_once_while_stick_is_kept_down() {
+    // the next note is in the judge window while the stick is still on the face
+    let script = InputScript::new().hit(500, JangguStick::궁채, JangguFace::궁편, 300);
+    let result = play_script(&chart(), script, 0, 3000);
+
+    assert_eq!(result.overchaos_count, 1);
+    assert_eq!(result.bad_count, 0);
+    assert_eq!(result.miss_count, 3);
+}
+
+#[test]
+fn misses_notes_hit_on_wrong_face() {
+    let script = InputScript::new()
+        .hit(500, JangguStick::궁채, JangguFace::열편, 50)
+        .hit(1000, JangguStick::열채, JangguFace::궁편, 50);
+    let result = play_script(&chart(), script, 0, 3000);
+
+    assert_eq!(result.total_judged_note_count(), 4);
+    assert_eq!(result.miss_count, 4);
+}
+
+#[test]
+fn applies_audio_offset_to_judge() {
+    let late_hits = hits(&[530, 1030, 1530, 2030]);
+
+    let result = play_script(&chart(), late_hits.clone(), 30, 3000);
+    assert_eq!(result.overchaos_count, 4);
+
+    let result = play_script(&chart(), late_hits, 0, 3000);
+    assert_eq!(result.perfect_count, 4);
+}
+
+#[test]
+fn applies_audio_offset_to_hat_notes() {
+    // hat note at 2000ms
+    let mut chart = chart();
+    chart.hats.push(GameHatNote::create_raw_note(4, 0, 0));
+    chart.assign_note_ids();
+    let janggu_state = JangguStateWithTick::new();
+    let mut timing_judge = TimingJudge::new(&chart, 300);
+
+    // too early for the hat note which is judged at 2300ms
+    timing_judge.judge(&janggu_state, None, true, (2000 - HAT_TIMING + 100) as u64);
+    assert_eq!(timing_judge.get_game_result().score, 0);
+
+    timing_judge.judge(&janggu_state, None, true, (2000 + HAT_TIMING + 200) as u64);
+    assert_eq!(timing_judge.get_game_result().score, HAT_SCORE);
+}
+
+#[test]
+fn counts_long_note_once() {
+    // hold note of 열채 on 열편 from 500 to 1500ms
+    let mut chart = chart();
+    chart.long_notes.push(GameLongNote::create_raw_long_note(
+        GameLongNoteKind::Hold,
+        JangguStick::열채,
+        JangguFace::열편,
+        1,
+        0,
+        0,
+        3,
+        0,
+        0,
+    ));
+    chart.assign_note_ids();
+    // 열채 is kept on 열편 while 궁채 hits the notes
+    let script =
+        InputScript::parse("500 dk\n550 k\n1000 dk\n1050 k\n1500 dk\n1550 -\n2000 d\n2050 -")
+            .expect("invalid script");
+    let result = play_script(&chart, script, 0, 3000);
+
+    assert_eq!(result.overchaos_count, 5);
+    assert_eq!(result.total_judged_note_count(), 5);
+    assert_eq!(result.combo, 5);
+    assert_eq!(result.score, 5000);
+}
+
+#[test]
+fn event_tick_is_between_judged_tick_and_now() {
+    let clock = ScriptClock::manual();
+    let now = clock.instant_at(1000);
+    let tick_of = |time: u64, judged_tick: i128| {
+        let event = JangguInputEvent {
+            stick: JangguStick::궁채,
+            face: JangguFace::궁편,
+            edge: JangguEdge::Down,
+            timestamp: clock.instant_at(time),
+        };
+        tick_of_janggu_event(&event, now, 1000, judged_tick)
+    };
+
+    assert_eq!(tick_of(970, 950), 970);
+    assert_eq!(tick_of(1000, 950), 1000);
+    // the judged tick is included, but the earlier ticks are not
+    assert_eq!(tick_of(950, 950), 950);
+    assert_eq!(tick_of(900, 950), 950);
+    // the event can't happen after the frame
+    assert_eq!(tick_of(1010, 950), 1000);
+    // nothing is left to judge in the frame
+    assert_eq!(tick_of(970, 1000), 1000);
+}
@@ -5,7 +5,7 @@ use bidrum_data_struct_lib::{janggu::JangguStick, song::GameChart};
 use self::hat_timing_judge::HatTimingJudge;
 
 use super::{game_result::GameResult, janggu_state_with_tick::JangguStateWithTick};
-use bidrum_data_struct_lib::song::GameNote;
+use bidrum_data_struct_lib::song::{GameLongNote, GameLongNoteKind, GameNote};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum NoteAccuracy {
@@ -37,15 +37,29 @@ use crate::constants::{BAD_TIMING, GOOD_TIMING, GREAT_TIMING, OVERCHAOS_TIMING,
 // Combination of GameNoteTrack and GameNote
 struct NoteForProcessing {
     note: GameNote,
-    bpm: u32,
-    delay: u64,
+    /// precise timing of the note, calculated with the tempo map of the chart
+    timing_in_ms: u64,
     id: u64,
     hit_timing: Option<u64>,
+    /// whether the note is a part of the stroke note, which is judged with the other parts
+    stroke: bool,
+}
+
+// Hold or roll note which is being processed
+struct LongNoteForProcessing {
+    note: GameLongNote,
+    timing_in_ms: u64,
+    end_timing_in_ms: u64,
+    /// time difference of the first hit, if the note is started
+    start_difference_abs: Option<i64>,
+    /// number of hits of the roll note, including the first hit
+    hit_count: u32,
 }
 
 /// Judges timing accuracy
 pub(crate) struct TimingJudge {
     notes: Vec<NoteForProcessing>,
+    long_notes: Vec<LongNoteForProcessing>,
     hat_judge: HatTimingJudge,
     overchaos_count: u64,
     perfect_count: u64,
@@ -64,6 +78,15 @@ pub(crate) struct TimingJudge {
 pub(crate) struct JudgeResult {
     pub accuracy: NoteAccuracy,
     pub note_id: u64,
+    /// false if the note is still being processed (e.g. the start of the long notes)
+    pub finished: bool,
+}
+
+/// calculte score by the accuracy
+fn score_from_time_difference(difference_abs: i64) -> u64 {
+    ((f64::abs(BAD_TIMING as f64 - difference_abs.clamp(OVERCHAOS_TIMING, BAD_TIMING) as f64)
+        / (BAD_TIMING - OVERCHAOS_TIMING) as f64)
+        * 1000.0) as u64
 }
 
 fn note_accuracy_from_time_difference(difference_abs: i64) -> NoteAccuracy {
@@ -84,39 +107,67 @@ fn note_accuracy_from_time_difference(difference_abs: i64) -> NoteAccuracy {
 
 impl TimingJudge {
     /// Creates new TimingJudge with collection of notes
-    pub fn new(chart: &GameChart) -> TimingJudge {
+    ///
+    /// # Arguments
+    ///   * `audio_offset`: how late the hits are to the sound in millisecond,
+    ///     which is added to the timings of the janggu notes and the hat notes
+    pub fn new(chart: &GameChart, audio_offset: i64) -> TimingJudge {
         // flattens GameNote and GameNoteTrack into NoteForProcessing
+        let tempo_map = chart.tempo_map();
+        let judged_timing = |timing: u64| (timing as i64 + audio_offset).max(0) as u64;
         let mut notes = Vec::<NoteForProcessing>::new();
         for j in &chart.left_face {
             notes.push(NoteForProcessing {
                 note: j.clone(),
-                bpm: chart.bpm,
-                delay: chart.delay,
+                timing_in_ms: judged_timing(j.timing_in_ms(&tempo_map)),
                 id: j.id,
                 hit_timing: None,
+                stroke: false,
             });
         }
         for j in &chart.right_face {
             notes.push(NoteForProcessing {
                 note: j.clone(),
-                bpm: chart.bpm,
-                delay: chart.delay,
+                timing_in_ms: judged_timing(j.timing_in_ms(&tempo_map)),
                 id: j.id,
                 hit_timing: None,
+                stroke: false,
             });
         }
 
+        for j in &chart.strokes {
+            // each stick of the stroke note is processed separately, and judged together
+            for k in j.notes() {
+                notes.push(NoteForProcessing {
+                    timing_in_ms: judged_timing(k.timing_in_ms(&tempo_map)),
+                    id: k.id,
+                    note: k,
+                    hit_timing: None,
+                    stroke: true,
+                });
+            }
+        }
+
         // sort the notes by their precise timings
-        notes.sort_by(|a, b| {
-            a.note
-                .timing_in_ms(a.bpm, a.delay)
-                .cmp(&b.note.timing_in_ms(b.bpm, b.delay))
-        });
+        notes.sort_by(|a, b| a.timing_in_ms.cmp(&b.timing_in_ms));
+
+        let mut long_notes = Vec::<LongNoteForProcessing>::new();
+        for j in &chart.long_notes {
+            long_notes.push(LongNoteForProcessing {
+                note: j.clone(),
+                timing_in_ms: judged_timing(j.timing_in_ms(&tempo_map)),
+                end_timing_in_ms: judged_timing(j.end_timing_in_ms(&tempo_map)),
+                start_difference_abs: None,
+                hit_count: 0,
+            });
+        }
+        long_notes.sort_by(|a, b| a.timing_in_ms.cmp(&b.timing_in_ms));
 
-        let hat_judge = HatTimingJudge::new(chart);
+        let hat_judge = HatTimingJudge::new(chart, audio_offset);
 
         return TimingJudge {
             notes: notes,
+            long_notes: long_notes,
             overchaos_count: 0,
             perfect_count: 0,
             great_count: 0,
@@ -138,14 +189,17 @@ impl TimingJudge {
     ///
     /// # Arguments
     ///   * `keydown`: the current janggu sate
+    ///   * `hit`: the stick which started to touch the face by the janggu event,
+    ///     or None when only the misses and the long notes are judged
     ///   * `tick_in_milliseconds` : the current time position of the song
     pub fn judge(
         &mut self,
         keydown: &JangguStateWithTick,
+        hit: Option<JangguStick>,
         spinning: bool,
         tick_in_milliseconds: u64,
     ) -> Vec<JudgeResult> {
-        let mut judged_notes = vec![];
+        let mut judged_notes: Vec<JudgeResult> = vec![];
 
         // process hat notes first
         let hat_judge_result = self.hat_judge.judge(spinning, tick_in_milliseconds);
@@ -165,15 +219,19 @@ impl TimingJudge {
                 break;
             }
 
-            let precise_timing = i.note.timing_in_ms(i.bpm.into(), i.delay);
+            let precise_timing = i.timing_in_ms;
             let difference = tick_in_milliseconds as i64 - precise_timing as i64;
 
             // judge the miss
+            // (the stroke note is missed once even if both sticks missed it)
             if difference > (BAD_TIMING) {
-                judged_notes.push(JudgeResult {
-                    note_id: i.id,
-                    accuracy: NoteAccuracy::Miss,
-                });
+                if !judged_notes.iter().any(|x| x.note_id == i.id) {
+                    judged_notes.push(JudgeResult {
+                        note_id: i.id,
+                        accuracy: NoteAccuracy::Miss,
+                        finished: true,
+                    });
+                }
                 continue;
             }
 
@@ -187,7 +245,8 @@ impl TimingJudge {
                 JangguStick::궁채 => keydown.궁채,
                 JangguStick::열채 => keydown.열채,
             };
-            i.hit_timing = if keydown.get_by_stick(i.note.stick).is_keydown_now
+            i.hit_timing = if i.hit_timing.is_none()
+                && hit == Some(i.note.stick)
                 && keydown_data.face.is_some_and(|x| x == i.note.face)
                 && !(match i.note.stick {
                     JangguStick::궁채 => processed_left_stick,
@@ -207,20 +266,128 @@ impl TimingJudge {
             };
 
             // if it's processable note, calculate accuracy
-            if let Some(hit_timing) = i.hit_timing {
+            // (stroke notes are judged after all the sticks are processed)
+            if let Some(hit_timing) = i.hit_timing.filter(|_| !i.stroke) {
                 let difference_abs = (hit_timing as i64 - precise_timing as i64).abs();
 
-                // calculte score by the accuracy
-                self.score += ((f64::abs(
-                    BAD_TIMING as f64 - difference_abs.clamp(OVERCHAOS_TIMING, BAD_TIMING) as f64,
-                ) / (BAD_TIMING - OVERCHAOS_TIMING) as f64)
-                    * 1000.0) as u64;
+                self.score += score_from_time_difference(difference_abs);
 
                 let note_accuracy = note_accuracy_from_time_difference(difference_abs);
 
                 judged_notes.push(JudgeResult {
                     note_id: i.id,
                     accuracy: note_accuracy,
+                    finished: true,
+                });
+            }
+        }
+
+        // judge the stroke notes whose all sticks hit the faces
+        // the accuracy is decided by the less accurate stick
+        for i in &self.notes {
+            if !i.stroke || judged_notes.iter().any(|x| x.note_id == i.id) {
+                continue;
+            }
+
+            let parts = self.notes.iter().filter(|x| x.id == i.id);
+            if parts.clone().all(|x| x.hit_timing.is_some()) {
+                let difference_abs = parts
+                    .map(|x| (x.hit_timing.unwrap() as i64 - x.timing_in_ms as i64).abs())
+                    .max()
+                    .unwrap();
+                self.score += score_from_time_difference(difference_abs);
+
+                judged_notes.push(JudgeResult {
+                    note_id: i.id,
+                    accuracy: note_accuracy_from_time_difference(difference_abs),
+                    finished: true,
+                });
+            }
+        }
+
+        // process long notes with the sticks which are not used by the notes above
+        for i in &mut self.long_notes {
+            let keydown_data = keydown.get_by_stick(i.note.stick);
+            let processed_stick = match i.note.stick {
+                JangguStick::궁채 => &mut processed_left_stick,
+                JangguStick::열채 => &mut processed_right_stick,
+            };
+            let hit_now = hit == Some(i.note.stick)
+                && keydown_data.face.is_some_and(|x| x == i.note.face)
+                && !*processed_stick;
+
+            let start_difference_abs = match i.start_difference_abs {
+                Some(start_difference_abs) => start_difference_abs,
+                None => {
+                    // judge the start of the note like the other notes
+                    let difference = tick_in_milliseconds as i64 - i.timing_in_ms as i64;
+                    if difference > BAD_TIMING {
+                        judged_notes.push(JudgeResult {
+                            note_id: i.note.id,
+                            accuracy: NoteAccuracy::Miss,
+                            finished: true,
+                        });
+                    } else if difference >= -BAD_TIMING && hit_now {
+                        *processed_stick = true;
+                        // the score is added once when the note is finished
+                        let difference_abs =
+                            (keydown_data.keydown_timing as i64 - i.timing_in_ms as i64).abs();
+
+                        i.start_difference_abs = Some(difference_abs);
+                        i.hit_count = 1;
+                        judged_notes.push(JudgeResult {
+                            note_id: i.note.id,
+                            accuracy: note_accuracy_from_time_difference(difference_abs),
+                            finished: false,
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            let end_difference = tick_in_milliseconds as i64 - i.end_timing_in_ms as i64;
+            let end_accuracy = match i.note.kind {
+                GameLongNoteKind::Hold => {
+                    // the stick should be kept on the face until the end
+                    let holding = keydown_data.face.is_some_and(|x| x == i.note.face);
+                    if end_difference >= 0 || (!holding && end_difference >= -BAD_TIMING) {
+                        Some(note_accuracy_from_time_difference(start_difference_abs))
+                    } else if !holding {
+                        Some(NoteAccuracy::Miss)
+                    } else {
+                        None
+                    }
+                }
+                GameLongNoteKind::Roll => {
+                    if hit_now && end_difference <= BAD_TIMING {
+                        *processed_stick = true;
+                        i.hit_count += 1;
+                    }
+
+                    let required_hit_count = i.note.required_hit_count();
+                    if i.hit_count >= required_hit_count && end_difference >= 0 {
+                        Some(note_accuracy_from_time_difference(start_difference_abs))
+                    } else if end_difference > BAD_TIMING {
+                        // half of the hits are regarded as bad
+                        if i.hit_count * 2 >= required_hit_count {
+                            Some(NoteAccuracy::Bad)
+                        } else {
+                            Some(NoteAccuracy::Miss)
+                        }
+                    } else {
+                        None
+                    }
+                }
+            };
+
+            if let Some(end_accuracy) = end_accuracy {
+                if !matches!(end_accuracy, NoteAccuracy::Miss) {
+                    self.score += score_from_time_difference(start_difference_abs);
+                }
+                judged_notes.push(JudgeResult {
+                    note_id: i.note.id,
+                    accuracy: end_accuracy,
+                    finished: true,
                 });
             }
         }
@@ -228,8 +395,13 @@ impl TimingJudge {
         // process combo and delete judged notes
         for i in &judged_notes {
             // delete judged note
-            self.notes
-                .remove(self.notes.iter().position(|x| x.id == i.note_id).unwrap());
+            // (stroke notes have several parts with the same id)
+            self.notes.retain(|x| x.id != i.note_id);
+            if !i.finished {
+                // the long note is counted once when it's finished
+                continue;
+            }
+            self.long_notes.retain(|x| x.note.id != i.note_id);
 
             let is_health_zero = self.health == 0;
             // increase or set combo and count
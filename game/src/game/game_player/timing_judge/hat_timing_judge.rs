@@ -1,4 +1,4 @@
-use bidrum_data_struct_lib::song::{GameChart, GameHatNote};
+use bidrum_data_struct_lib::song::GameChart;
 
 use crate::constants::HAT_TIMING;
 
@@ -6,9 +6,8 @@ use super::NoteAccuracy;
 
 // Combination of GameNoteTrack and GameNote
 struct HatNoteForProcessing {
-    note: GameHatNote,
-    bpm: u32,
-    delay: u64,
+    /// precise timing of the note, calculated with the tempo map of the chart
+    timing_in_ms: u64,
     id: u64,
 }
 
@@ -25,24 +24,23 @@ pub(super) struct HatJudgeResult {
 
 impl HatTimingJudge {
     /// Creates new TimingJudge with collection of notes
-    pub fn new(chart: &GameChart) -> HatTimingJudge {
+    ///
+    /// # Arguments
+    ///   * `audio_offset`: how late the hits are to the sound in millisecond,
+    ///     which is added to the timings of the hat notes as the janggu notes
+    pub fn new(chart: &GameChart, audio_offset: i64) -> HatTimingJudge {
         // flattens GameNote and GameNoteTrack into NoteForProcessing
+        let tempo_map = chart.tempo_map();
         let mut notes = Vec::<HatNoteForProcessing>::new();
         for j in &chart.hats {
             notes.push(HatNoteForProcessing {
-                note: j.clone(),
-                bpm: chart.bpm,
-                delay: chart.delay,
+                timing_in_ms: (j.timing_in_ms(&tempo_map) as i64 + audio_offset).max(0) as u64,
                 id: j.id,
             });
         }
 
         // sort the notes by their precise timings
-        notes.sort_by(|a, b| {
-            a.note
-                .timing_in_ms(a.bpm, a.delay)
-                .cmp(&b.note.timing_in_ms(b.bpm, b.delay))
-        });
+        notes.sort_by(|a, b| a.timing_in_ms.cmp(&b.timing_in_ms));
 
         return HatTimingJudge { notes: notes };
     }
@@ -59,7 +57,7 @@ impl HatTimingJudge {
 
         // if sticks are not keydown, there's no need to process the stick
         for i in &mut self.notes {
-            let precise_timing = i.note.timing_in_ms(i.bpm.into(), i.delay);
+            let precise_timing = i.timing_in_ms;
             let difference = tick_in_milliseconds as i64 - precise_timing as i64;
 
             // judge the miss
@@ -1,11 +1,15 @@
-use std::time::Instant;
+use std::{path::PathBuf, time::Instant};
 
+use bidrum_data_struct_lib::calibration::CalibrationProfile;
 use bidrum_hat::BidrumHat;
 use kira::manager::{backend::DefaultBackend, AudioManager, AudioManagerSettings};
 
 use crate::controller_wrapper::ControllerWrapper;
 
-use super::{game_common_context::GameCommonContext, start::start_game, title::render_title};
+use super::{
+    game_common_context::GameCommonContext, operator_menu::operator_menu, start::start_game,
+    title::render_title,
+};
 
 pub struct InitGameOptions {
     pub width: Option<u32>,
@@ -13,6 +17,11 @@ pub struct InitGameOptions {
     pub fullscreen: bool,
     pub vsync: bool,
     pub price: u32,
+    /// directories where the songs are loaded from
+    pub music_roots: Vec<PathBuf>,
+    pub calibration: CalibrationProfile,
+    /// where the operator menu saves the calibration
+    pub calibration_profile_path: PathBuf,
 }
 
 pub(crate) fn init_game(controller_wrapper: ControllerWrapper, options: InitGameOptions) {
@@ -110,6 +119,9 @@ pub(crate) fn init_game(controller_wrapper: ControllerWrapper, options: InitGame
         game_initialized_at: Instant::now(),
         hat: hat,
         freetype_library: freetype_library,
+        music_roots: options.music_roots,
+        calibration: options.calibration,
+        calibration_profile_path: options.calibration_profile_path,
     };
 
     // enter game loop
@@ -122,6 +134,9 @@ pub(crate) fn init_game(controller_wrapper: ControllerWrapper, options: InitGame
             super::title::TitleResult::StartGame => {
                 start_game(&mut context);
             }
+            super::title::TitleResult::OperatorMenu => {
+                operator_menu(&mut context);
+            }
         }
     }
 }
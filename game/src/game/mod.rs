@@ -3,6 +3,7 @@ pub mod display_result;
 pub mod game_common_context;
 pub mod game_player;
 pub mod init;
+pub mod operator_menu;
 pub mod render_video;
 pub mod select_song;
 pub mod start;
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use sdl2::{
+    event::Event,
+    keyboard::{Keycode, Mod},
+    pixels::Color,
+    rect::Rect,
+};
+
+use crate::constants::{
+    DEFAULT_FONT_COLOR, DEFAULT_FONT_OUTLINE_COLOR, DEFAULT_FONT_OUTLINE_SIZE,
+    DEFAULT_FONT_PATH as FONT_PATH, GAME_RESULT_FONT_SIZE, OPERATOR_MENU_SELECTED_FONT_COLOR,
+};
+
+use super::{
+    game_common_context::GameCommonContext, util::create_outlined_font_texture::create_font_texture,
+};
+
+/// Offsets farther than this are not allowed, in millisecond
+const MAX_OFFSET: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuItem {
+    /// offset of the judge, which is the audio offset of the calibration profile
+    JudgeOffset,
+    VisualOffset,
+}
+
+const MENU_ITEMS: [MenuItem; 2] = [MenuItem::JudgeOffset, MenuItem::VisualOffset];
+
+fn render_menu(common_context: &mut GameCommonContext, lines: &[(String, bool)]) {
+    let texture_creator = common_context.canvas.texture_creator();
+    let font = common_context
+        .freetype_library
+        .new_face(
+            FONT_PATH.to_owned() + "/sans.ttf",
+            262144, /* Regular */
+        )
+        .expect("Failed to load sans");
+
+    common_context.canvas.set_draw_color(Color::BLACK);
+    common_context.canvas.clear();
+
+    let line_height = GAME_RESULT_FONT_SIZE as i32 * 2;
+    let viewport = common_context.canvas.viewport();
+    let top = (viewport.height() as i32 - line_height * lines.len() as i32) / 2;
+    for (idx, (text, selected)) in lines.iter().enumerate() {
+        if text.is_empty() {
+            continue;
+        }
+
+        let texture = create_font_texture(
+            &texture_creator,
+            &font,
+            text,
+            GAME_RESULT_FONT_SIZE,
+            DEFAULT_FONT_OUTLINE_SIZE,
+            if *selected {
+                OPERATOR_MENU_SELECTED_FONT_COLOR
+            } else {
+                DEFAULT_FONT_COLOR
+            },
+            Some(DEFAULT_FONT_OUTLINE_COLOR),
+        )
+        .expect("Font rendering failure");
+        common_context
+            .canvas
+            .copy(
+                &texture,
+                None,
+                Some(Rect::new(
+                    (viewport.width() - texture.query().width) as i32 / 2,
+                    top + line_height * idx as i32,
+                    texture.query().width,
+                    texture.query().height,
+                )),
+            )
+            .expect("Failed to render text");
+    }
+}
+
+/// Menu for the operator to set the judge offset and the visual offset of the cabinet
+///
+/// The offsets are saved into the calibration profile, and applied from the next play.
+pub(crate) fn operator_menu(common_context: &mut GameCommonContext) {
+    let mut calibration = common_context.calibration;
+    let mut selected = 0;
+    loop {
+        for event in common_context.event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => return,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    ..
+                } => {
+                    let step = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        10
+                    } else {
+                        1
+                    };
+                    let offset = match MENU_ITEMS[selected] {
+                        MenuItem::JudgeOffset => &mut calibration.audio_offset,
+                        MenuItem::VisualOffset => &mut calibration.visual_offset,
+                    };
+                    match keycode {
+                        // leave without saving
+                        Keycode::Escape => return,
+                        Keycode::Return => {
+                            common_context.calibration = calibration;
+                            if let Err(err) =
+                                calibration.save(&common_context.calibration_profile_path)
+                            {
+                                eprintln!("{}", err);
+                            }
+                            return;
+                        }
+                        Keycode::Up => {
+                            selected = (selected + MENU_ITEMS.len() - 1) % MENU_ITEMS.len()
+                        }
+                        Keycode::Down => selected = (selected + 1) % MENU_ITEMS.len(),
+                        Keycode::Left => *offset = (*offset - step).max(-MAX_OFFSET),
+                        Keycode::Right => *offset = (*offset + step).min(MAX_OFFSET),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut lines = vec![("운영자 설정".to_string(), false), (String::new(), false)];
+        for (idx, item) in MENU_ITEMS.iter().enumerate() {
+            let (name, offset) = match item {
+                MenuItem::JudgeOffset => ("판정 오프셋", calibration.audio_offset),
+                MenuItem::VisualOffset => ("화면 오프셋", calibration.visual_offset),
+            };
+            lines.push((format!("{}: {:+} ms", name, offset), idx == selected));
+        }
+        lines.push((String::new(), false));
+        lines.push(("↑↓: 선택  ←→: 1ms 조절 (Shift: 10ms)".to_string(), false));
+        lines.push(("Enter: 저장  Esc: 취소".to_string(), false));
+
+        render_menu(common_context, &lines);
+        common_context.canvas.present();
+        std::thread::sleep(Duration::from_millis(3));
+    }
+}
@@ -2,7 +2,7 @@ use std::time::Duration;
 use std::{path::Path, time::Instant};
 
 use bidrum_data_struct_lib::janggu::JangguFace;
-use bidrum_data_struct_lib::song::GameSong;
+use bidrum_data_struct_lib::song::{ChartSummary, GameSong};
 use sdl2::{image::LoadTexture, rect::Rect, render::Texture};
 
 use crate::constants::DEFAULT_FONT_PATH as FONT_PATH;
@@ -27,7 +27,12 @@ struct SongSelectionItem<'a> {
     pub title: String,
     pub artist: String,
     pub cover_img_texture: Texture<'a>,
+    /// levels of the charts, from the easiest one
     pub levels: Vec<u32>,
+    /// summaries of the charts of `levels`, which are displayed when selecting the level
+    pub chart_summary_texts: Vec<Option<String>>,
+    /// range of the levels which is displayed under the artist (e.g. `Lv.1~5 | 채보 3개`)
+    pub level_range_text: String,
 }
 
 pub(crate) struct SongSelectionResult {
@@ -53,18 +58,40 @@ pub(crate) fn select_song(
     let font_path = &(FONT_PATH.to_owned() + "/sans.ttf");
     let title_font_size = 30;
     let artist_font_size = 20;
+    let summary_font_size = 16;
 
     // convert GameSong vector to SongSelectionItem vector
     let song_selection_items = {
         let mut song_selection_item_vec: Vec<SongSelectionItem> = vec![];
         for song in songs {
+            // (level, displayed level, summary) of the charts
+            let mut charts: Vec<(u32, u32, Option<String>)> = vec![];
+            for level in &song.levels {
+                match song.get_chart_summary(*level) {
+                    Ok(summary) => charts.push((
+                        *level,
+                        summary.display_level(),
+                        Some(chart_summary_text(&summary)),
+                    )),
+                    Err(err) => {
+                        eprintln!("Failed to get chart summary: {}", err);
+                        charts.push((*level, *level, None));
+                    }
+                }
+            }
+            charts.sort_by_key(|x| (x.1, x.0));
+
             song_selection_item_vec.push(SongSelectionItem {
                 title: song.title.clone(),
                 artist: song.artist.clone(),
                 cover_img_texture: texture_creator
                     .load_texture(song.cover_image_filename.clone())
                     .expect("failed to load cover image"),
-                levels: song.levels.clone(),
+                levels: charts.iter().map(|x| x.0).collect(),
+                level_range_text: level_range_text(
+                    &charts.iter().map(|x| x.1).collect::<Vec<u32>>(),
+                ),
+                chart_summary_texts: charts.into_iter().map(|x| x.2).collect(),
             });
         }
         song_selection_item_vec
@@ -111,6 +138,10 @@ pub(crate) fn select_song(
     let moving_distance = song_selection_item_interval; // moving distance of song selection item
     let mut moving_direction: MovingDirection = MovingDirection::Stop; // moving direction of song selection item
 
+    // the level of the selected song is selected after the song is selected
+    let mut selecting_level = false;
+    let mut selected_level_idx: usize = 0;
+
     let song_item_size_changing_speed = (selected_song_item_width - song_item_width) as f32
         / (moving_distance / moving_speed) as f32; // changing speed of song item size according to the moving speed
 
@@ -133,13 +164,14 @@ pub(crate) fn select_song(
 
         // process janggu input
         janggu_state.update(common_context.read_janggu_state(), tick as i128);
+        let mut hit_both = false;
         if (janggu_state.궁채.is_keydown_now
             && matches!(janggu_state.궁채.face, Some(JangguFace::궁편)))
             && (janggu_state.열채.is_keydown_now
                 && matches!(janggu_state.열채.face, Some(JangguFace::열편)))
         {
             if moving_direction == MovingDirection::Stop {
-                break 'running;
+                hit_both = true;
             }
         } else if (janggu_state.궁채.is_keydown_now
             && matches!(janggu_state.궁채.face, Some(JangguFace::열편)))
@@ -170,25 +202,54 @@ pub(crate) fn select_song(
         }
 
         // to detect delay for hitting both side
+        let selected_song_levels = &song_selection_items[selecetd_song_item_idx as usize].levels;
         if hit_left && hit_right {
             // detect hitting both side
-            break 'running;
+            hit_both = true;
+            hit_left = false;
+            hit_right = false;
         } else if hit_left {
             if last_left_hit_time.elapsed() > Duration::from_millis(hit_both_side_time_delay) {
-                // after the limit, regard as going to left song
-                moving_direction = MovingDirection::Left;
-                last_key_press_time = Instant::now();
+                if selecting_level {
+                    // harder level
+                    selected_level_idx =
+                        (selected_level_idx + 1).min(selected_song_levels.len() - 1);
+                } else {
+                    // after the limit, regard as going to left song
+                    moving_direction = MovingDirection::Left;
+                    last_key_press_time = Instant::now();
+                }
                 hit_left = false;
             }
         } else if hit_right {
             if last_right_hit_time.elapsed() > Duration::from_millis(hit_both_side_time_delay) {
-                // after the limit, regard as going to right song
-                moving_direction = MovingDirection::Right;
-                last_key_press_time = Instant::now();
+                if selecting_level {
+                    if selected_level_idx == 0 {
+                        // going below the easiest level goes back to the song selection
+                        selecting_level = false;
+                    } else {
+                        // easier level
+                        selected_level_idx -= 1;
+                    }
+                } else {
+                    // after the limit, regard as going to right song
+                    moving_direction = MovingDirection::Right;
+                    last_key_press_time = Instant::now();
+                }
                 hit_right = false;
             }
         }
 
+        if hit_both {
+            if selecting_level || selected_song_levels.len() == 1 {
+                break 'running;
+            }
+
+            // the song has several charts, so the level is selected next
+            selecting_level = true;
+            selected_level_idx = 0;
+        }
+
         let elapsed_time = last_key_press_time.elapsed().as_millis() as f32;
         if moving_direction == MovingDirection::Left {
             // if user press right key, then song items move to right for specific distance
@@ -314,6 +375,8 @@ pub(crate) fn select_song(
                 (title_font_size as f32 * (item_rect.w as f32 / song_item_width as f32)) as u16;
             let artist_font_size =
                 (artist_font_size as f32 * (item_rect.w as f32 / song_item_width as f32)) as u16;
+            let summary_font_size =
+                (summary_font_size as f32 * (item_rect.w as f32 / song_item_width as f32)) as u16;
 
             // draw title font
             let title_str_center_x = item_center_x;
@@ -363,6 +426,45 @@ pub(crate) fn select_song(
                 .canvas
                 .copy(&texture, None, artist_str_rect)
                 .expect("failed to render artist texture");
+
+            // draw the level range of the selected song, or the chart summary of the selected level
+            if moving_direction == MovingDirection::Stop
+                && i == (leftmost_item_idx + right_most_item_idx) / 2
+            {
+                let song_selection_item = &song_selection_items[real_song_selection_idx as usize];
+                let chart_summary_text = if selecting_level {
+                    let summary_text = match &song_selection_item.chart_summary_texts
+                        [selected_level_idx]
+                    {
+                        Some(summary_text) => summary_text.clone(),
+                        None => format!("Lv.{}", song_selection_item.levels[selected_level_idx]),
+                    };
+                    format!("< {} >", summary_text)
+                } else {
+                    song_selection_item.level_range_text.clone()
+                };
+                let summary_str_center_y =
+                    artist_str_center_y + (artist_str_center_y - title_str_center_y) * 3 / 2;
+                let texture = create_font_texture(
+                    &texture_creator,
+                    &font,
+                    &chart_summary_text,
+                    summary_font_size,
+                    0,
+                    SELECT_SONG_FONT_COLOR,
+                    None,
+                )
+                .unwrap();
+                let texture_query = texture.query();
+                let mut summary_str_rect: Rect =
+                    Rect::new(-1, -1, texture_query.width, texture_query.height);
+                set_center_x_of_rect(&mut summary_str_rect, item_center_x);
+                set_center_y_of_rect(&mut summary_str_rect, summary_str_center_y);
+                common_context
+                    .canvas
+                    .copy(&texture, None, summary_str_rect)
+                    .expect("failed to render chart summary texture");
+            }
         }
 
         // drawing common
@@ -371,14 +473,60 @@ pub(crate) fn select_song(
         common_context.canvas.present();
     }
 
+    // selected_level_idx is 0 (the easiest level) if the level is not selected
     SongSelectionResult {
-        selected_level: songs[selecetd_song_item_idx as usize]
-            .get_chart_levels()
-            .unwrap()[0],
+        selected_level: song_selection_items[selecetd_song_item_idx as usize].levels
+            [selected_level_idx],
         selected_song: songs[selecetd_song_item_idx as usize].clone(),
     }
 }
 
+/// Creates the text of the range of the displayed levels (e.g. `Lv.1~5 | 채보 3개`)
+fn level_range_text(display_levels: &[u32]) -> String {
+    let min_level = display_levels.iter().min().copied().unwrap_or_default();
+    let max_level = display_levels.iter().max().copied().unwrap_or_default();
+
+    if min_level == max_level {
+        format!("Lv.{} | 채보 {}개", min_level, display_levels.len())
+    } else {
+        format!(
+            "Lv.{}~{} | 채보 {}개",
+            min_level,
+            max_level,
+            display_levels.len()
+        )
+    }
+}
+
+/// Creates the text of chart summary (e.g. `보통 Lv.3 | 노트 320 | 2:31 | 최대 8/초 | 채보 Team Overchaos`)
+fn chart_summary_text(summary: &ChartSummary) -> String {
+    let mut texts = vec![];
+    if let Some(difficulty_name) = &summary.metadata.difficulty_name {
+        texts.push(format!(
+            "{} Lv.{}",
+            difficulty_name,
+            summary.display_level()
+        ));
+    } else {
+        texts.push(format!("Lv.{}", summary.display_level()));
+    }
+    texts.push(format!(
+        "노트 {}",
+        summary.note_count + summary.hat_count + summary.long_note_count
+    ));
+    texts.push(format!(
+        "{}:{:02}",
+        summary.duration_in_ms / 60000,
+        summary.duration_in_ms / 1000 % 60
+    ));
+    texts.push(format!("최대 {}/초", summary.peak_density));
+    if let Some(charter) = &summary.metadata.charter {
+        texts.push(format!("채보 {}", charter));
+    }
+
+    texts.join(" | ")
+}
+
 pub(crate) fn set_center_x_of_rect(rect: &mut Rect, x: i32) {
     rect.set_x(x - rect.w / 2);
 }
@@ -6,7 +6,22 @@ use super::{
 };
 
 pub(crate) fn start_game(common_context: &mut GameCommonContext) {
-    let songs = GameSong::get_songs();
+    let loaded = GameSong::get_songs(&common_context.music_roots);
+    // broken songs are skipped
+    for (path, err) in &loaded.failures {
+        eprintln!("Skipping song {}: {}", path.display(), err);
+    }
+    for path in &loaded.duplicates {
+        eprintln!(
+            "Skipping song {}: the song is already loaded from another directory",
+            path.display()
+        );
+    }
+    let songs = loaded.songs;
+    if songs.is_empty() {
+        eprintln!("There's no playable song");
+        return;
+    }
     let mut total_stages = 0;
 
     // TO-DO: do authentication here
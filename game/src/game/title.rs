@@ -2,7 +2,10 @@ use std::time::Instant;
 use std::{path::Path, time::Duration};
 
 use num_rational::Rational64;
-use sdl2::{image::LoadTexture, pixels::Color, rect::Rect, render::Canvas, video::Window};
+use sdl2::{
+    event::Event, image::LoadTexture, keyboard::Keycode, pixels::Color, rect::Rect, render::Canvas,
+    video::Window,
+};
 
 use crate::constants::DEFAULT_FONT_COLOR;
 use crate::constants::DEFAULT_FONT_PATH as FONT_PATH;
@@ -117,6 +120,8 @@ fn render_text(common_context: &mut GameCommonContext) {
 pub(crate) enum TitleResult {
     Exit,
     StartGame,
+    /// F2 key is pressed to open the operator menu
+    OperatorMenu,
 }
 
 pub(crate) fn render_title(common_context: &mut GameCommonContext) -> TitleResult {
@@ -141,6 +146,13 @@ pub(crate) fn render_title(common_context: &mut GameCommonContext) -> TitleResul
             if event_loop_common(&event) {
                 return TitleResult::Exit;
             }
+            if let Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                ..
+            } = event
+            {
+                return TitleResult::OperatorMenu;
+            }
         }
 
         janggu_state.update(
@@ -9,6 +9,7 @@ use bidrum_data_struct_lib::{
     song::GameChart,
 };
 use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use num_rational::Rational64;
 use sdl2::{image::LoadTexture, rect::Rect, render::Texture};
 
 use crate::constants::DEFAULT_AUDIO_PATH as AUDIO_PATH;
@@ -22,6 +23,7 @@ use crate::game::{
         chart_player_ui::{displayed_song_note::DisplayedSongNote, ChartPlayerUI},
         effect_sound_player::EffectSoundPlayer,
         janggu_state_with_tick::JangguStateWithTick,
+        tick_of_janggu_event,
     },
 };
 
@@ -216,10 +218,15 @@ fn display_tryitout_notes(
     let mut judged_all_at = None;
     let tryitout_tutorial_started_at = Instant::now();
 
-    let mut chart_player = ChartPlayer::new(chart.clone(), &texture_creator);
+    let mut chart_player =
+        ChartPlayer::new(chart.clone(), &texture_creator, common_context.calibration);
+    let tempo_map = chart.tempo_map();
 
     let mut janggu_state = JangguStateWithTick::new();
-    janggu_state.update(common_context.read_janggu_state(), 0);
+    // hits before the tutorial play are not judged
+    common_context.read_janggu_events();
+    // tick of the last judge, which never goes backward
+    let mut judged_tick: i128 = 0;
 
     loop {
         for event in common_context.event_pump.poll_iter() {
@@ -235,18 +242,33 @@ fn display_tryitout_notes(
             return;
         }
 
-        // Update janggu input state
+        // Update janggu input state with the events, and judge the hits at their ticks
         let tick = tryitout_tutorial_started_at.elapsed().as_millis() as u64;
-        janggu_state.update(common_context.read_janggu_state(), tick as i128);
+        let now = Instant::now();
+        for event in common_context.read_janggu_events() {
+            let event_tick = tick_of_janggu_event(&event, now, tick.into(), judged_tick);
+            let hit = janggu_state.update_with_event(&event, event_tick);
+            effect_sound_player.play_janggu_sound(&janggu_state, &mut common_context.audio_manager);
+            chart_player.judge(
+                &janggu_state,
+                hit,
+                common_context.hat.spinning(),
+                event_tick,
+            );
+            judged_tick = event_tick;
+        }
 
         // Clear canvas
         common_context.canvas.clear();
 
-        // Play effect sound
-        effect_sound_player.play_janggu_sound(&janggu_state, &mut common_context.audio_manager);
-
-        // Judge and display UI
-        chart_player.judge(&janggu_state, common_context.hat.spinning(), tick.into());
+        // Judge the misses and display UI
+        chart_player.judge(
+            &janggu_state,
+            None,
+            common_context.hat.spinning(),
+            tick.into(),
+        );
+        judged_tick = tick.into();
         chart_player.draw(
             tick.into(),
             &mut common_context.canvas,
@@ -259,8 +281,8 @@ fn display_tryitout_notes(
             [chart.left_face.clone(), chart.right_face.clone()]
                 .concat()
                 .iter()
-                .filter(|x| (x.timing_in_ms(chart.bpm, chart.delay) as i64).sub(tick as i64) > -800)
-                .map(|x| x.get_position(chart.bpm, chart.delay, 120, tick))
+                .filter(|x| (x.timing_in_ms(&tempo_map) as i64).sub(tick as i64) > -800)
+                .map(|x| x.get_position(&tempo_map, Rational64::from_integer(120), tick))
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
         {
             if min_note_position > 1.5 {
@@ -2,6 +2,10 @@ mod constants;
 mod controller_wrapper;
 mod game;
 
+use std::path::PathBuf;
+
+use bidrum_controller_lib::serial::{discovery, ProtocolMode};
+use bidrum_data_struct_lib::calibration::{CalibrationProfile, DEFAULT_CALIBRATION_PROFILE_PATH};
 use clap::Parser;
 use controller_wrapper::ControllerWrapper;
 use game::init::{init_game, InitGameOptions};
@@ -10,8 +14,15 @@ use game::init::{init_game, InitGameOptions};
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Port of janggu controller
-    #[arg(short, long)]
+    /// (default: found among the USB serial ports, or the keyboard if not found)
+    #[arg(short, long, conflicts_with = "keyboard")]
     controller_port: Option<String>,
+    /// Uses the keyboard (d, f, j, k) even if the janggu controller is found
+    #[arg(long)]
+    keyboard: bool,
+    /// Prints the serial ports probed for the janggu controller and exits
+    #[arg(long)]
+    list_controllers: bool,
     /// Window width (default value: width of current display mode)
     #[arg(long)]
     window_width: Option<u32>,
@@ -24,6 +35,22 @@ struct Args {
     /// Enables vsync or not? (Default: enabled in macos, disabled otherwise)
     #[arg(long)]
     vsync: Option<bool>,
+    /// Music library directory, which can be given several times
+    /// (songs in the former directory take precedence)
+    #[arg(long = "music-dir", default_value = "music")]
+    music_dirs: Vec<PathBuf>,
+    /// Calibration profile measured by beep-boop of chart-recorder,
+    /// which keeps the offsets set in the operator menu (F2 in the title)
+    #[arg(long, default_value = DEFAULT_CALIBRATION_PROFILE_PATH)]
+    calibration_profile: PathBuf,
+    /// Judge offset in milliseconds, which is positive if the hits come late (e.g. slow speakers)
+    /// (overrides the calibration profile)
+    #[arg(long, allow_negative_numbers = true)]
+    judge_offset: Option<i64>,
+    /// Visual offset in milliseconds, which is positive if the screen comes late (e.g. slow TV)
+    /// (overrides the calibration profile)
+    #[arg(long, allow_negative_numbers = true)]
+    visual_offset: Option<i64>,
     /// Price
     #[cfg(not(feature = "uncommercial"))]
     #[arg(long, default_value_t = 2)]
@@ -44,12 +71,65 @@ macro_rules! price {
     };
 }
 
+/// Prints the probed serial ports
+fn list_controllers() {
+    let controllers = discovery::find_controllers();
+    if controllers.is_empty() {
+        println!("No USB serial ports found");
+    }
+
+    for controller in controllers {
+        let usb_ids = match controller.usb_ids {
+            Some((vid, pid)) => format!("{:04x}:{:04x}", vid, pid),
+            None => "-".to_string(),
+        };
+        let description = match &controller.probe {
+            Ok(ProtocolMode::Framed(Some(info))) => format!(
+                "firmware {}.{}, capabilities {:#04x}",
+                info.firmware_version.0, info.firmware_version.1, info.capabilities
+            ),
+            Ok(ProtocolMode::Framed(None)) => "no answer to the handshake".to_string(),
+            Ok(ProtocolMode::Legacy) => "legacy protocol".to_string(),
+            Err(reason) => reason.clone(),
+        };
+        println!(
+            "{} {} [{}] {}",
+            if controller.is_janggu_controller() {
+                "*"
+            } else {
+                " "
+            },
+            controller.port_name,
+            usb_ids,
+            description
+        );
+    }
+}
+
 fn main() {
     if cfg!(feature = "uncommercial") {
         println!("This is uncommercial version, only free play is available.");
     }
 
     let args = Args::parse();
+    if args.list_controllers {
+        list_controllers();
+        return;
+    }
+
+    // the broken profile is overwritten by calibrating again in the operator menu
+    let mut calibration = CalibrationProfile::load_or_default(&args.calibration_profile)
+        .unwrap_or_else(|err| {
+            eprintln!("{}, so the offsets are not applied", err);
+            CalibrationProfile::default()
+        });
+    if let Some(judge_offset) = args.judge_offset {
+        calibration.audio_offset = judge_offset;
+    }
+    if let Some(visual_offset) = args.visual_offset {
+        calibration.visual_offset = visual_offset;
+    }
+
     let options = InitGameOptions {
         fullscreen: !args.windowed,
         height: args.window_height,
@@ -60,11 +140,28 @@ fn main() {
             false
         }),
         price: price!(args),
+        music_roots: args.music_dirs,
+        calibration: calibration,
+        calibration_profile_path: args.calibration_profile,
     };
 
-    let controller_wrapper = match args.controller_port {
-        Some(controller_port) => ControllerWrapper::serial(controller_port),
-        _ => ControllerWrapper::keyboard(),
+    let controller_port = if args.keyboard {
+        None
+    } else {
+        args.controller_port.or_else(|| {
+            println!("Finding janggu controller");
+            discovery::find_controller()
+        })
+    };
+    let controller_wrapper = match controller_port {
+        Some(controller_port) => {
+            println!("Using janggu controller at {}", controller_port);
+            ControllerWrapper::serial(controller_port)
+        }
+        _ => {
+            println!("Using keyboard as janggu controller");
+            ControllerWrapper::keyboard()
+        }
     };
     init_game(controller_wrapper, options);
 }